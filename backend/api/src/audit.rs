@@ -0,0 +1,303 @@
+use crate::auth::Identity;
+use crate::projects::{is_project_scoped, Project};
+use crate::routes::api_path;
+use axum::{
+    body::{to_bytes, Body},
+    extract::{MatchedPath, RawPathParams, Request},
+    http::{HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Header a request id is read from and echoed back in
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Largest response body read back to find the id of a created entity
+const MAX_CREATED_BODY: usize = 1024 * 1024;
+
+/// Id of the request, taken from `x-request-id` or generated
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RequestId(pub String);
+
+/// Tag every request with a request id and echo it in the response
+pub async fn request_id(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|value| !value.is_empty() && value.len() <= 128)
+        .map_or_else(|| Uuid::new_v4().to_string(), String::from);
+
+    request.extensions_mut().insert(RequestId(id.clone()));
+    let mut response = next.run(request).await;
+    if let Ok(value) = HeaderValue::from_str(&id) {
+        response.headers_mut().insert(REQUEST_ID_HEADER, value);
+    }
+    response
+}
+
+/// What a mutating route acts on
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AuditTarget {
+    pub action: &'static str,
+    pub entity_type: &'static str,
+    /// Table and key column the entity's row is read from, if it has one
+    pub table: Option<(&'static str, &'static str)>,
+}
+
+/// Audit target of `method` on the route `path`, relative to `/api`; None for reads
+pub fn audit_target(method: &Method, path: &str) -> Option<AuditTarget> {
+    let path = api_path(path);
+
+    let action = match *method {
+        Method::POST if path.ends_with("/run") => "run",
+        Method::POST if path.ends_with("/rollback") => "update",
+        Method::POST => "create",
+        Method::PUT | Method::PATCH => "update",
+        Method::DELETE => "delete",
+        _ => return None,
+    };
+    let (entity_type, table) = match path.trim_start_matches('/').split('/').next()? {
+        "runs" | "test-runs" => ("test_run", Some(("test_runs", "id"))),
+        "definitions" | "test-definitions" => ("test_definition", Some(("test_definitions", "id"))),
+        "executors" | "test-executors" => ("test_executor", Some(("test_executors", "id"))),
+        "suites" | "test-suites" => ("test_suite", Some(("test_suites", "id"))),
+        "schedules" => ("schedule", Some(("test_schedules", "id"))),
+        "tokens" => ("api_token", Some(("api_tokens", "id"))),
+        "roles" => ("role_assignment", Some(("role_assignments", "subject"))),
+        "projects" if path.contains("/members") => ("project_member", None),
+        "projects" => ("project", Some(("projects", "slug"))),
+        "k8s" => ("k8s_job", None),
+        _ => return None,
+    };
+
+    Some(AuditTarget {
+        action,
+        entity_type,
+        table,
+    })
+}
+
+/// Record every POST, PUT, PATCH and DELETE in `audit_events`
+///
+/// The entity's row is read before and after the request, so the event shows
+/// what changed. Failing to write the event is logged but does not fail the request.
+pub async fn audit(
+    Extension(pool): Extension<PgPool>,
+    Extension(identity): Extension<Identity>,
+    Extension(RequestId(request_id)): Extension<RequestId>,
+    matched_path: MatchedPath,
+    params: RawPathParams,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(target) = audit_target(request.method(), matched_path.as_str()) else {
+        return next.run(request).await;
+    };
+    // Runs leave the definition or suite they were started from unchanged
+    let table = target.table.filter(|_| target.action != "run");
+    // A row is keyed by the first path parameter alone; the version of a rollback only qualifies it
+    let path_id = match table {
+        Some(_) => params.iter().next().map(|(_, value)| value.to_string()),
+        None => Some(
+            params
+                .iter()
+                .map(|(_, value)| value)
+                .collect::<Vec<_>>()
+                .join("/"),
+        ),
+    }
+    .filter(|id| !id.is_empty());
+    // A deleted project is gone by the time the event is written
+    let project_id = request
+        .extensions()
+        .get::<Project>()
+        .filter(|_| is_project_scoped(matched_path.as_str()))
+        .filter(|_| !(target.entity_type == "project" && target.action == "delete"))
+        .map(|project| project.id);
+
+    let before = match (table, &path_id) {
+        (Some(table), Some(id)) => entity_row(&pool, table, id).await,
+        _ => None,
+    };
+
+    let mut response = next.run(request).await;
+    let status = response.status();
+
+    let entity_id = match path_id {
+        Some(id) => Some(id),
+        None if status.is_success() => {
+            let key = table.map_or("id", |(_, key)| key);
+            let (id, body) = created_id(response, key).await;
+            response = body;
+            id
+        }
+        None => None,
+    };
+    let after = match (table, &entity_id) {
+        (Some(table), Some(id)) if status.is_success() && target.action != "delete" => {
+            entity_row(&pool, table, id).await
+        }
+        _ => None,
+    };
+    let changes = (status.is_success() && table.is_some()).then(|| changes(&before, &after));
+
+    if let Err(e) = sqlx::query(
+        "INSERT INTO audit_events (id, actor, action, entity_type, entity_id, before, after, changes, request_id, status_code, project_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)",
+    )
+    .bind(Uuid::new_v4())
+    .bind(identity.actor())
+    .bind(target.action)
+    .bind(target.entity_type)
+    .bind(&entity_id)
+    .bind(&before)
+    .bind(&after)
+    .bind(&changes)
+    .bind(&request_id)
+    .bind(i32::from(status.as_u16()))
+    .bind(project_id)
+    .execute(&pool)
+    .await
+    {
+        tracing::error!(
+            "Failed to record audit event for {} {:?}: {}",
+            target.entity_type,
+            entity_id,
+            e
+        );
+    }
+
+    response
+}
+
+/// Current row of an entity as JSON, leaving out token hashes and search vectors
+async fn entity_row(pool: &PgPool, (table, key): (&str, &str), id: &str) -> Option<Value> {
+    match sqlx::query_scalar::<_, Value>(&format!(
+        "SELECT to_jsonb(t) - 'token_hash' - 'search_vector' FROM {table} t WHERE {key}::text = $1"
+    ))
+    .bind(id)
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(row) => row,
+        Err(e) => {
+            tracing::error!("Failed to read {} {} for auditing: {}", table, id, e);
+            None
+        }
+    }
+}
+
+/// Field `key` in the JSON body of a created entity, and the response rebuilt around the body
+async fn created_id(response: Response, key: &str) -> (Option<String>, Response) {
+    let (parts, body) = response.into_parts();
+    let bytes = match to_bytes(body, MAX_CREATED_BODY).await {
+        Ok(bytes) => bytes,
+        Err(e) => {
+            tracing::error!("Failed to read response body for auditing: {}", e);
+            let mut response = Response::from_parts(parts, Body::empty());
+            *response.status_mut() = StatusCode::INTERNAL_SERVER_ERROR;
+            return (None, response);
+        }
+    };
+    let id = serde_json::from_slice::<Value>(&bytes)
+        .ok()
+        .and_then(|body| match body.get(key)? {
+            Value::String(id) => Some(id.clone()),
+            id => Some(id.to_string()),
+        });
+
+    (id, Response::from_parts(parts, Body::from(bytes)))
+}
+
+/// Fields that differ between two rows, as `{field: {before, after}}`
+///
+/// A missing row counts as having no fields, so a creation lists every field
+/// under `after` and a deletion every field under `before`.
+pub fn changes(before: &Option<Value>, after: &Option<Value>) -> Value {
+    let empty = Map::new();
+    let before = before.as_ref().and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.as_ref().and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        let old = before.get(key).unwrap_or(&Value::Null);
+        let new = after.get(key).unwrap_or(&Value::Null);
+        if old != new && !changes.contains_key(key) {
+            changes.insert(
+                key.clone(),
+                serde_json::json!({ "before": old, "after": new }),
+            );
+        }
+    }
+    Value::Object(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_audit_target() {
+        assert_eq!(audit_target(&Method::GET, "/test-definitions/:id"), None);
+
+        let update = audit_target(&Method::PUT, "/api/test-definitions/:id").unwrap();
+        assert_eq!(update.action, "update");
+        assert_eq!(update.entity_type, "test_definition");
+        assert_eq!(update.table, Some(("test_definitions", "id")));
+        let patch = audit_target(&Method::PATCH, "/api/v1/suites/:id").unwrap();
+        assert_eq!((patch.action, patch.entity_type), ("update", "test_suite"));
+        let rollback = audit_target(
+            &Method::POST,
+            "/api/v1/definitions/:id/versions/:version/rollback",
+        )
+        .unwrap();
+        assert_eq!(rollback.action, "update");
+        assert_eq!(rollback.table, Some(("test_definitions", "id")));
+
+        let run = audit_target(&Method::POST, "/test-suites/:id/run").unwrap();
+        assert_eq!((run.action, run.entity_type), ("run", "test_suite"));
+
+        let v1 = audit_target(&Method::DELETE, "/api/v1/executors/:id").unwrap();
+        assert_eq!((v1.action, v1.entity_type), ("delete", "test_executor"));
+        assert_eq!(v1.table, Some(("test_executors", "id")));
+
+        let kill = audit_target(&Method::DELETE, "/k8s/jobs/:job_name").unwrap();
+        assert_eq!((kill.action, kill.entity_type), ("delete", "k8s_job"));
+        assert_eq!(kill.table, None);
+
+        let role = audit_target(&Method::PUT, "/roles/:subject").unwrap();
+        assert_eq!(role.table, Some(("role_assignments", "subject")));
+
+        let member = audit_target(&Method::PUT, "/projects/:project/members/:subject").unwrap();
+        assert_eq!((member.entity_type, member.table), ("project_member", None));
+    }
+
+    #[test]
+    fn test_changes() {
+        let before = Some(json!({"name": "api", "image": "node:18", "labels": ["a"]}));
+        let after = Some(json!({"name": "api", "image": "node:20", "labels": []}));
+        assert_eq!(
+            changes(&before, &after),
+            json!({
+                "image": {"before": "node:18", "after": "node:20"},
+                "labels": {"before": ["a"], "after": []}
+            })
+        );
+
+        assert_eq!(
+            changes(&before, &None)["name"],
+            json!({"before": "api", "after": null})
+        );
+        // Null fields of created rows are not changes
+        assert_eq!(
+            changes(&None, &Some(json!({"name": "api", "description": null}))),
+            json!({"name": {"before": null, "after": "api"}})
+        );
+        assert_eq!(changes(&None, &None), json!({}));
+    }
+}
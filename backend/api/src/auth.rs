@@ -0,0 +1,455 @@
+use crate::authz::{token_subject, Role};
+use crate::error::ApiError;
+use crate::oidc::{OidcConfig, OidcUser, OidcVerifier};
+use axum::{
+    extract::Request,
+    http::{header, HeaderMap},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use k8s_openapi::api::authentication::v1::{TokenReview, TokenReviewSpec};
+use kube::{
+    api::{Api, PostParams},
+    Client,
+};
+use rand::RngCore;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+/// Audience the controller's projected service account token is issued for
+pub const DEFAULT_TOKEN_AUDIENCE: &str = "sparktest-api";
+
+/// Prefix of every API token, which tells them apart from controller tokens
+pub const API_TOKEN_PREFIX: &str = "spt_";
+
+/// How long a service account token accepted by a TokenReview is trusted
+const TOKEN_REVIEW_TTL: Duration = Duration::from_secs(60);
+
+/// What an API token may do, capping its role; each scope includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// Read-only requests
+    Read,
+    /// Creating, updating, deleting and running tests
+    Write,
+    /// Everything, including managing tokens and roles
+    Admin,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Read => "read",
+            Scope::Write => "write",
+            Scope::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "read" => Some(Scope::Read),
+            "write" => Some(Scope::Write),
+            "admin" => Some(Scope::Admin),
+            _ => None,
+        }
+    }
+}
+
+/// Who a request was made by
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Identity {
+    /// No credentials were presented
+    Anonymous,
+    /// The SparkTest controller, the only identity allowed to register CRD-origin runs
+    Controller,
+    /// A caller presenting an API token
+    Token {
+        id: Uuid,
+        name: String,
+        scopes: Vec<Scope>,
+        /// Role assigned to the token in `role_assignments`, if any
+        role: Option<Role>,
+    },
+    /// A user signed in through the OIDC issuer
+    User {
+        subject: String,
+        username: String,
+        groups: Vec<String>,
+        /// Highest role assigned to the user or their groups, or the default role
+        role: Option<Role>,
+    },
+}
+
+impl Identity {
+    /// Who created runs, definitions and suites are recorded as, if anyone
+    pub fn actor(&self) -> Option<String> {
+        match self {
+            Identity::Anonymous => None,
+            Identity::Controller => Some("controller".to_string()),
+            Identity::Token { name, .. } => Some(format!("token:{name}")),
+            Identity::User { username, .. } => Some(username.clone()),
+        }
+    }
+
+    /// Subjects roles can be assigned to for this identity
+    pub fn subjects(&self) -> Vec<String> {
+        match self {
+            Identity::Anonymous | Identity::Controller => Vec::new(),
+            Identity::Token { id, .. } => vec![token_subject(id)],
+            Identity::User {
+                username, groups, ..
+            } => std::iter::once(format!("user:{username}"))
+                .chain(groups.iter().map(|group| format!("group:{group}")))
+                .collect(),
+        }
+    }
+
+    /// This identity acting as `role` within a project
+    ///
+    /// A token's scopes still cap it.
+    pub fn in_project(self, role: Role) -> Self {
+        match self {
+            Identity::Token {
+                id, name, scopes, ..
+            } => Identity::Token {
+                id,
+                name,
+                scopes,
+                role: Some(role),
+            },
+            Identity::User {
+                subject,
+                username,
+                groups,
+                ..
+            } => Identity::User {
+                subject,
+                username,
+                groups,
+                role: Some(role),
+            },
+            identity => identity,
+        }
+    }
+}
+
+/// How bearer tokens are verified
+///
+/// API tokens are looked up in the `api_tokens` table, and tokens from the
+/// OIDC issuer are checked against its published keys. The controller either
+/// presents a shared token, mounted from the same Secret on both sides, or a
+/// projected service account token that is checked with a Kubernetes TokenReview.
+#[derive(Clone)]
+pub struct AuthConfig {
+    /// Reject requests without a valid token, except the health check
+    pub required: bool,
+    /// Role of requests without a token while authentication is optional
+    pub anonymous_role: Option<Role>,
+    pub controller_token: Option<String>,
+    /// `namespace:name` of the controller's service account
+    pub controller_service_account: Option<String>,
+    pub token_audience: String,
+    pub oidc: Option<Arc<OidcVerifier>>,
+    /// Tokens accepted by a TokenReview, with when they were reviewed
+    reviewed: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            required: false,
+            anonymous_role: Some(Role::Viewer),
+            controller_token: None,
+            controller_service_account: None,
+            token_audience: DEFAULT_TOKEN_AUDIENCE.to_string(),
+            oidc: None,
+            reviewed: Arc::default(),
+        }
+    }
+}
+
+impl AuthConfig {
+    /// Read `API_AUTH_REQUIRED`, `API_ANONYMOUS_ROLE`, `CONTROLLER_TOKEN`,
+    /// `CONTROLLER_SERVICE_ACCOUNT`, `CONTROLLER_TOKEN_AUDIENCE` and the `OIDC_*` settings
+    ///
+    /// `API_ANONYMOUS_ROLE=none` refuses anonymous requests; an unknown role falls back to viewer.
+    pub fn from_env() -> Self {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        let anonymous_role = match var("API_ANONYMOUS_ROLE").as_deref() {
+            None => Some(Role::Viewer),
+            Some("none") => None,
+            Some(role) => Role::parse(role).or_else(|| {
+                tracing::warn!("Unknown API_ANONYMOUS_ROLE {}, using viewer", role);
+                Some(Role::Viewer)
+            }),
+        };
+        Self {
+            required: var("API_AUTH_REQUIRED").is_some_and(|v| v == "true"),
+            anonymous_role,
+            controller_token: var("CONTROLLER_TOKEN"),
+            controller_service_account: var("CONTROLLER_SERVICE_ACCOUNT"),
+            token_audience: var("CONTROLLER_TOKEN_AUDIENCE")
+                .unwrap_or_else(|| DEFAULT_TOKEN_AUDIENCE.to_string()),
+            oidc: OidcConfig::from_env().map(|config| Arc::new(OidcVerifier::new(config))),
+            ..Self::default()
+        }
+    }
+
+    /// This configuration with anonymous requests acting as `role`, or refused for None
+    pub fn with_anonymous_role(self, role: Option<Role>) -> Self {
+        Self {
+            anonymous_role: role,
+            ..self
+        }
+    }
+
+    /// Whether any way of authenticating the controller is configured
+    pub fn controller_auth_enabled(&self) -> bool {
+        self.controller_token.is_some() || self.controller_service_account.is_some()
+    }
+
+    /// Identity a bearer token belongs to, or None if it is not valid
+    pub async fn verify(&self, token: &str, pool: &PgPool) -> Option<Identity> {
+        if token.starts_with(API_TOKEN_PREFIX) {
+            return verify_api_token(token, pool).await;
+        }
+
+        // Service account tokens are JWTs too, so the issuer decides who checks them
+        if let Some(oidc) = self.oidc.as_ref().filter(|oidc| oidc.claims_issuer(token)) {
+            return match oidc.verify(token).await {
+                Ok(user) => Some(user_identity(user, oidc.config.default_role, pool).await),
+                Err(e) => {
+                    tracing::warn!("Rejected OIDC token: {}", e);
+                    None
+                }
+            };
+        }
+
+        if let Some(expected) = &self.controller_token {
+            if constant_time_eq(token.as_bytes(), expected.as_bytes()) {
+                return Some(Identity::Controller);
+            }
+        }
+
+        if let Some(service_account) = &self.controller_service_account {
+            if self.review_token(token, service_account).await {
+                return Some(Identity::Controller);
+            }
+        }
+
+        None
+    }
+
+    /// Whether the API server vouches for `token` belonging to `service_account`
+    async fn review_token(&self, token: &str, service_account: &str) -> bool {
+        if let Some(reviewed_at) = self.reviewed.lock().unwrap().get(token) {
+            if reviewed_at.elapsed() < TOKEN_REVIEW_TTL {
+                return true;
+            }
+        }
+
+        let review = TokenReview {
+            spec: TokenReviewSpec {
+                token: Some(token.to_string()),
+                audiences: Some(vec![self.token_audience.clone()]),
+            },
+            ..Default::default()
+        };
+        let result = match Client::try_default().await {
+            Ok(client) => {
+                Api::<TokenReview>::all(client)
+                    .create(&PostParams::default(), &review)
+                    .await
+            }
+            Err(e) => Err(e),
+        };
+        let status = match result {
+            Ok(review) => review.status,
+            Err(e) => {
+                tracing::error!("TokenReview failed: {}", e);
+                return false;
+            }
+        };
+
+        let username = status
+            .as_ref()
+            .filter(|s| s.authenticated == Some(true))
+            .and_then(|s| s.user.as_ref())
+            .and_then(|u| u.username.as_deref());
+        let accepted = username == Some(&format!("system:serviceaccount:{service_account}"));
+
+        if accepted {
+            let mut reviewed = self.reviewed.lock().unwrap();
+            reviewed.retain(|_, reviewed_at| reviewed_at.elapsed() < TOKEN_REVIEW_TTL);
+            reviewed.insert(token.to_string(), Instant::now());
+        }
+        accepted
+    }
+}
+
+/// Look up an unrevoked, unexpired API token, recording that it was used
+async fn verify_api_token(token: &str, pool: &PgPool) -> Option<Identity> {
+    let row = sqlx::query_as::<_, (Uuid, String, Vec<String>)>(
+        "UPDATE api_tokens SET last_used_at = NOW() WHERE token_hash = $1 AND revoked_at IS NULL AND (expires_at IS NULL OR expires_at > NOW()) RETURNING id, name, scopes",
+    )
+    .bind(hash_token(token))
+    .fetch_optional(pool)
+    .await;
+
+    match row {
+        Ok(Some((id, name, scopes))) => Some(Identity::Token {
+            role: assigned_role(&[token_subject(&id)], pool).await,
+            id,
+            name,
+            scopes: scopes.iter().filter_map(|s| Scope::parse(s)).collect(),
+        }),
+        Ok(None) => None,
+        Err(e) => {
+            tracing::error!("Failed to look up API token: {}", e);
+            None
+        }
+    }
+}
+
+/// Highest role assigned to any of `subjects`, if any
+async fn assigned_role(subjects: &[String], pool: &PgPool) -> Option<Role> {
+    match sqlx::query_scalar::<_, String>(
+        "SELECT role FROM role_assignments WHERE subject = ANY($1)",
+    )
+    .bind(subjects)
+    .fetch_all(pool)
+    .await
+    {
+        Ok(roles) => roles.iter().filter_map(|r| Role::parse(r)).max(),
+        Err(e) => {
+            tracing::error!("Failed to look up roles of {:?}: {}", subjects, e);
+            None
+        }
+    }
+}
+
+/// Identity of an OIDC user, with the roles assigned to them and their groups
+async fn user_identity(user: OidcUser, default_role: Option<Role>, pool: &PgPool) -> Identity {
+    let subjects: Vec<String> = std::iter::once(format!("user:{}", user.username))
+        .chain(user.groups.iter().map(|group| format!("group:{group}")))
+        .collect();
+
+    Identity::User {
+        role: assigned_role(&subjects, pool).await.or(default_role),
+        subject: user.subject,
+        username: user.username,
+        groups: user.groups,
+    }
+}
+
+/// A new random API token
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    format!("{API_TOKEN_PREFIX}{}", to_hex(&bytes))
+}
+
+/// Hash stored in place of an API token
+///
+/// Tokens are 256 random bits, so a fast unsalted hash is enough.
+pub fn hash_token(token: &str) -> String {
+    to_hex(&Sha256::digest(token.as_bytes()))
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{b:02x}")).collect()
+}
+
+/// Resolve the caller's identity from the `Authorization` header
+///
+/// Requests without a token are anonymous unless authentication is
+/// required; an invalid token is always rejected.
+pub async fn authenticate(
+    Extension(auth): Extension<AuthConfig>,
+    Extension(pool): Extension<PgPool>,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let identity = match bearer_token(request.headers()) {
+        Some(token) => auth.verify(token, &pool).await.ok_or_else(|| {
+            tracing::warn!("Rejected request with invalid bearer token");
+            ApiError::unauthorized("Invalid bearer token")
+        })?,
+        None if auth.required => return Err(ApiError::unauthorized("Missing bearer token")),
+        None => Identity::Anonymous,
+    };
+
+    request.extensions_mut().insert(identity);
+    Ok(next.run(request).await)
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Compare without leaking the position of the first difference through timing
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_verify_controller_token() {
+        // Controller tokens never reach the database
+        let pool = PgPool::connect_lazy("postgresql://localhost/sparktest").unwrap();
+        let auth = AuthConfig {
+            controller_token: Some("s3cret".to_string()),
+            ..AuthConfig::default()
+        };
+
+        assert_eq!(
+            auth.verify("s3cret", &pool).await,
+            Some(Identity::Controller)
+        );
+        assert_eq!(auth.verify("s3cre", &pool).await, None);
+        assert_eq!(auth.verify("wrong!", &pool).await, None);
+        assert_eq!(AuthConfig::default().verify("s3cret", &pool).await, None);
+    }
+
+    #[test]
+    fn test_generate_and_hash_token() {
+        let token = generate_token();
+        assert!(token.starts_with(API_TOKEN_PREFIX));
+        assert_eq!(token.len(), API_TOKEN_PREFIX.len() + 64);
+        assert_ne!(token, generate_token());
+
+        assert_eq!(hash_token(&token), hash_token(&token));
+        assert_eq!(
+            hash_token("abc"),
+            "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad"
+        );
+    }
+
+    #[test]
+    fn test_bearer_token() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, "Basic abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), None);
+
+        headers.insert(header::AUTHORIZATION, "Bearer abc".parse().unwrap());
+        assert_eq!(bearer_token(&headers), Some("abc"));
+    }
+}
@@ -0,0 +1,263 @@
+use crate::auth::{AuthConfig, Identity, Scope};
+use crate::error::ApiError;
+use crate::routes::api_path;
+use axum::{
+    extract::{MatchedPath, Request},
+    http::Method,
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+
+/// What a caller may do; each role includes the ones before it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Role {
+    /// List and inspect runs, logs, definitions, suites and executors
+    Viewer,
+    /// Trigger runs of definitions and suites
+    Runner,
+    /// Create, update and delete definitions, suites, executors, schedules and runs
+    Editor,
+    /// Manage tokens, roles, projects and their members, read the audit log, and delete Kubernetes jobs directly
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Viewer => "viewer",
+            Role::Runner => "runner",
+            Role::Editor => "editor",
+            Role::Admin => "admin",
+        }
+    }
+
+    pub fn parse(s: &str) -> Option<Self> {
+        match s {
+            "viewer" => Some(Role::Viewer),
+            "runner" => Some(Role::Runner),
+            "editor" => Some(Role::Editor),
+            "admin" => Some(Role::Admin),
+            _ => None,
+        }
+    }
+
+    /// Highest role a token with `scope` can act as
+    pub fn for_scope(scope: Scope) -> Self {
+        match scope {
+            Scope::Read => Role::Viewer,
+            Scope::Write => Role::Editor,
+            Scope::Admin => Role::Admin,
+        }
+    }
+}
+
+/// Subject a role is assigned to in `role_assignments` for an API token
+///
+/// Users are assigned roles as `user:<username>` and groups as `group:<name>`.
+pub fn token_subject(id: &uuid::Uuid) -> String {
+    format!("token:{id}")
+}
+
+/// Role an identity acts as, if any
+///
+/// A token acts as its assigned role, capped by its scopes, or as the
+/// highest role its scopes allow when none is assigned. A user acts as the
+/// role resolved when they signed in. Requests without a token act as the
+/// configured anonymous role, viewer unless `API_ANONYMOUS_ROLE` says otherwise.
+pub fn effective_role(identity: &Identity, auth: &AuthConfig) -> Option<Role> {
+    match identity {
+        // Anonymous requests only get this far when authentication is optional
+        Identity::Anonymous => auth.anonymous_role,
+        // The controller reads definitions and suites and registers runs
+        Identity::Controller => Some(Role::Runner),
+        Identity::Token { scopes, role, .. } => {
+            let cap = scopes
+                .iter()
+                .map(|scope| Role::for_scope(*scope))
+                .max()
+                .unwrap_or(Role::Viewer);
+            Some(role.map_or(cap, |role| role.min(cap)))
+        }
+        Identity::User { role, .. } => *role,
+    }
+}
+
+/// Role needed for `method` on the route `path`, relative to `/api`
+pub fn required_role(method: &Method, path: &str) -> Role {
+    match (method, api_path(path)) {
+        (_, "/tokens" | "/tokens/:id" | "/roles" | "/roles/:subject" | "/audit") => Role::Admin,
+        (&Method::DELETE, "/k8s/jobs/:job_name") => Role::Admin,
+        (_, "/projects/:project/members" | "/projects/:project/members/:subject") => Role::Admin,
+        (&Method::POST | &Method::PUT | &Method::DELETE, "/projects" | "/projects/:project") => {
+            Role::Admin
+        }
+        (
+            &Method::POST,
+            "/runs"
+            | "/test-runs"
+            | "/definitions/:id/run"
+            | "/test-definitions/:id/run"
+            | "/suites/:id/run"
+            | "/test-suites/:id/run",
+        ) => Role::Runner,
+        (&Method::GET | &Method::HEAD, _) => Role::Viewer,
+        _ => Role::Editor,
+    }
+}
+
+/// Whether `identity` may call `method` on the route `path`
+pub fn is_allowed(identity: &Identity, auth: &AuthConfig, method: &Method, path: &str) -> bool {
+    effective_role(identity, auth).is_some_and(|role| role >= required_role(method, path))
+}
+
+/// Reject callers whose role is below what the matched route requires
+pub async fn authorize(
+    Extension(identity): Extension<Identity>,
+    Extension(auth): Extension<AuthConfig>,
+    matched_path: MatchedPath,
+    request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let required = required_role(request.method(), matched_path.as_str());
+    if !is_allowed(&identity, &auth, request.method(), matched_path.as_str()) {
+        tracing::warn!(
+            "Rejected {} {} from {:?}: needs {} role",
+            request.method(),
+            matched_path.as_str(),
+            identity,
+            required.as_str()
+        );
+        return Err(ApiError::forbidden(format!(
+            "Requires the {} role",
+            required.as_str()
+        )));
+    }
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_required_role() {
+        assert_eq!(required_role(&Method::GET, "/test-runs"), Role::Viewer);
+        assert_eq!(
+            required_role(&Method::GET, "/k8s/logs/:job_name"),
+            Role::Viewer
+        );
+        assert_eq!(required_role(&Method::POST, "/test-runs"), Role::Runner);
+        assert_eq!(
+            required_role(&Method::POST, "/api/test-suites/:id/run"),
+            Role::Runner
+        );
+        assert_eq!(
+            required_role(&Method::POST, "/api/v1/definitions/:id/run"),
+            Role::Runner
+        );
+        assert_eq!(
+            required_role(&Method::PUT, "/api/v1/definitions/:id"),
+            Role::Editor
+        );
+        assert_eq!(required_role(&Method::GET, "/api/v1/audit"), Role::Admin);
+        assert_eq!(
+            required_role(&Method::POST, "/test-definitions"),
+            Role::Editor
+        );
+        assert_eq!(
+            required_role(&Method::DELETE, "/test-executors/:id"),
+            Role::Editor
+        );
+        assert_eq!(
+            required_role(&Method::DELETE, "/k8s/jobs/:job_name"),
+            Role::Admin
+        );
+        assert_eq!(required_role(&Method::GET, "/tokens"), Role::Admin);
+        assert_eq!(required_role(&Method::GET, "/api/audit"), Role::Admin);
+        assert_eq!(required_role(&Method::GET, "/projects"), Role::Viewer);
+        assert_eq!(required_role(&Method::POST, "/projects"), Role::Admin);
+        assert_eq!(
+            required_role(&Method::GET, "/projects/:project/members"),
+            Role::Admin
+        );
+    }
+
+    #[test]
+    fn test_effective_role() {
+        let auth = AuthConfig::default();
+        let token = |scopes: Vec<Scope>, role: Option<Role>| Identity::Token {
+            id: Uuid::new_v4(),
+            name: "ci".to_string(),
+            scopes,
+            role,
+        };
+
+        assert_eq!(
+            effective_role(&token(vec![Scope::Read], None), &auth),
+            Some(Role::Viewer)
+        );
+        assert_eq!(
+            effective_role(&token(vec![Scope::Read, Scope::Write], None), &auth),
+            Some(Role::Editor)
+        );
+        assert_eq!(
+            effective_role(&token(vec![Scope::Admin], Some(Role::Runner)), &auth),
+            Some(Role::Runner)
+        );
+        // An assignment cannot lift a token above its scopes
+        assert_eq!(
+            effective_role(&token(vec![Scope::Read], Some(Role::Admin)), &auth),
+            Some(Role::Viewer)
+        );
+        assert_eq!(
+            effective_role(&Identity::Controller, &auth),
+            Some(Role::Runner)
+        );
+
+        let user = Identity::User {
+            subject: "user-1".to_string(),
+            username: "ada@example.com".to_string(),
+            groups: vec![],
+            role: None,
+        };
+        assert_eq!(effective_role(&user, &auth), None);
+        assert_eq!(
+            effective_role(&Identity::Anonymous, &auth),
+            Some(Role::Viewer)
+        );
+    }
+
+    #[test]
+    fn test_anonymous_requests() {
+        let auth = AuthConfig::default();
+        let anonymous = Identity::Anonymous;
+
+        assert!(is_allowed(&anonymous, &auth, &Method::GET, "/test-runs"));
+        assert!(!is_allowed(
+            &anonymous,
+            &auth,
+            &Method::DELETE,
+            "/k8s/jobs/:job_name"
+        ));
+        assert!(!is_allowed(&anonymous, &auth, &Method::POST, "/tokens"));
+        assert!(!is_allowed(
+            &anonymous,
+            &auth,
+            &Method::PUT,
+            "/api/v1/roles/:subject"
+        ));
+        assert!(!is_allowed(&anonymous, &auth, &Method::POST, "/test-runs"));
+
+        // Refusing them outright, or opting in to an open API
+        let closed = AuthConfig::default().with_anonymous_role(None);
+        assert!(!is_allowed(&anonymous, &closed, &Method::GET, "/test-runs"));
+        let open = AuthConfig::default().with_anonymous_role(Some(Role::Admin));
+        assert!(is_allowed(&anonymous, &open, &Method::POST, "/tokens"));
+    }
+}
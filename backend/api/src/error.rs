@@ -0,0 +1,228 @@
+use crate::audit::RequestId;
+use crate::responses::ErrorResponse;
+use axum::{
+    body::{to_bytes, Body},
+    extract::Request,
+    http::{header, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+use serde_json::Value;
+use std::fmt;
+
+/// Largest plain-text error body that is carried over into a JSON error
+const MAX_PLAIN_ERROR_BODY: usize = 64 * 1024;
+
+/// An error returned by the API, rendered as a JSON body
+///
+/// ```json
+/// {"code": "not_found", "message": "Test run 5f0c... not found", "details": null, "requestId": "..."}
+/// ```
+///
+/// The request id is filled in by [`json_errors`], which knows the request.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiError {
+    pub status: StatusCode,
+    /// Stable, machine-readable error code
+    pub code: &'static str,
+    pub message: String,
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    pub fn new(status: StatusCode, message: impl Into<String>) -> Self {
+        Self {
+            status,
+            code: code_for(status),
+            message: message.into(),
+            details: None,
+        }
+    }
+
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, message)
+    }
+
+    pub fn unauthorized(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::UNAUTHORIZED, message)
+    }
+
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, message)
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, message)
+    }
+
+    pub fn conflict(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::CONFLICT, message)
+    }
+
+    /// A failure on our side; the cause is logged by the caller, not returned
+    pub fn internal(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::INTERNAL_SERVER_ERROR, message)
+    }
+
+    /// The Kubernetes API could not be reached
+    pub fn kubernetes_unavailable(e: impl fmt::Display) -> Self {
+        tracing::error!("Failed to initialize Kubernetes client: {}", e);
+        Self::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Kubernetes client unavailable",
+        )
+    }
+
+    /// A Kubernetes API call failed; missing objects are reported as not found
+    pub fn kubernetes(message: impl Into<String>, e: anyhow::Error) -> Self {
+        let message = message.into();
+        let not_found = matches!(
+            e.downcast_ref::<kube::Error>(),
+            Some(kube::Error::Api(response)) if response.code == 404
+        );
+        if not_found {
+            return Self::not_found(message);
+        }
+
+        tracing::error!("{}: {:#}", message, e);
+        Self::new(StatusCode::BAD_GATEWAY, message).with_details(serde_json::json!({
+            "cause": format!("{e:#}")
+        }))
+    }
+
+    pub fn with_details(mut self, details: Value) -> Self {
+        self.details = Some(details);
+        self
+    }
+
+    fn body(&self, request_id: Option<&str>) -> ErrorResponse {
+        ErrorResponse {
+            code: self.code.to_string(),
+            message: self.message.clone(),
+            details: self.details.clone(),
+            request_id: request_id.map(str::to_string),
+        }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}): {}", self.status, self.code, self.message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let mut response = (self.status, axum::Json(self.body(None))).into_response();
+        // Kept so `json_errors` can render the body again with the request id
+        response.extensions_mut().insert(self);
+        response
+    }
+}
+
+/// Error code of an HTTP status
+fn code_for(status: StatusCode) -> &'static str {
+    match status {
+        StatusCode::BAD_REQUEST => "bad_request",
+        StatusCode::UNAUTHORIZED => "unauthorized",
+        StatusCode::FORBIDDEN => "forbidden",
+        StatusCode::NOT_FOUND => "not_found",
+        StatusCode::METHOD_NOT_ALLOWED => "method_not_allowed",
+        StatusCode::CONFLICT => "conflict",
+        StatusCode::PRECONDITION_FAILED => "precondition_failed",
+        StatusCode::PAYLOAD_TOO_LARGE => "payload_too_large",
+        StatusCode::UNSUPPORTED_MEDIA_TYPE => "unsupported_media_type",
+        StatusCode::UNPROCESSABLE_ENTITY => "unprocessable_entity",
+        StatusCode::BAD_GATEWAY => "bad_gateway",
+        StatusCode::SERVICE_UNAVAILABLE => "service_unavailable",
+        status if status.is_client_error() => "client_error",
+        _ => "internal",
+    }
+}
+
+/// Give every error response the JSON error body, with the request's id
+///
+/// Errors that are not [`ApiError`]s, such as rejected JSON bodies or
+/// unknown routes, keep their status and use their text as the message.
+pub async fn json_errors(request: Request, next: Next) -> Response {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .map(|RequestId(id)| id.clone());
+    let mut response = next.run(request).await;
+
+    if let Some(error) = response.extensions_mut().remove::<ApiError>() {
+        return with_body(response, error.body(request_id.as_deref()));
+    }
+
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .is_some_and(|value| value.as_bytes().starts_with(b"application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let text = to_bytes(body, MAX_PLAIN_ERROR_BODY)
+        .await
+        .map(|bytes| String::from_utf8_lossy(&bytes).trim().to_string())
+        .unwrap_or_default();
+    let message = if text.is_empty() {
+        status.canonical_reason().unwrap_or("Error").to_string()
+    } else {
+        text
+    };
+
+    let error = ApiError::new(status, message);
+    with_body(
+        Response::from_parts(parts, Body::empty()),
+        error.body(request_id.as_deref()),
+    )
+}
+
+fn with_body(response: Response, body: ErrorResponse) -> Response {
+    let (mut parts, _) = response.into_parts();
+    parts.headers.insert(
+        header::CONTENT_TYPE,
+        HeaderValue::from_static("application/json"),
+    );
+    parts.headers.remove(header::CONTENT_LENGTH);
+    Response::from_parts(
+        parts,
+        Body::from(serde_json::to_vec(&body).unwrap_or_default()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_error_body() {
+        let error = ApiError::not_found("Test run 1 not found")
+            .with_details(serde_json::json!({ "id": "1" }));
+        assert_eq!(error.code, "not_found");
+        assert_eq!(
+            serde_json::to_value(error.body(Some("req-1"))).unwrap(),
+            serde_json::json!({
+                "code": "not_found",
+                "message": "Test run 1 not found",
+                "details": { "id": "1" },
+                "requestId": "req-1"
+            })
+        );
+
+        let response = ApiError::conflict("Slug taken").into_response();
+        assert_eq!(response.status(), StatusCode::CONFLICT);
+        assert!(response.extensions().get::<ApiError>().is_some());
+    }
+
+    #[test]
+    fn test_code_for() {
+        assert_eq!(code_for(StatusCode::BAD_GATEWAY), "bad_gateway");
+        assert_eq!(code_for(StatusCode::IM_A_TEAPOT), "client_error");
+        assert_eq!(code_for(StatusCode::INTERNAL_SERVER_ERROR), "internal");
+    }
+}
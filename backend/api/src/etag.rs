@@ -0,0 +1,100 @@
+use crate::error::ApiError;
+use axum::{
+    async_trait,
+    extract::FromRequestParts,
+    http::{header, request::Parts, HeaderValue, StatusCode},
+    response::{IntoResponseParts, ResponseParts},
+};
+use std::convert::Infallible;
+
+/// Version of an entity, sent as its `ETag` header
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ETag(pub i32);
+
+impl IntoResponseParts for ETag {
+    type Error = Infallible;
+
+    fn into_response_parts(self, mut parts: ResponseParts) -> Result<ResponseParts, Infallible> {
+        if let Ok(value) = HeaderValue::from_str(&format!("\"{}\"", self.0)) {
+            parts.headers_mut().insert(header::ETAG, value);
+        }
+        Ok(parts)
+    }
+}
+
+/// Versions an update expects from the request's `If-Match` header
+///
+/// None when the header is absent or `*`, so the update applies to any version.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct IfMatch(pub Option<Vec<i32>>);
+
+impl IfMatch {
+    /// Parse a header value such as `"3"` or `"3", "4"`; tags that are not
+    /// versions of ours are dropped, so they never match
+    pub fn parse(value: &str) -> Self {
+        if value.trim() == "*" {
+            return Self(None);
+        }
+        let versions = value
+            .split(',')
+            .filter_map(|tag| {
+                let tag = tag.trim();
+                let tag = tag.strip_prefix("W/").unwrap_or(tag);
+                tag.strip_prefix('"')?.strip_suffix('"')?.parse().ok()
+            })
+            .collect();
+        Self(Some(versions))
+    }
+
+    /// Reject an update of `what` at `version` with 412 when the caller expected another version
+    pub fn check(&self, what: &str, version: i32) -> Result<(), ApiError> {
+        match &self.0 {
+            Some(versions) if !versions.contains(&version) => {
+                Err(Self::failed(what)
+                    .with_details(serde_json::json!({ "currentVersion": version })))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    /// The error for an update of `what` that another update got to first
+    pub fn failed(what: &str) -> ApiError {
+        ApiError::new(
+            StatusCode::PRECONDITION_FAILED,
+            format!("{what} was changed by someone else; fetch it again and retry"),
+        )
+    }
+}
+
+#[async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for IfMatch {
+    type Rejection = Infallible;
+
+    async fn from_request_parts(parts: &mut Parts, _state: &S) -> Result<Self, Infallible> {
+        Ok(match parts.headers.get(header::IF_MATCH) {
+            Some(value) => value
+                .to_str()
+                .map_or_else(|_| Self(Some(Vec::new())), Self::parse),
+            None => Self::default(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_if_match() {
+        assert_eq!(IfMatch::parse("*"), IfMatch(None));
+        assert_eq!(IfMatch::parse("\"3\""), IfMatch(Some(vec![3])));
+        assert_eq!(IfMatch::parse("W/\"3\", \"4\""), IfMatch(Some(vec![3, 4])));
+        assert_eq!(IfMatch::parse("3"), IfMatch(Some(Vec::new())));
+
+        assert!(IfMatch(None).check("Test suite", 7).is_ok());
+        assert!(IfMatch::parse("\"7\"").check("Test suite", 7).is_ok());
+        let error = IfMatch::parse("\"6\"").check("Test suite", 7).unwrap_err();
+        assert_eq!(error.status, StatusCode::PRECONDITION_FAILED);
+        assert_eq!(error.code, "precondition_failed");
+    }
+}
@@ -1,17 +1,35 @@
-use crate::k8s::{monitor_job_and_update_status, KubernetesClient};
-use axum::{extract::Path, http::StatusCode, response::Json, Extension, Json as JsonBody};
+use crate::auth::{generate_token, hash_token, AuthConfig, Identity, Scope, API_TOKEN_PREFIX};
+use crate::authz::{effective_role, Role};
+use crate::error::ApiError;
+use crate::etag::{ETag, IfMatch};
+use crate::k8s::{monitor_job_and_update_status, JobLogs, KubernetesClient, JOB_TTL_SECONDS};
+use crate::pagination::{Cursor, ListQuery, Listing, Page, BY_NAME, NEWEST_FIRST};
+use crate::projects::{Project, DEFAULT_PROJECT_ID};
+use crate::responses::*;
+use crate::validation::{is_dns_subdomain, Validator, RUN_ORIGINS};
+use axum::{
+    extract::{Path, Query},
+    http::StatusCode,
+    response::Json,
+    Extension, Json as JsonBody,
+};
 use chrono;
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use sparktest_core::{
+    ConcurrencyPolicy, CronSchedule, DefinitionVersion, Executor, K8sRef, RunOrigin, RunSpec,
+    SuiteRun, TestDefinition, TestRun, TestSchedule, TestSuite,
+};
 use sqlx::PgPool;
+use utoipa::{IntoParams, ToSchema};
 use uuid::Uuid;
 
-#[derive(Serialize)]
+#[derive(Serialize, ToSchema)]
 pub struct HealthResponse {
     pub status: String,
     pub timestamp: String,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateRunRequest {
     pub name: String,
     pub image: String,
@@ -22,20 +40,26 @@ pub struct CreateRunRequest {
     pub k8s_ref: Option<K8sRefInput>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct RunDefinitionRequest {
     pub name: Option<String>,
     pub image: Option<String>,
     pub commands: Option<Vec<String>>,
+    /// Version of the definition to run; the current one if omitted. Cannot be
+    /// combined with an image or commands
+    pub version: Option<i32>,
 }
 
-#[derive(Deserialize, Serialize)]
+#[derive(Deserialize, Serialize, ToSchema)]
 pub struct K8sRefInput {
     pub namespace: String,
     pub name: String,
+    /// Job the controller created for the TestRun
+    #[serde(rename = "jobName", default)]
+    pub job_name: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateDefinitionRequest {
     pub name: String,
     pub description: Option<String>,
@@ -47,25 +71,28 @@ pub struct CreateDefinitionRequest {
     pub labels: Option<Vec<String>>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateExecutorRequest {
     pub name: String,
     pub description: Option<String>,
     pub image: String,
+    /// Deprecated: `defaultCommand` as a list of words
     #[serde(default)]
     pub command: Option<Vec<String>>,
     #[serde(rename = "defaultCommand")]
     pub default_command: Option<String>,
     #[serde(rename = "supportedFileTypes", default)]
     pub supported_file_types: Option<Vec<String>>,
+    /// Deprecated: `environmentVariables` as an object, of which only the keys are kept
     #[serde(default)]
+    #[schema(value_type = Option<Object>)]
     pub env: Option<serde_json::Value>,
     #[serde(rename = "environmentVariables", default)]
     pub environment_variables: Option<Vec<String>>,
     pub icon: Option<String>,
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct CreateSuiteRequest {
     pub name: String,
     pub description: Option<String>,
@@ -73,14 +100,300 @@ pub struct CreateSuiteRequest {
     pub execution_mode: String,
     #[serde(default)]
     pub labels: Option<Vec<String>>,
-    #[serde(rename = "testDefinitionIds")]
-    pub test_definition_ids: Vec<Uuid>,
+    #[serde(rename = "definitionIds", alias = "testDefinitionIds")]
+    pub definition_ids: Vec<Uuid>,
 }
 
 fn default_execution_mode() -> String {
     "sequential".to_string()
 }
 
+/// Changes to a test definition; omitted fields keep their value
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateDefinitionRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    pub image: Option<String>,
+    pub commands: Option<Vec<String>>,
+    /// `null` removes the executor
+    #[serde(rename = "executorId", default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<Uuid>)]
+    pub executor_id: Option<Option<Uuid>>,
+    pub labels: Option<Vec<String>>,
+}
+
+/// Changes to an executor; omitted fields keep their value
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateExecutorRequest {
+    pub name: Option<String>,
+    /// `null` removes the description
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub description: Option<Option<String>>,
+    pub image: Option<String>,
+    /// Deprecated: `defaultCommand` as a list of words
+    pub command: Option<Vec<String>>,
+    #[serde(rename = "defaultCommand")]
+    pub default_command: Option<String>,
+    #[serde(rename = "supportedFileTypes")]
+    pub supported_file_types: Option<Vec<String>>,
+    /// Deprecated: `environmentVariables` as an object, of which only the keys are kept
+    #[schema(value_type = Option<Object>)]
+    pub env: Option<serde_json::Value>,
+    #[serde(rename = "environmentVariables")]
+    pub environment_variables: Option<Vec<String>>,
+    /// `null` removes the icon
+    #[serde(default, deserialize_with = "nullable")]
+    #[schema(value_type = Option<String>)]
+    pub icon: Option<Option<String>>,
+}
+
+/// Changes to a test suite; omitted fields keep their value
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateSuiteRequest {
+    pub name: Option<String>,
+    pub description: Option<String>,
+    #[serde(rename = "executionMode")]
+    pub execution_mode: Option<String>,
+    pub labels: Option<Vec<String>>,
+    #[serde(rename = "definitionIds", alias = "testDefinitionIds")]
+    pub definition_ids: Option<Vec<Uuid>>,
+}
+
+/// Deserialize a field that may be omitted (`None`) or set to `null` (`Some(None)`)
+fn nullable<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+impl CreateRunRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &self.name);
+        validator.image("image", &self.image);
+        validator.commands("commands", &self.commands);
+        if let Some(origin) = &self.origin {
+            validator.check(
+                "origin",
+                RUN_ORIGINS.contains(&origin.as_str()),
+                format!("Must be one of {}", RUN_ORIGINS.join(", ")),
+            );
+        }
+        if let Some(job_name) = self.k8s_ref.as_ref().and_then(|r| r.job_name.as_deref()) {
+            validator.check(
+                "k8sRef.jobName",
+                is_dns_subdomain(job_name),
+                "Must be a valid Kubernetes object name",
+            );
+        }
+        validator.finish()
+    }
+}
+
+impl RunDefinitionRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        if let Some(name) = &self.name {
+            validator.name("name", name);
+        }
+        if let Some(image) = &self.image {
+            validator.image("image", image);
+        }
+        if let Some(commands) = &self.commands {
+            validator.commands("commands", commands);
+        }
+        if self.version.is_some() {
+            let pinned = "Cannot be overridden when running a specific version";
+            validator.check("image", self.image.is_none(), pinned);
+            validator.check("commands", self.commands.is_none(), pinned);
+        }
+        validator.finish()
+    }
+
+    /// `version` if the run executes it as is, None if it overrides its image or commands
+    fn executed_version(&self, version: i32, image: &str, commands: &[String]) -> Option<i32> {
+        let same_image = self.image.as_deref().is_none_or(|i| i == image);
+        let same_commands = self.commands.as_deref().is_none_or(|c| c == commands);
+        (same_image && same_commands).then_some(version)
+    }
+}
+
+impl CreateDefinitionRequest {
+    async fn validate(&self, pool: &PgPool, project: &Project) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &self.name);
+        validator.image("image", &self.image);
+        validator.commands("commands", &self.commands);
+        validator.labels("labels", self.labels.as_deref().unwrap_or_default());
+        if let Some(executor_id) = self.executor_id {
+            validator
+                .existing_ids(
+                    pool,
+                    "executorId",
+                    "test_executors",
+                    project.id,
+                    &[executor_id],
+                )
+                .await?;
+        }
+        validator.finish()
+    }
+}
+
+impl CreateExecutorRequest {
+    fn validate(&self) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &self.name);
+        validator.image("image", &self.image);
+        validator.finish()
+    }
+}
+
+impl CreateSuiteRequest {
+    async fn validate(&self, pool: &PgPool, project: &Project) -> Result<(), ApiError> {
+        let mut validator = Validator::new();
+        validator.name("name", &self.name);
+        validator.execution_mode("executionMode", &self.execution_mode);
+        validator.labels("labels", self.labels.as_deref().unwrap_or_default());
+        validator
+            .existing_ids(
+                pool,
+                "definitionIds",
+                "test_definitions",
+                project.id,
+                &self.definition_ids,
+            )
+            .await?;
+        validator.finish()
+    }
+}
+
+impl UpdateDefinitionRequest {
+    /// The full definition after applying the changes to `current`
+    fn apply(self, current: TestDefinition) -> CreateDefinitionRequest {
+        CreateDefinitionRequest {
+            name: self.name.unwrap_or(current.name),
+            description: self.description.or(Some(current.description)),
+            image: self.image.unwrap_or(current.image),
+            commands: self.commands.unwrap_or(current.commands),
+            executor_id: self.executor_id.unwrap_or(current.executor_id),
+            labels: self.labels.or(Some(current.labels)),
+        }
+    }
+}
+
+impl UpdateExecutorRequest {
+    /// The full executor after applying the changes to `current`
+    fn apply(self, current: Executor) -> CreateExecutorRequest {
+        let keep_command = self.default_command.is_none() && self.command.is_none();
+        let keep_variables = self.environment_variables.is_none() && self.env.is_none();
+
+        CreateExecutorRequest {
+            name: self.name.unwrap_or(current.name),
+            description: self.description.unwrap_or(current.description),
+            image: self.image.unwrap_or(current.image),
+            command: self.command,
+            default_command: if keep_command {
+                current.default_command
+            } else {
+                self.default_command
+            },
+            supported_file_types: self
+                .supported_file_types
+                .or(Some(current.supported_file_types)),
+            env: self.env,
+            environment_variables: if keep_variables {
+                Some(current.environment_variables)
+            } else {
+                self.environment_variables
+            },
+            icon: self.icon.unwrap_or(current.icon),
+        }
+    }
+}
+
+impl UpdateSuiteRequest {
+    /// The full suite after applying the changes to `current`
+    fn apply(self, current: TestSuite) -> CreateSuiteRequest {
+        CreateSuiteRequest {
+            name: self.name.unwrap_or(current.name),
+            description: self.description.or(Some(current.description)),
+            execution_mode: self.execution_mode.unwrap_or(current.execution_mode),
+            labels: self.labels.or(Some(current.labels)),
+            definition_ids: self.definition_ids.unwrap_or(current.definition_ids),
+        }
+    }
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateScheduleRequest {
+    pub name: String,
+    #[serde(rename = "cronExpression")]
+    pub cron_expression: String,
+    #[serde(default = "default_timezone")]
+    pub timezone: String,
+    #[serde(rename = "definitionId", alias = "testDefinitionId")]
+    pub definition_id: Option<Uuid>,
+    #[serde(rename = "suiteId", alias = "testSuiteId")]
+    pub suite_id: Option<Uuid>,
+    #[serde(rename = "concurrencyPolicy", default)]
+    #[schema(value_type = String, example = "Allow")]
+    pub concurrency_policy: ConcurrencyPolicy,
+    #[serde(
+        rename = "successfulRunsHistoryLimit",
+        default = "default_successful_history"
+    )]
+    pub successful_runs_history_limit: i32,
+    #[serde(rename = "failedRunsHistoryLimit", default = "default_failed_history")]
+    pub failed_runs_history_limit: i32,
+    #[serde(default = "default_enabled")]
+    pub enabled: bool,
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct CreateTokenRequest {
+    pub name: String,
+    #[serde(default = "default_token_scopes")]
+    pub scopes: Vec<Scope>,
+    #[serde(rename = "expiresAt")]
+    pub expires_at: Option<chrono::DateTime<chrono::Utc>>,
+}
+
+fn default_token_scopes() -> Vec<Scope> {
+    vec![Scope::Read]
+}
+
+#[derive(Deserialize, ToSchema)]
+pub struct AssignRoleRequest {
+    pub role: Role,
+}
+
+fn default_timezone() -> String {
+    "UTC".to_string()
+}
+
+fn default_successful_history() -> i32 {
+    3
+}
+
+fn default_failed_history() -> i32 {
+    1
+}
+
+fn default_enabled() -> bool {
+    true
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/health",
+    tag = "health",
+    security(()),
+    responses((status = 200, description = "The API is up", body = HealthResponse))
+)]
 pub async fn health_check() -> Json<HealthResponse> {
     Json(HealthResponse {
         status: "healthy".to_string(),
@@ -88,62 +401,161 @@ pub async fn health_check() -> Json<HealthResponse> {
     })
 }
 
-pub async fn get_runs(
-    Extension(pool): Extension<PgPool>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, String, Vec<String>, String, chrono::DateTime<chrono::Utc>, Option<i32>, Option<Vec<String>>, Option<Uuid>, Option<Uuid>, Option<String>, Option<String>, Option<String>)>(
-        "SELECT id, name, image, command, status, created_at, duration, logs, test_definition_id, executor_id, origin::text, k8s_ref_namespace, k8s_ref_name FROM test_runs ORDER BY created_at DESC"
-    )
-    .fetch_all(&pool)
-    .await
-    {
-        Ok(rows) => {
-            let runs: Vec<serde_json::Value> = rows
-                .into_iter()
-                .map(|(id, name, image, command, status, created_at, duration, logs, test_definition_id, executor_id, origin, k8s_ref_namespace, k8s_ref_name)| {
-                    let mut run_json = serde_json::json!({
-                        "id": id,
-                        "name": name,
-                        "image": image,
-                        "command": command,
-                        "status": status,
-                        "createdAt": created_at,
-                        "duration": duration,
-                        "logs": logs,
-                        "testDefinitionId": test_definition_id,
-                        "executorId": executor_id,
-                        "origin": origin.unwrap_or_else(|| "api".to_string())
-                    });
-
-                    // Add k8sRef if both namespace and name are present
-                    if let (Some(ns), Some(n)) = (k8s_ref_namespace, k8s_ref_name) {
-                        run_json["k8sRef"] = serde_json::json!({
-                            "namespace": ns,
-                            "name": n
-                        });
-                    }
+/// A `test_runs` row; more columns than sqlx reads into a tuple
+#[derive(sqlx::FromRow)]
+struct RunRow {
+    id: Uuid,
+    name: String,
+    image: String,
+    command: Vec<String>,
+    status: String,
+    created_at: chrono::DateTime<chrono::Utc>,
+    duration: Option<i32>,
+    logs: Option<Vec<String>>,
+    test_definition_id: Option<Uuid>,
+    executor_id: Option<Uuid>,
+    origin: Option<String>,
+    k8s_ref_namespace: Option<String>,
+    k8s_ref_name: Option<String>,
+    schedule_id: Option<Uuid>,
+    created_by: Option<String>,
+    definition_version: Option<i32>,
+    k8s_job_name: Option<String>,
+}
 
-                    run_json
-                })
-                .collect();
-            Ok(Json(runs))
-        }
-        Err(e) => {
-            tracing::error!("Failed to fetch test runs: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+/// Runs by status, origin, definition, the definitions of a suite, executor,
+/// definition label, creation time and name
+const RUN_LISTING: Listing = Listing {
+    name: "test runs",
+    table: "test_runs",
+    columns: "id, name, image, command, status, created_at, duration, logs, test_definition_id, executor_id, origin::text AS origin, k8s_ref_namespace, k8s_ref_name, schedule_id, created_by, definition_version, k8s_job_name",
+    filter: "project_id = $1 AND ($2::text IS NULL OR status = $2) AND ($3::text IS NULL OR origin::text = $3) AND ($4::uuid IS NULL OR test_definition_id = $4) AND ($5::uuid IS NULL OR test_definition_id IN (SELECT UNNEST(test_definition_ids) FROM test_suites WHERE id = $5)) AND ($6::uuid IS NULL OR executor_id = $6) AND ($7::text IS NULL OR test_definition_id IN (SELECT id FROM test_definitions WHERE $7 = ANY(labels))) AND ($8::timestamptz IS NULL OR created_at >= $8) AND ($9::timestamptz IS NULL OR created_at < $9) AND ($10::text IS NULL OR strpos(lower(name), lower($10)) > 0)",
+    filters: &[
+        "status",
+        "origin",
+        "definitionId",
+        "suiteId",
+        "executorId",
+        "label",
+        "createdAfter",
+        "createdBefore",
+    ],
+    order: NEWEST_FIRST,
+};
+
+fn run_response(row: RunRow) -> TestRun {
+    let RunRow {
+        id,
+        name,
+        image,
+        command: commands,
+        status,
+        created_at,
+        duration,
+        logs,
+        test_definition_id: definition_id,
+        executor_id,
+        origin,
+        k8s_ref_namespace,
+        k8s_ref_name,
+        schedule_id,
+        created_by,
+        definition_version,
+        k8s_job_name,
+    } = row;
+    let origin = match origin.as_deref() {
+        Some("crd") => RunOrigin::Crd,
+        _ => RunOrigin::Api,
+    };
+
+    TestRun {
+        id,
+        name,
+        image,
+        commands,
+        status,
+        created_at,
+        definition_id,
+        executor_id,
+        schedule_id,
+        duration,
+        logs,
+        // Runs from before job names were stored are named after the run
+        k8s_job_name: k8s_job_name.or_else(|| {
+            (origin == RunOrigin::Api).then(|| sparktest_core::job_name(&id.to_string()))
+        }),
+        origin,
+        // Only set when both namespace and name are present
+        k8s_ref: k8s_ref_namespace
+            .zip(k8s_ref_name)
+            .map(|(namespace, name)| K8sRef { namespace, name }),
+        created_by,
+        definition_version,
     }
 }
 
+/// List the project's runs, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/runs",
+    tag = "runs",
+    params(ListQuery),
+    responses((status = 200, description = "A page of runs", body = [TestRun], headers(
+        ("x-total-count" = i64, description = "Runs matching the filters"),
+        ("x-next-cursor" = String, description = "Cursor of the next page, if there is one")
+    )))
+)]
+pub async fn get_runs(
+    Query(query): Query<ListQuery>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Page<TestRun>, ApiError> {
+    let (rows, total, limit) = RUN_LISTING
+        .fetch::<RunRow>(&pool, project.id, &query)
+        .await?;
+    Ok(Page::new(
+        rows,
+        limit,
+        total,
+        |row| Cursor::created_at(row.created_at, row.id),
+        run_response,
+    ))
+}
+
+/// Start a run of an image, outside of any definition
+#[utoipa::path(
+    post,
+    path = "/api/v1/runs",
+    tag = "runs",
+    request_body = CreateRunRequest,
+    responses(
+        (status = 200, description = "The run, failed if its job could not be created", body = TestRun),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
 pub async fn create_run(
     Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
     JsonBody(req): JsonBody<CreateRunRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
+) -> Result<Json<TestRun>, ApiError> {
+    req.validate()?;
     let run_uuid = Uuid::new_v4();
     let now = chrono::Utc::now();
 
     // Determine origin (default to "api" if not provided)
-    let origin = req.origin.as_deref().unwrap_or("api");
+    let origin = match req.origin.as_deref() {
+        Some("crd") => RunOrigin::Crd,
+        _ => RunOrigin::Api,
+    };
+
+    // Only the controller may register runs on behalf of TestRun resources
+    if origin == RunOrigin::Crd && identity != Identity::Controller {
+        tracing::warn!("Rejected CRD-origin run from {:?} caller", identity);
+        return Err(ApiError::forbidden(
+            "Only the controller may create runs with origin crd",
+        ));
+    }
 
     // Extract k8s_ref fields if provided
     let (k8s_ref_namespace, k8s_ref_name) = match &req.k8s_ref {
@@ -151,9 +563,24 @@ pub async fn create_run(
         None => (None, None),
     };
 
+    let spec = RunSpec {
+        ttl_seconds_after_finished: Some(JOB_TTL_SECONDS),
+        ..RunSpec::new(
+            run_uuid.to_string(),
+            req.image.clone(),
+            req.commands.clone(),
+            origin.clone(),
+        )
+    };
+    // The controller has already created the Job of a CRD-origin run
+    let job_name = match origin {
+        RunOrigin::Crd => req.k8s_ref.as_ref().and_then(|r| r.job_name.clone()),
+        RunOrigin::Api => Some(spec.job_name()),
+    };
+
     // Insert the run first with status 'running', including origin and k8s_ref
     if let Err(e) = sqlx::query(
-        "INSERT INTO test_runs (id, name, image, command, status, created_at, origin, k8s_ref_namespace, k8s_ref_name) VALUES ($1, $2, $3, $4, $5, $6, $7::run_origin, $8, $9)"
+        "INSERT INTO test_runs (id, name, image, command, status, created_at, origin, k8s_ref_namespace, k8s_ref_name, created_by, project_id, k8s_job_name) VALUES ($1, $2, $3, $4, $5, $6, $7::run_origin, $8, $9, $10, $11, $12)"
     )
     .bind(run_uuid)
     .bind(&req.name)
@@ -161,30 +588,72 @@ pub async fn create_run(
     .bind(&req.commands) // TEXT[]
     .bind("running")
     .bind(now)
-    .bind(origin)
+    .bind(origin.as_str())
     .bind(&k8s_ref_namespace)
     .bind(&k8s_ref_name)
+    .bind(identity.actor())
+    .bind(project.id)
+    .bind(&job_name)
     .execute(&pool)
     .await {
         tracing::error!("Failed to insert test run: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+        return Err(ApiError::internal("Failed to insert test run"));
     }
 
-    // Derive a Kubernetes job name from the run id
-    let job_name = format!("test-run-{run_uuid}");
-
-    // Build a shell command to run sequential commands if multiple provided
-    let k8s_command: Vec<String> = if req.commands.len() == 1 {
-        // Use the single command directly under sh -c for consistency
-        vec!["sh".into(), "-c".into(), req.commands[0].clone()]
-    } else {
-        vec!["sh".into(), "-c".into(), req.commands.join(" && ")]
+    let run = |status: &str| TestRun {
+        id: run_uuid,
+        name: req.name.clone(),
+        image: req.image.clone(),
+        commands: req.commands.clone(),
+        status: status.to_string(),
+        created_at: now,
+        definition_id: None,
+        executor_id: None,
+        schedule_id: None,
+        duration: None,
+        logs: None,
+        k8s_job_name: job_name.clone(),
+        origin: origin.clone(),
+        k8s_ref: req.k8s_ref.as_ref().map(|k8s_ref| K8sRef {
+            namespace: k8s_ref.namespace.clone(),
+            name: k8s_ref.name.clone(),
+        }),
+        created_by: identity.actor(),
+        definition_version: None,
     };
 
+    // The controller has created the Job in the TestRun's namespace, so it is only watched here
+    if origin == RunOrigin::Crd {
+        match (&req.k8s_ref, &job_name) {
+            (Some(k8s_ref), Some(job_name)) => {
+                let pool_clone = pool.clone();
+                let job_name_clone = job_name.clone();
+                let namespace = k8s_ref.namespace.clone();
+                tokio::spawn(async move {
+                    if let Err(e) = monitor_job_and_update_status(
+                        run_uuid,
+                        job_name_clone,
+                        namespace,
+                        pool_clone,
+                    )
+                    .await
+                    {
+                        tracing::error!("Job monitor failed for run {}: {}", run_uuid, e);
+                    }
+                });
+            }
+            _ => tracing::warn!(
+                "CRD-origin run {} was registered without a job name; its status is not tracked",
+                run_uuid
+            ),
+        }
+        return Ok(Json(run("running")));
+    }
+
     // Attempt to create the Kubernetes job (build client per request)
-    match KubernetesClient::new().await {
+    match KubernetesClient::for_namespace(&project.k8s_namespace).await {
         Ok(client) => {
-            if let Err(e) = client.submit_job(&job_name, &req.image, &k8s_command).await {
+            if let Err(e) = client.submit_job(&spec).await {
                 tracing::error!(
                     "Failed to create Kubernetes job for run {}: {}",
                     run_uuid,
@@ -202,107 +671,84 @@ pub async fn create_run(
                         upd_err
                     );
                 }
-                // Build response with jobCreated=false
-                let run = serde_json::json!({
-                    "id": run_uuid,
-                    "name": req.name,
-                    "image": req.image,
-                    "command": req.commands,
-                    "status": "failed",
-                    "createdAt": now.to_rfc3339(),
-                    "jobName": job_name,
-                    "jobCreated": false
-                });
-                return Ok(Json(run));
+                return Ok(Json(run("failed")));
             }
         }
         Err(e) => {
             tracing::error!("Failed to initialize Kubernetes client: {}", e);
-            let run = serde_json::json!({
-                "id": run_uuid,
-                "name": req.name,
-                "image": req.image,
-                "command": req.commands,
-                "status": "failed",
-                "createdAt": now.to_rfc3339(),
-                "jobName": job_name,
-                "jobCreated": false
-            });
-            return Ok(Json(run));
+            return Ok(Json(run("failed")));
         }
     }
 
     // Spawn background monitor task on success
     let pool_clone = pool.clone();
-    let job_name_clone = job_name.clone();
+    let job_name_clone = spec.job_name();
     tokio::spawn(async move {
-        if let Err(e) = monitor_job_and_update_status(run_uuid, job_name_clone, pool_clone).await {
+        if let Err(e) = monitor_job_and_update_status(
+            run_uuid,
+            job_name_clone,
+            project.k8s_namespace,
+            pool_clone,
+        )
+        .await
+        {
             tracing::error!("Job monitor failed for run {}: {}", run_uuid, e);
         }
     });
-    let run = serde_json::json!({
-        "id": run_uuid,
-        "name": req.name,
-        "image": req.image,
-        "command": req.commands,
-        "status": "running",
-        "createdAt": now.to_rfc3339(),
-        "jobName": job_name,
-        "jobCreated": true
-    });
-    Ok(Json(run))
+    Ok(Json(run("running")))
 }
 
+#[utoipa::path(
+    get,
+    path = "/api/v1/runs/{id}",
+    tag = "runs",
+    params(("id" = Uuid, Path, description = "Run id")),
+    responses(
+        (status = 200, description = "The run", body = TestRun),
+        (status = 404, description = "No such run in the project", body = ErrorResponse)
+    )
+)]
 pub async fn get_run(
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, String, Vec<String>, String, Option<Uuid>, chrono::DateTime<chrono::Utc>, Option<i32>, Option<Vec<String>>, Option<Uuid>, Option<String>, Option<String>, Option<String>)>(
-        "SELECT id, name, image, command, status, test_definition_id, created_at, duration, logs, executor_id, origin::text, k8s_ref_namespace, k8s_ref_name FROM test_runs WHERE id = $1"
-    )
+    Extension(project): Extension<Project>,
+) -> Result<Json<TestRun>, ApiError> {
+    match sqlx::query_as::<_, RunRow>(&format!(
+        "SELECT {} FROM test_runs WHERE id = $1 AND project_id = $2",
+        RUN_LISTING.columns
+    ))
     .bind(id)
+    .bind(project.id)
     .fetch_optional(&pool)
     .await
     {
-        Ok(Some((id, name, image, command, status, test_definition_id, created_at, duration, logs, executor_id, origin, k8s_ref_namespace, k8s_ref_name))) => {
-            let mut run = serde_json::json!({
-                "id": id,
-                "name": name,
-                "image": image,
-                "command": command,
-                "status": status,
-                "testDefinitionId": test_definition_id,
-                "createdAt": created_at,
-                "duration": duration,
-                "logs": logs,
-                "executorId": executor_id,
-                "origin": origin.unwrap_or_else(|| "api".to_string())
-            });
-
-            // Add k8sRef if both namespace and name are present
-            if let (Some(ns), Some(n)) = (k8s_ref_namespace, k8s_ref_name) {
-                run["k8sRef"] = serde_json::json!({
-                    "namespace": ns,
-                    "name": n
-                });
-            }
-
-            Ok(Json(run))
-        }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Ok(Some(row)) => Ok(Json(run_response(row))),
+        Ok(None) => Err(ApiError::not_found(format!("Test run {id} not found"))),
         Err(e) => {
             tracing::error!("Failed to fetch test run {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::internal("Failed to fetch test run"))
         }
     }
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/runs/{id}",
+    tag = "runs",
+    params(("id" = Uuid, Path, description = "Run id")),
+    responses(
+        (status = 204, description = "The run was deleted"),
+        (status = 404, description = "No such run in the project", body = ErrorResponse)
+    )
+)]
 pub async fn delete_run(
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<StatusCode, StatusCode> {
-    match sqlx::query("DELETE FROM test_runs WHERE id = $1")
-        .bind(id.to_string())
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM test_runs WHERE id = $1 AND project_id = $2")
+        .bind(id)
+        .bind(project.id)
         .execute(&pool)
         .await
     {
@@ -310,921 +756,2660 @@ pub async fn delete_run(
             if result.rows_affected() > 0 {
                 Ok(StatusCode::NO_CONTENT)
             } else {
-                Err(StatusCode::NOT_FOUND)
+                Err(ApiError::not_found(format!("Test run {id} not found")))
             }
         }
         Err(e) => {
             tracing::error!("Failed to delete test run {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            Err(ApiError::internal("Failed to delete test run"))
         }
     }
 }
 
-pub async fn k8s_health() -> Json<serde_json::Value> {
-    // Attempt to create Kubernetes client and check health
-    match KubernetesClient::new().await {
-        Ok(client) => match client.health_check().await {
-            Ok(is_healthy) => Json(serde_json::json!({
-                "kubernetes_connected": is_healthy,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            Err(_) => Json(serde_json::json!({
-                "kubernetes_connected": false,
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "error": "Kubernetes health check failed"
-            })),
-        },
-        Err(_) => Json(serde_json::json!({
-            "kubernetes_connected": false,
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "error": "Could not create Kubernetes client"
-        })),
+/// Whether the project's cluster is reachable; 503 when it is not
+#[utoipa::path(
+    get,
+    path = "/api/v1/k8s/health",
+    tag = "kubernetes",
+    responses(
+        (status = 200, description = "The cluster is reachable", body = KubernetesHealth),
+        (status = 503, description = "The cluster is not reachable", body = ErrorResponse)
+    )
+)]
+pub async fn k8s_health(
+    Extension(project): Extension<Project>,
+) -> Result<Json<KubernetesHealth>, ApiError> {
+    let client = KubernetesClient::for_namespace(&project.k8s_namespace)
+        .await
+        .map_err(ApiError::kubernetes_unavailable)?;
+    let connected = client.health_check().await.unwrap_or_else(|e| {
+        tracing::warn!("Kubernetes health check failed: {}", e);
+        false
+    });
+
+    if !connected {
+        return Err(ApiError::new(
+            StatusCode::SERVICE_UNAVAILABLE,
+            "Kubernetes health check failed",
+        )
+        .with_details(serde_json::json!({ "kubernetesConnected": false })));
     }
+    Ok(Json(KubernetesHealth {
+        kubernetes_connected: true,
+        timestamp: chrono::Utc::now(),
+    }))
 }
 
-pub async fn get_job_logs(Path(job_name): Path<String>) -> Json<serde_json::Value> {
-    // Attempt to get real job logs from Kubernetes
-    match KubernetesClient::new().await {
-        Ok(client) => match client.get_job_logs(&job_name).await {
-            Ok(job_logs) => Json(serde_json::json!({
-                "job_name": job_logs.job_name,
-                "pod_name": job_logs.pod_name,
-                "logs": job_logs.logs,
-                "timestamp": job_logs.timestamp.to_rfc3339(),
-                "status": job_logs.status
-            })),
-            Err(e) => Json(serde_json::json!({
-                "job_name": job_name,
-                "error": format!("Failed to get job logs: {}", e),
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "status": "error"
-            })),
-        },
-        Err(_) => Json(serde_json::json!({
-            "job_name": job_name,
-            "error": "Kubernetes client unavailable",
-            "timestamp": chrono::Utc::now().to_rfc3339(),
-            "status": "error"
-        })),
-    }
+#[utoipa::path(
+    get,
+    path = "/api/v1/k8s/logs/{job_name}",
+    tag = "kubernetes",
+    params(("job_name" = String, Path, description = "Kubernetes job name")),
+    responses(
+        (status = 200, description = "Logs of the job's pod", body = JobLogs),
+        (status = 404, description = "No such job", body = ErrorResponse)
+    )
+)]
+pub async fn get_job_logs(
+    Path(job_name): Path<String>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<JobLogs>, ApiError> {
+    let client = KubernetesClient::for_namespace(&project.k8s_namespace)
+        .await
+        .map_err(ApiError::kubernetes_unavailable)?;
+    let job_logs = client
+        .get_job_logs(&job_name)
+        .await
+        .map_err(|e| ApiError::kubernetes(format!("Failed to get logs of job {job_name}"), e))?;
+
+    Ok(Json(job_logs))
 }
 
-pub async fn get_job_status(Path(job_name): Path<String>) -> Json<serde_json::Value> {
-    // Attempt to get real job status from Kubernetes
-    match KubernetesClient::new().await {
-        Ok(client) => match client.get_job_status(&job_name).await {
-            Ok(status) => Json(serde_json::json!({
-                "job_name": job_name,
-                "status": status,
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            Err(e) => Json(serde_json::json!({
-                "job_name": job_name,
-                "status": "error",
-                "error": format!("Failed to get job status: {}", e),
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-        },
-        Err(_) => Json(serde_json::json!({
-            "job_name": job_name,
-            "status": "error",
-            "error": "Kubernetes client unavailable",
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        })),
-    }
+#[utoipa::path(
+    get,
+    path = "/api/v1/k8s/status/{job_name}",
+    tag = "kubernetes",
+    params(("job_name" = String, Path, description = "Kubernetes job name")),
+    responses(
+        (status = 200, description = "Status of the job", body = JobStatus),
+        (status = 404, description = "No such job", body = ErrorResponse)
+    )
+)]
+pub async fn get_job_status(
+    Path(job_name): Path<String>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<JobStatus>, ApiError> {
+    let client = KubernetesClient::for_namespace(&project.k8s_namespace)
+        .await
+        .map_err(ApiError::kubernetes_unavailable)?;
+    let status = client
+        .get_job_status(&job_name)
+        .await
+        .map_err(|e| ApiError::kubernetes(format!("Failed to get status of job {job_name}"), e))?;
+
+    Ok(Json(JobStatus {
+        job_name,
+        status,
+        timestamp: chrono::Utc::now(),
+    }))
 }
 
-pub async fn delete_job(Path(job_name): Path<String>) -> Json<serde_json::Value> {
-    // Attempt to delete real job from Kubernetes
-    match KubernetesClient::new().await {
-        Ok(client) => match client.delete_job(&job_name).await {
-            Ok(_) => Json(serde_json::json!({
-                "message": format!("Job {} deleted successfully", job_name),
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            Err(e) => Json(serde_json::json!({
-                "error": format!("Failed to delete job {}: {}", job_name, e),
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-        },
-        Err(_) => Json(serde_json::json!({
-            "error": format!("Kubernetes client unavailable - cannot delete job {}", job_name),
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        })),
-    }
+#[utoipa::path(
+    delete,
+    path = "/api/v1/k8s/jobs/{job_name}",
+    tag = "kubernetes",
+    params(("job_name" = String, Path, description = "Kubernetes job name")),
+    responses(
+        (status = 200, description = "The job was deleted", body = MessageResponse),
+        (status = 404, description = "No such job", body = ErrorResponse)
+    )
+)]
+pub async fn delete_job(
+    Path(job_name): Path<String>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<MessageResponse>, ApiError> {
+    let client = KubernetesClient::for_namespace(&project.k8s_namespace)
+        .await
+        .map_err(ApiError::kubernetes_unavailable)?;
+    client
+        .delete_job(&job_name)
+        .await
+        .map_err(|e| ApiError::kubernetes(format!("Failed to delete job {job_name}"), e))?;
+
+    Ok(Json(MessageResponse {
+        message: format!("Job {} deleted successfully", job_name),
+        timestamp: chrono::Utc::now(),
+    }))
 }
 
-pub async fn list_jobs() -> Json<serde_json::Value> {
-    match KubernetesClient::new().await {
-        Ok(client) => match client.list_jobs().await {
-            Ok(names) => Json(serde_json::json!({
-                "jobs": names,
-                "count": names.len(),
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-            Err(e) => Json(serde_json::json!({
-                "error": format!("Failed to list jobs: {}", e),
-                "timestamp": chrono::Utc::now().to_rfc3339()
-            })),
-        },
-        Err(e) => Json(serde_json::json!({
-            "error": format!("Kubernetes client unavailable: {}", e),
-            "timestamp": chrono::Utc::now().to_rfc3339()
-        })),
+/// Names of the project's SparkTest jobs
+#[utoipa::path(
+    get,
+    path = "/api/v1/k8s/jobs",
+    tag = "kubernetes",
+    responses((status = 200, description = "The jobs", body = JobListResponse))
+)]
+pub async fn list_jobs(
+    Extension(project): Extension<Project>,
+) -> Result<Json<JobListResponse>, ApiError> {
+    let client = KubernetesClient::for_namespace(&project.k8s_namespace)
+        .await
+        .map_err(ApiError::kubernetes_unavailable)?;
+    let jobs = client
+        .list_jobs()
+        .await
+        .map_err(|e| ApiError::kubernetes("Failed to list jobs", e))?;
+
+    Ok(Json(JobListResponse {
+        count: jobs.len(),
+        jobs,
+        timestamp: chrono::Utc::now(),
+    }))
+}
+
+type DefinitionRow = (
+    Uuid,
+    String,
+    String,
+    Vec<String>,
+    Option<String>,
+    chrono::DateTime<chrono::Utc>,
+    Option<Uuid>,
+    Option<Vec<String>>,
+    Option<String>,
+    i32,
+    chrono::DateTime<chrono::Utc>,
+);
+
+/// Definitions by suite, executor, label, creation time and name or description
+const DEFINITION_LISTING: Listing = Listing {
+    name: "test definitions",
+    table: "test_definitions",
+    columns: "id, name, image, commands, description, created_at, executor_id, labels, created_by, version, updated_at",
+    filter: "project_id = $1 AND ($5::uuid IS NULL OR id IN (SELECT UNNEST(test_definition_ids) FROM test_suites WHERE id = $5)) AND ($6::uuid IS NULL OR executor_id = $6) AND ($7::text IS NULL OR $7 = ANY(labels)) AND ($8::timestamptz IS NULL OR created_at >= $8) AND ($9::timestamptz IS NULL OR created_at < $9) AND ($10::text IS NULL OR strpos(lower(name), lower($10)) > 0 OR strpos(lower(description), lower($10)) > 0)",
+    filters: &[
+        "suiteId",
+        "executorId",
+        "label",
+        "createdAfter",
+        "createdBefore",
+    ],
+    order: NEWEST_FIRST,
+};
+
+fn definition_response(row: DefinitionRow) -> TestDefinition {
+    let (
+        id,
+        name,
+        image,
+        commands,
+        description,
+        created_at,
+        executor_id,
+        labels,
+        created_by,
+        version,
+        updated_at,
+    ) = row;
+
+    TestDefinition {
+        id,
+        name,
+        image,
+        commands,
+        description: description.unwrap_or_default(),
+        created_at,
+        executor_id,
+        labels: labels.unwrap_or_default(),
+        created_by,
+        version,
+        updated_at,
     }
 }
 
-pub async fn get_definitions(
-    Extension(pool): Extension<PgPool>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, String, Vec<String>, Option<String>, chrono::DateTime<chrono::Utc>, Option<Uuid>, Option<Vec<String>>)>(
-        "SELECT id, name, image, commands, description, created_at, executor_id, labels FROM test_definitions ORDER BY created_at DESC"
-    )
-    .fetch_all(&pool)
+/// One of the project's test definitions
+async fn fetch_definition(
+    pool: &PgPool,
+    project: &Project,
+    id: Uuid,
+) -> Result<TestDefinition, ApiError> {
+    match sqlx::query_as::<_, DefinitionRow>(&format!(
+        "SELECT {} FROM test_definitions WHERE id = $1 AND project_id = $2",
+        DEFINITION_LISTING.columns
+    ))
+    .bind(id)
+    .bind(project.id)
+    .fetch_optional(pool)
     .await
     {
-        Ok(rows) => {
-            let definitions: Vec<serde_json::Value> = rows
-                .into_iter()
-                .map(|(id, name, image, commands, description, created_at, executor_id, labels)| {
-                    serde_json::json!({
-                        "id": id,
-                        "name": name,
-                        "image": image,
-                        "commands": commands,
-                        "description": description,
-                        "createdAt": created_at,
-                        "executorId": executor_id,
-                        "labels": labels.unwrap_or_default()
-                    })
-                })
-                .collect();
-            Ok(Json(definitions))
-        }
+        Ok(Some(row)) => Ok(definition_response(row)),
+        Ok(None) => Err(ApiError::not_found(format!(
+            "Test definition {id} not found"
+        ))),
         Err(e) => {
-            tracing::error!("Failed to fetch test definitions: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to fetch test definition {}: {}", id, e);
+            Err(ApiError::internal("Failed to fetch test definition"))
         }
     }
 }
 
+/// List the project's test definitions, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/definitions",
+    tag = "test definitions",
+    params(ListQuery),
+    responses((status = 200, description = "A page of test definitions", body = [TestDefinition], headers(
+        ("x-total-count" = i64, description = "Definitions matching the filters"),
+        ("x-next-cursor" = String, description = "Cursor of the next page, if there is one")
+    )))
+)]
+pub async fn get_definitions(
+    Query(query): Query<ListQuery>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Page<TestDefinition>, ApiError> {
+    let (rows, total, limit) = DEFINITION_LISTING
+        .fetch::<DefinitionRow>(&pool, project.id, &query)
+        .await?;
+    Ok(Page::new(
+        rows,
+        limit,
+        total,
+        |row| Cursor::created_at(row.5, row.0),
+        definition_response,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/definitions/{id}",
+    tag = "test definitions",
+    params(("id" = Uuid, Path, description = "Test definition id")),
+    responses(
+        (status = 200, description = "The test definition", body = TestDefinition, headers(
+            ("etag" = String, description = "Version of the definition, for `If-Match`")
+        )),
+        (status = 404, description = "No such definition in the project", body = ErrorResponse)
+    )
+)]
 pub async fn get_definition(
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, String, Vec<String>, Option<String>, chrono::DateTime<chrono::Utc>, Option<Uuid>, Option<Vec<String>>)>(
-        "SELECT id, name, image, commands, description, created_at, executor_id, labels FROM test_definitions WHERE id = $1"
-    )
-    .bind(id)
-    .fetch_optional(&pool)
-    .await
-    {
-        Ok(Some((id, name, image, commands, description, created_at, executor_id, labels))) => {
-            Ok(Json(serde_json::json!({
-                "id": id,
-                "name": name,
-                "image": image,
-                "commands": commands,
-                "description": description,
-                "createdAt": created_at,
-                "executorId": executor_id,
-                "labels": labels.unwrap_or_default()
-            })))
-        }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
-        Err(e) => {
-            tracing::error!("Failed to fetch test definition {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
+    Extension(project): Extension<Project>,
+) -> Result<(ETag, Json<TestDefinition>), ApiError> {
+    let definition = fetch_definition(&pool, &project, id).await?;
+    Ok((ETag(definition.version), Json(definition)))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/definitions",
+    tag = "test definitions",
+    request_body = CreateDefinitionRequest,
+    responses(
+        (status = 200, description = "The created test definition", body = TestDefinition, headers(
+            ("etag" = String, description = "Version of the definition, for `If-Match`")
+        )),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
 pub async fn create_definition(
     Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
     JsonBody(req): JsonBody<CreateDefinitionRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let id = Uuid::new_v4();
-    let now = chrono::Utc::now();
-    let description = req.description.unwrap_or_default();
-    let labels = req.labels.unwrap_or_default();
-
-    if let Err(e) = sqlx::query(
-        "INSERT INTO test_definitions (id, name, description, image, commands, created_at, executor_id, labels) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
-    )
-    .bind(id)
+) -> Result<(ETag, Json<TestDefinition>), ApiError> {
+    req.validate(&pool, &project).await?;
+
+    match sqlx::query_as::<_, DefinitionRow>(&format!(
+        "WITH definition AS (INSERT INTO test_definitions (id, name, description, image, commands, created_at, executor_id, labels, created_by, project_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10) RETURNING *), {} SELECT {} FROM definition",
+        record_definition_version("$9"),
+        DEFINITION_LISTING.columns
+    ))
+    .bind(Uuid::new_v4())
     .bind(&req.name)
-    .bind(&description)
+    .bind(req.description.unwrap_or_default())
     .bind(&req.image)
     .bind(&req.commands)
-    .bind(now)
+    .bind(chrono::Utc::now())
     .bind(req.executor_id)
-    .bind(&labels)
-    .execute(&pool)
-    .await {
-        tracing::error!("Failed to create test definition: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    .bind(req.labels.unwrap_or_default())
+    .bind(identity.actor())
+    .bind(project.id)
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(row) => {
+            let definition = definition_response(row);
+            Ok((ETag(definition.version), Json(definition)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create test definition: {}", e);
+            Err(ApiError::internal("Failed to create test definition"))
+        }
     }
-
-    Ok(Json(serde_json::json!({
-        "id": id,
-        "name": req.name,
-        "description": description,
-        "image": req.image,
-        "commands": req.commands,
-        "createdAt": now,
-        "executorId": req.executor_id,
-        "labels": labels
-    })))
 }
 
+/// Change some fields of a test definition; `PUT` is accepted as well
+#[utoipa::path(
+    patch,
+    path = "/api/v1/definitions/{id}",
+    tag = "test definitions",
+    params(
+        ("id" = Uuid, Path, description = "Test definition id"),
+        ("If-Match" = Option<String>, Header, description = "Only update if the definition is still at this `ETag`")
+    ),
+    request_body = UpdateDefinitionRequest,
+    responses(
+        (status = 200, description = "The updated test definition", body = TestDefinition, headers(
+            ("etag" = String, description = "New version of the definition")
+        )),
+        (status = 404, description = "No such definition in the project", body = ErrorResponse),
+        (status = 412, description = "The definition changed since the `If-Match` version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
 pub async fn update_definition(
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-    JsonBody(req): JsonBody<CreateDefinitionRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let description = req.description.unwrap_or_default();
-    let labels = req.labels.unwrap_or_default();
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
+    if_match: IfMatch,
+    JsonBody(req): JsonBody<UpdateDefinitionRequest>,
+) -> Result<(ETag, Json<TestDefinition>), ApiError> {
+    let current = fetch_definition(&pool, &project, id).await?;
+    let version = current.version;
+    if_match.check("Test definition", version)?;
+    let req = req.apply(current);
+    req.validate(&pool, &project).await?;
+
+    save_definition(&pool, &project, &identity, id, version, req).await
+}
 
-    match sqlx::query(
-        "UPDATE test_definitions SET name = $1, description = $2, image = $3, commands = $4, executor_id = $5, labels = $6 WHERE id = $7"
+/// SQL for a `recorded` CTE that stores the row of the `definition` CTE as a
+/// new version, made by `created_by`
+fn record_definition_version(created_by: &str) -> String {
+    format!(
+        "recorded AS (INSERT INTO test_definition_versions (definition_id, version, name, description, image, commands, executor_id, labels, created_at, created_by) SELECT id, version, name, description, image, commands, executor_id, COALESCE(labels, '{{}}'), updated_at, {created_by} FROM definition)"
     )
+}
+
+/// Overwrite a definition that is still at `version` with `req`, recording the result as its next version
+async fn save_definition(
+    pool: &PgPool,
+    project: &Project,
+    identity: &Identity,
+    id: Uuid,
+    version: i32,
+    req: CreateDefinitionRequest,
+) -> Result<(ETag, Json<TestDefinition>), ApiError> {
+    // Only applies if no other update got in since the definition was read
+    match sqlx::query_as::<_, DefinitionRow>(&format!(
+        "WITH definition AS (UPDATE test_definitions SET name = $1, description = $2, image = $3, commands = $4, executor_id = $5, labels = $6, version = version + 1, updated_at = NOW() WHERE id = $7 AND project_id = $8 AND version = $9 RETURNING *), {} SELECT {} FROM definition",
+        record_definition_version("$10"),
+        DEFINITION_LISTING.columns
+    ))
     .bind(&req.name)
-    .bind(&description)
+    .bind(req.description.unwrap_or_default())
     .bind(&req.image)
     .bind(&req.commands)
     .bind(req.executor_id)
-    .bind(&labels)
+    .bind(req.labels.unwrap_or_default())
     .bind(id)
-    .execute(&pool)
-    .await {
-        Ok(result) if result.rows_affected() == 0 => Err(StatusCode::NOT_FOUND),
-        Ok(_) => Ok(Json(serde_json::json!({
-            "id": id,
-            "name": req.name,
-            "description": description,
-            "image": req.image,
-            "commands": req.commands,
-            "executorId": req.executor_id,
-            "labels": labels
-        }))),
-        Err(e) => {
-            tracing::error!("Failed to update test definition {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
-    }
-}
-
-pub async fn delete_definition(
-    Path(id): Path<Uuid>,
-    Extension(pool): Extension<PgPool>,
-) -> Result<StatusCode, StatusCode> {
-    match sqlx::query("DELETE FROM test_definitions WHERE id = $1")
-        .bind(id)
-        .execute(&pool)
-        .await
+    .bind(project.id)
+    .bind(version)
+    .bind(identity.actor())
+    .fetch_optional(pool)
+    .await
     {
-        Ok(result) if result.rows_affected() == 0 => Err(StatusCode::NOT_FOUND),
-        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Ok(Some(row)) => {
+            let definition = definition_response(row);
+            Ok((ETag(definition.version), Json(definition)))
+        }
+        Ok(None) => Err(IfMatch::failed("Test definition")),
         Err(e) => {
-            tracing::error!("Failed to delete test definition {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to update test definition {}: {}", id, e);
+            Err(ApiError::internal("Failed to update test definition"))
         }
     }
 }
 
-pub async fn run_definition(
-    Path(id): Path<Uuid>,
-    Extension(pool): Extension<PgPool>,
-    JsonBody(req): JsonBody<RunDefinitionRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let definition = match sqlx::query_as::<_, (Uuid, String, String, Vec<String>, Option<Uuid>)>(
-        "SELECT id, name, image, commands, executor_id FROM test_definitions WHERE id = $1",
+type DefinitionVersionRow = (
+    Uuid,
+    i32,
+    String,
+    Option<String>,
+    String,
+    Vec<String>,
+    Option<Uuid>,
+    Vec<String>,
+    chrono::DateTime<chrono::Utc>,
+    Option<String>,
+);
+
+/// Versions of one of the project's definitions, oldest first: all of them, or
+/// only `version` and the one before it
+async fn fetch_definition_versions(
+    pool: &PgPool,
+    project: &Project,
+    id: Uuid,
+    version: Option<i32>,
+) -> Result<Vec<DefinitionVersion>, ApiError> {
+    let rows = match sqlx::query_as::<_, DefinitionVersionRow>(
+        "SELECT v.definition_id, v.version, v.name, v.description, v.image, v.commands, v.executor_id, v.labels, v.created_at, v.created_by FROM test_definition_versions v JOIN test_definitions d ON d.id = v.definition_id WHERE v.definition_id = $1 AND d.project_id = $2 AND ($3::int IS NULL OR v.version IN ($3, $3 - 1)) ORDER BY v.version",
     )
     .bind(id)
-    .fetch_optional(&pool)
+    .bind(project.id)
+    .bind(version)
+    .fetch_all(pool)
     .await
     {
-        Ok(Some(definition)) => definition,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Ok(rows) => rows,
         Err(e) => {
-            tracing::error!("Failed to fetch test definition {} for run: {}", id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!("Failed to fetch versions of test definition {}: {}", id, e);
+            return Err(ApiError::internal("Failed to fetch test definition versions"));
         }
     };
 
-    let (definition_id, definition_name, definition_image, definition_commands, executor_id) =
-        definition;
-    let run_uuid = Uuid::new_v4();
-    let now = chrono::Utc::now();
-    let run_name = req
-        .name
-        .unwrap_or_else(|| format!("{} - Manual Run", definition_name));
-    let image = req.image.unwrap_or(definition_image);
-    let commands = req.commands.unwrap_or(definition_commands);
+    let mut versions: Vec<DefinitionVersion> = Vec::with_capacity(rows.len());
+    for row in rows {
+        let mut version = definition_version_response(row);
+        if let Some(previous) = versions.last() {
+            version.changes = version.diff(previous);
+        }
+        versions.push(version);
+    }
+    Ok(versions)
+}
 
-    if commands.is_empty() {
-        return Err(StatusCode::BAD_REQUEST);
+/// One version of one of the project's definitions, with its changes
+async fn fetch_definition_version(
+    pool: &PgPool,
+    project: &Project,
+    id: Uuid,
+    version: i32,
+) -> Result<DefinitionVersion, ApiError> {
+    fetch_definition_versions(pool, project, id, Some(version))
+        .await?
+        .pop()
+        .filter(|found| found.version == version)
+        .ok_or_else(|| {
+            ApiError::not_found(format!(
+                "Version {version} of test definition {id} not found"
+            ))
+        })
+}
+
+fn definition_version_response(row: DefinitionVersionRow) -> DefinitionVersion {
+    let (
+        definition_id,
+        version,
+        name,
+        description,
+        image,
+        commands,
+        executor_id,
+        labels,
+        created_at,
+        created_by,
+    ) = row;
+
+    DefinitionVersion {
+        definition_id,
+        version,
+        name,
+        description: description.unwrap_or_default(),
+        image,
+        commands,
+        executor_id,
+        labels,
+        created_at,
+        created_by,
+        changes: Vec::new(),
     }
+}
 
-    if let Err(e) = sqlx::query(
-        "INSERT INTO test_runs (id, name, image, command, status, created_at, test_definition_id, executor_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+/// Every version of a test definition, newest first, with what changed in each
+#[utoipa::path(
+    get,
+    path = "/api/v1/definitions/{id}/versions",
+    tag = "test definitions",
+    params(("id" = Uuid, Path, description = "Test definition id")),
+    responses(
+        (status = 200, description = "The definition's versions", body = [DefinitionVersion]),
+        (status = 404, description = "No such definition in the project", body = ErrorResponse)
     )
-    .bind(run_uuid)
-    .bind(&run_name)
-    .bind(&image)
-    .bind(&commands)
-    .bind("running")
-    .bind(now)
-    .bind(definition_id)
-    .bind(executor_id)
-    .execute(&pool)
-    .await {
-        tracing::error!("Failed to insert run for definition {}: {}", definition_id, e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+)]
+pub async fn get_definition_versions(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<Vec<DefinitionVersion>>, ApiError> {
+    fetch_definition(&pool, &project, id).await?;
+    let mut versions = fetch_definition_versions(&pool, &project, id, None).await?;
+    versions.reverse();
+    Ok(Json(versions))
+}
+
+/// One version of a test definition, with what changed from the version before
+#[utoipa::path(
+    get,
+    path = "/api/v1/definitions/{id}/versions/{version}",
+    tag = "test definitions",
+    params(
+        ("id" = Uuid, Path, description = "Test definition id"),
+        ("version" = i32, Path, description = "Version of the definition")
+    ),
+    responses(
+        (status = 200, description = "The version", body = DefinitionVersion),
+        (status = 404, description = "No such definition or version in the project", body = ErrorResponse)
+    )
+)]
+pub async fn get_definition_version(
+    Path((id, version)): Path<(Uuid, i32)>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<DefinitionVersion>, ApiError> {
+    Ok(Json(
+        fetch_definition_version(&pool, &project, id, version).await?,
+    ))
+}
+
+/// Make an earlier version of a test definition current again, as a new version
+#[utoipa::path(
+    post,
+    path = "/api/v1/definitions/{id}/versions/{version}/rollback",
+    tag = "test definitions",
+    params(
+        ("id" = Uuid, Path, description = "Test definition id"),
+        ("version" = i32, Path, description = "Version to roll back to"),
+        ("If-Match" = Option<String>, Header, description = "Only roll back if the definition is still at this `ETag`")
+    ),
+    responses(
+        (status = 200, description = "The definition as it now is", body = TestDefinition, headers(
+            ("etag" = String, description = "New version of the definition")
+        )),
+        (status = 404, description = "No such definition or version in the project", body = ErrorResponse),
+        (status = 412, description = "The definition changed since the `If-Match` version", body = ErrorResponse),
+        (status = 422, description = "The version's executor no longer exists", body = ErrorResponse)
+    )
+)]
+pub async fn rollback_definition(
+    Path((id, version)): Path<(Uuid, i32)>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
+    if_match: IfMatch,
+) -> Result<(ETag, Json<TestDefinition>), ApiError> {
+    let current = fetch_definition(&pool, &project, id).await?;
+    if_match.check("Test definition", current.version)?;
+    let target = fetch_definition_version(&pool, &project, id, version).await?;
+
+    let req = CreateDefinitionRequest {
+        name: target.name,
+        description: Some(target.description),
+        image: target.image,
+        commands: target.commands,
+        executor_id: target.executor_id,
+        labels: Some(target.labels),
+    };
+    req.validate(&pool, &project).await?;
+
+    save_definition(&pool, &project, &identity, id, current.version, req).await
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/definitions/{id}",
+    tag = "test definitions",
+    params(("id" = Uuid, Path, description = "Test definition id")),
+    responses(
+        (status = 204, description = "The test definition was deleted"),
+        (status = 404, description = "No such definition in the project", body = ErrorResponse)
+    )
+)]
+pub async fn delete_definition(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM test_definitions WHERE id = $1 AND project_id = $2")
+        .bind(id)
+        .bind(project.id)
+        .execute(&pool)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => Err(ApiError::not_found(format!(
+            "Test definition {id} not found"
+        ))),
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete test definition {}: {}", id, e);
+            Err(ApiError::internal("Failed to delete test definition"))
+        }
     }
+}
 
-    let job_name = format!("test-run-{run_uuid}");
-    let k8s_command: Vec<String> = if commands.len() == 1 {
-        vec!["sh".into(), "-c".into(), commands[0].clone()]
-    } else {
-        vec!["sh".into(), "-c".into(), commands.join(" && ")]
+/// Start a run of a test definition, or one of its earlier versions, optionally
+/// overriding its name, image or commands
+#[utoipa::path(
+    post,
+    path = "/api/v1/definitions/{id}/run",
+    tag = "test definitions",
+    params(("id" = Uuid, Path, description = "Test definition id")),
+    request_body = RunDefinitionRequest,
+    responses(
+        (status = 200, description = "The run, failed if its job could not be created", body = TestRun),
+        (status = 404, description = "No such definition in the project", body = ErrorResponse),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
+pub async fn run_definition(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
+    JsonBody(req): JsonBody<RunDefinitionRequest>,
+) -> Result<Json<TestRun>, ApiError> {
+    req.validate()?;
+    let definition = match sqlx::query_as::<_, (Uuid, String, String, Vec<String>, Option<Uuid>, i32)>(
+        "SELECT v.definition_id, v.name, v.image, v.commands, v.executor_id, v.version FROM test_definition_versions v JOIN test_definitions d ON d.id = v.definition_id WHERE v.definition_id = $1 AND d.project_id = $2 AND v.version = COALESCE($3, d.version)",
+    )
+    .bind(id)
+    .bind(project.id)
+    .bind(req.version)
+    .fetch_optional(&pool)
+    .await
+    {
+        Ok(Some(definition)) => definition,
+        Ok(None) => {
+            return Err(ApiError::not_found(match req.version {
+                Some(version) => format!("Version {version} of test definition {id} not found"),
+                None => format!("Test definition {id} not found"),
+            }))
+        }
+        Err(e) => {
+            tracing::error!("Failed to fetch test definition {} for run: {}", id, e);
+            return Err(ApiError::internal("Failed to fetch test definition"));
+        }
     };
 
-    let mut status = "running";
-    let mut job_created = false;
-    match KubernetesClient::new().await {
-        Ok(client) => {
-            if client
-                .submit_job(&job_name, &image, &k8s_command)
-                .await
-                .is_ok()
-            {
-                job_created = true;
-                let pool_clone = pool.clone();
-                let job_name_clone = job_name.clone();
-                tokio::spawn(async move {
-                    if let Err(e) =
-                        monitor_job_and_update_status(run_uuid, job_name_clone, pool_clone).await
-                    {
-                        tracing::error!("Job monitor failed for run {}: {}", run_uuid, e);
-                    }
-                });
-            } else {
-                status = "failed";
-                sqlx::query("UPDATE test_runs SET status = $1 WHERE id = $2")
-                    .bind(status)
-                    .bind(run_uuid)
-                    .execute(&pool)
-                    .await
-                    .ok();
-            }
+    let (
+        definition_id,
+        definition_name,
+        definition_image,
+        definition_commands,
+        executor_id,
+        definition_version,
+    ) = definition;
+    // A run whose image or commands differ from the definition's did not execute any version of it
+    let definition_version =
+        req.executed_version(definition_version, &definition_image, &definition_commands);
+    let run_name = req
+        .name
+        .unwrap_or_else(|| format!("{} - Manual Run", definition_name));
+    let image = req.image.unwrap_or(definition_image);
+    let commands = req.commands.unwrap_or(definition_commands);
+
+    let run = match launch_run(
+        &pool,
+        &project,
+        &run_name,
+        &image,
+        &commands,
+        RunAttribution {
+            definition_id: Some(definition_id),
+            definition_version,
+            executor_id,
+            created_by: identity.actor(),
+            ..Default::default()
+        },
+    )
+    .await
+    {
+        Ok(run) => run,
+        Err(e) => {
+            tracing::error!(
+                "Failed to insert run for definition {}: {}",
+                definition_id,
+                e
+            );
+            return Err(ApiError::internal("Failed to insert test run"));
         }
+    };
+
+    Ok(Json(run))
+}
+
+type ExecutorRow = (
+    Uuid,
+    String,
+    Option<String>,
+    String,
+    String,
+    Vec<String>,
+    Vec<String>,
+    Option<String>,
+    i32,
+    chrono::DateTime<chrono::Utc>,
+);
+
+/// Executors by name, in name order since they have no creation time
+const EXECUTOR_LISTING: Listing = Listing {
+    name: "test executors",
+    table: "test_executors",
+    columns: "id, name, description, image, default_command, supported_file_types, environment_variables, icon, version, updated_at",
+    filter: "project_id = $1 AND ($10::text IS NULL OR strpos(lower(name), lower($10)) > 0)",
+    filters: &[],
+    order: BY_NAME,
+};
+
+fn executor_response(row: ExecutorRow) -> Executor {
+    let (
+        id,
+        name,
+        description,
+        image,
+        default_command,
+        supported_file_types,
+        environment_variables,
+        icon,
+        version,
+        updated_at,
+    ) = row;
+
+    Executor {
+        id,
+        name,
+        image,
+        description,
+        default_command: Some(default_command).filter(|command| !command.is_empty()),
+        supported_file_types,
+        environment_variables,
+        icon,
+        version,
+        updated_at,
+    }
+}
+
+/// One of the project's executors
+async fn fetch_executor(pool: &PgPool, project: &Project, id: Uuid) -> Result<Executor, ApiError> {
+    match sqlx::query_as::<_, ExecutorRow>(&format!(
+        "SELECT {} FROM test_executors WHERE id = $1 AND project_id = $2",
+        EXECUTOR_LISTING.columns
+    ))
+    .bind(id)
+    .bind(project.id)
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(Some(row)) => Ok(executor_response(row)),
+        Ok(None) => Err(ApiError::not_found(format!("Test executor {id} not found"))),
         Err(e) => {
-            tracing::error!("Failed to initialize Kubernetes client: {}", e);
-            status = "failed";
-            sqlx::query("UPDATE test_runs SET status = $1 WHERE id = $2")
-                .bind(status)
-                .bind(run_uuid)
-                .execute(&pool)
-                .await
-                .ok();
-        }
-    }
-
-    Ok(Json(serde_json::json!({
-        "id": run_uuid,
-        "name": run_name,
-        "image": image,
-        "command": commands,
-        "status": status,
-        "createdAt": now,
-        "testDefinitionId": definition_id,
-        "executorId": executor_id,
-        "jobName": job_name,
-        "jobCreated": job_created
-    })))
+            tracing::error!("Failed to fetch test executor {}: {}", id, e);
+            Err(ApiError::internal("Failed to fetch test executor"))
+        }
+    }
 }
 
+/// List the project's executors by name
+#[utoipa::path(
+    get,
+    path = "/api/v1/executors",
+    tag = "test executors",
+    params(ListQuery),
+    responses((status = 200, description = "A page of executors", body = [Executor], headers(
+        ("x-total-count" = i64, description = "Executors matching the filters"),
+        ("x-next-cursor" = String, description = "Cursor of the next page, if there is one")
+    )))
+)]
 pub async fn get_executors(
+    Query(query): Query<ListQuery>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Page<Executor>, ApiError> {
+    let (rows, total, limit) = EXECUTOR_LISTING
+        .fetch::<ExecutorRow>(&pool, project.id, &query)
+        .await?;
+    Ok(Page::new(
+        rows,
+        limit,
+        total,
+        |row| Cursor::new(row.1.clone(), row.0),
+        executor_response,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/executors/{id}",
+    tag = "test executors",
+    params(("id" = Uuid, Path, description = "Executor id")),
+    responses(
+        (status = 200, description = "The executor", body = Executor, headers(
+            ("etag" = String, description = "Version of the executor, for `If-Match`")
+        )),
+        (status = 404, description = "No such executor in the project", body = ErrorResponse)
+    )
+)]
+pub async fn get_executor(
+    Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, Option<String>, String, String, Vec<String>, Vec<String>, Option<String>)>(
-        "SELECT id, name, description, image, default_command, supported_file_types, environment_variables, icon FROM test_executors ORDER BY name"
+    Extension(project): Extension<Project>,
+) -> Result<(ETag, Json<Executor>), ApiError> {
+    let executor = fetch_executor(&pool, &project, id).await?;
+    Ok((ETag(executor.version), Json(executor)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/executors",
+    tag = "test executors",
+    request_body = CreateExecutorRequest,
+    responses(
+        (status = 200, description = "The created executor", body = Executor, headers(
+            ("etag" = String, description = "Version of the executor, for `If-Match`")
+        )),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
     )
-    .fetch_all(&pool)
+)]
+pub async fn create_executor(
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    JsonBody(req): JsonBody<CreateExecutorRequest>,
+) -> Result<(ETag, Json<Executor>), ApiError> {
+    req.validate()?;
+
+    match sqlx::query_as::<_, ExecutorRow>(&format!(
+        "INSERT INTO test_executors (id, name, description, image, default_command, supported_file_types, environment_variables, icon, project_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING {}",
+        EXECUTOR_LISTING.columns
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&req.name)
+    .bind(&req.description)
+    .bind(&req.image)
+    .bind(normalize_default_command(&req))
+    .bind(req.supported_file_types.clone().unwrap_or_default())
+    .bind(normalize_environment_variables(&req))
+    .bind(&req.icon)
+    .bind(project.id)
+    .fetch_one(&pool)
     .await
     {
-        Ok(rows) => {
-            let executors: Vec<serde_json::Value> = rows
-                .into_iter()
-                .map(|(id, name, description, image, default_command, supported_file_types, environment_variables, icon)| {
-                    let command = if default_command.is_empty() {
-                        Vec::<String>::new()
-                    } else {
-                        vec![default_command.clone()]
-                    };
-                    let env = env_vars_to_object(&environment_variables);
-                    serde_json::json!({
-                        "id": id,
-                        "name": name,
-                        "description": description,
-                        "image": image,
-                        "defaultCommand": default_command,
-                        "command": command,
-                        "supportedFileTypes": supported_file_types,
-                        "environmentVariables": environment_variables,
-                        "env": env,
-                        "icon": icon
-                    })
-                })
-                .collect();
-            Ok(Json(executors))
+        Ok(row) => {
+            let executor = executor_response(row);
+            Ok((ETag(executor.version), Json(executor)))
         }
         Err(e) => {
-            tracing::error!("Failed to fetch test executors: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to create test executor: {}", e);
+            Err(ApiError::internal("Failed to create test executor"))
         }
     }
 }
 
-pub async fn get_executor(
+/// Change some fields of an executor; `PUT` is accepted as well
+#[utoipa::path(
+    patch,
+    path = "/api/v1/executors/{id}",
+    tag = "test executors",
+    params(
+        ("id" = Uuid, Path, description = "Executor id"),
+        ("If-Match" = Option<String>, Header, description = "Only update if the executor is still at this `ETag`")
+    ),
+    request_body = UpdateExecutorRequest,
+    responses(
+        (status = 200, description = "The updated executor", body = Executor, headers(
+            ("etag" = String, description = "New version of the executor")
+        )),
+        (status = 404, description = "No such executor in the project", body = ErrorResponse),
+        (status = 412, description = "The executor changed since the `If-Match` version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
+pub async fn update_executor(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    if_match: IfMatch,
+    JsonBody(req): JsonBody<UpdateExecutorRequest>,
+) -> Result<(ETag, Json<Executor>), ApiError> {
+    let current = fetch_executor(&pool, &project, id).await?;
+    let version = current.version;
+    if_match.check("Test executor", version)?;
+    let req = req.apply(current);
+    req.validate()?;
+
+    // Only applies if no other update got in since the executor was read
+    match sqlx::query_as::<_, ExecutorRow>(&format!(
+        "UPDATE test_executors SET name = $1, description = $2, image = $3, default_command = $4, supported_file_types = $5, environment_variables = $6, icon = $7, version = version + 1, updated_at = NOW() WHERE id = $8 AND project_id = $9 AND version = $10 RETURNING {}",
+        EXECUTOR_LISTING.columns
+    ))
+    .bind(&req.name)
+    .bind(&req.description)
+    .bind(&req.image)
+    .bind(normalize_default_command(&req))
+    .bind(req.supported_file_types.clone().unwrap_or_default())
+    .bind(normalize_environment_variables(&req))
+    .bind(&req.icon)
+    .bind(id)
+    .bind(project.id)
+    .bind(version)
+    .fetch_optional(&pool)
+    .await
+    {
+        Ok(Some(row)) => {
+            let executor = executor_response(row);
+            Ok((ETag(executor.version), Json(executor)))
+        }
+        Ok(None) => Err(IfMatch::failed("Test executor")),
+        Err(e) => {
+            tracing::error!("Failed to update test executor {}: {}", id, e);
+            Err(ApiError::internal("Failed to update test executor"))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/executors/{id}",
+    tag = "test executors",
+    params(("id" = Uuid, Path, description = "Executor id")),
+    responses(
+        (status = 204, description = "The executor was deleted"),
+        (status = 404, description = "No such executor in the project", body = ErrorResponse)
+    )
+)]
+pub async fn delete_executor(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM test_executors WHERE id = $1 AND project_id = $2")
+        .bind(id)
+        .bind(project.id)
+        .execute(&pool)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            Err(ApiError::not_found(format!("Test executor {id} not found")))
+        }
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete test executor {}: {}", id, e);
+            Err(ApiError::internal("Failed to delete test executor"))
+        }
+    }
+}
+
+fn normalize_default_command(req: &CreateExecutorRequest) -> String {
+    req.default_command
+        .clone()
+        .or_else(|| {
+            req.command.as_ref().map(|command| {
+                command
+                    .iter()
+                    .map(String::as_str)
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+        })
+        .unwrap_or_default()
+}
+
+fn normalize_environment_variables(req: &CreateExecutorRequest) -> Vec<String> {
+    if let Some(vars) = &req.environment_variables {
+        return vars.clone();
+    }
+
+    match &req.env {
+        Some(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
+        _ => Vec::new(),
+    }
+}
+
+type SuiteRow = (
+    Uuid,
+    String,
+    Option<String>,
+    String,
+    Vec<String>,
+    Vec<Uuid>,
+    chrono::DateTime<chrono::Utc>,
+    Option<String>,
+    i32,
+    chrono::DateTime<chrono::Utc>,
+);
+
+/// Suites by contained definition, label, creation time and name
+const SUITE_LISTING: Listing = Listing {
+    name: "test suites",
+    table: "test_suites",
+    columns: "id, name, description, execution_mode, labels, test_definition_ids, created_at, created_by, version, updated_at",
+    filter: "project_id = $1 AND ($4::uuid IS NULL OR $4 = ANY(test_definition_ids)) AND ($7::text IS NULL OR $7 = ANY(labels)) AND ($8::timestamptz IS NULL OR created_at >= $8) AND ($9::timestamptz IS NULL OR created_at < $9) AND ($10::text IS NULL OR strpos(lower(name), lower($10)) > 0)",
+    filters: &["definitionId", "label", "createdAfter", "createdBefore"],
+    order: NEWEST_FIRST,
+};
+
+fn suite_response(row: SuiteRow) -> TestSuite {
+    let (
+        id,
+        name,
+        description,
+        execution_mode,
+        labels,
+        definition_ids,
+        created_at,
+        created_by,
+        version,
+        updated_at,
+    ) = row;
+
+    TestSuite {
+        id,
+        name,
+        description: description.unwrap_or_default(),
+        definition_ids,
+        created_at,
+        execution_mode,
+        labels,
+        created_by,
+        version,
+        updated_at,
+    }
+}
+
+/// One of the project's test suites
+async fn fetch_suite(pool: &PgPool, project: &Project, id: Uuid) -> Result<TestSuite, ApiError> {
+    match sqlx::query_as::<_, SuiteRow>(&format!(
+        "SELECT {} FROM test_suites WHERE id = $1 AND project_id = $2",
+        SUITE_LISTING.columns
+    ))
+    .bind(id)
+    .bind(project.id)
+    .fetch_optional(pool)
+    .await
+    {
+        Ok(Some(row)) => Ok(suite_response(row)),
+        Ok(None) => Err(ApiError::not_found(format!("Test suite {id} not found"))),
+        Err(e) => {
+            tracing::error!("Failed to fetch test suite {}: {}", id, e);
+            Err(ApiError::internal("Failed to fetch test suite"))
+        }
+    }
+}
+
+/// List the project's test suites, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/suites",
+    tag = "test suites",
+    params(ListQuery),
+    responses((status = 200, description = "A page of test suites", body = [TestSuite], headers(
+        ("x-total-count" = i64, description = "Suites matching the filters"),
+        ("x-next-cursor" = String, description = "Cursor of the next page, if there is one")
+    )))
+)]
+pub async fn get_suites(
+    Query(query): Query<ListQuery>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Page<TestSuite>, ApiError> {
+    let (rows, total, limit) = SUITE_LISTING
+        .fetch::<SuiteRow>(&pool, project.id, &query)
+        .await?;
+    Ok(Page::new(
+        rows,
+        limit,
+        total,
+        |row| Cursor::created_at(row.6, row.0),
+        suite_response,
+    ))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/suites/{id}",
+    tag = "test suites",
+    params(("id" = Uuid, Path, description = "Test suite id")),
+    responses(
+        (status = 200, description = "The test suite", body = TestSuite, headers(
+            ("etag" = String, description = "Version of the suite, for `If-Match`")
+        )),
+        (status = 404, description = "No such suite in the project", body = ErrorResponse)
+    )
+)]
+pub async fn get_suite(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<(ETag, Json<TestSuite>), ApiError> {
+    let suite = fetch_suite(&pool, &project, id).await?;
+    Ok((ETag(suite.version), Json(suite)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/v1/suites",
+    tag = "test suites",
+    request_body = CreateSuiteRequest,
+    responses(
+        (status = 200, description = "The created test suite", body = TestSuite, headers(
+            ("etag" = String, description = "Version of the suite, for `If-Match`")
+        )),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
+pub async fn create_suite(
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
+    JsonBody(req): JsonBody<CreateSuiteRequest>,
+) -> Result<(ETag, Json<TestSuite>), ApiError> {
+    req.validate(&pool, &project).await?;
+
+    match sqlx::query_as::<_, SuiteRow>(&format!(
+        "INSERT INTO test_suites (id, name, description, execution_mode, labels, test_definition_ids, created_at, created_by, project_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9) RETURNING {}",
+        SUITE_LISTING.columns
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&req.name)
+    .bind(req.description.unwrap_or_default())
+    .bind(&req.execution_mode)
+    .bind(req.labels.unwrap_or_default())
+    .bind(&req.definition_ids)
+    .bind(chrono::Utc::now())
+    .bind(identity.actor())
+    .bind(project.id)
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(row) => {
+            let suite = suite_response(row);
+            Ok((ETag(suite.version), Json(suite)))
+        }
+        Err(e) => {
+            tracing::error!("Failed to create test suite: {}", e);
+            Err(ApiError::internal("Failed to create test suite"))
+        }
+    }
+}
+
+/// Change some fields of a test suite; `PUT` is accepted as well
+#[utoipa::path(
+    patch,
+    path = "/api/v1/suites/{id}",
+    tag = "test suites",
+    params(
+        ("id" = Uuid, Path, description = "Test suite id"),
+        ("If-Match" = Option<String>, Header, description = "Only update if the suite is still at this `ETag`")
+    ),
+    request_body = UpdateSuiteRequest,
+    responses(
+        (status = 200, description = "The updated test suite", body = TestSuite, headers(
+            ("etag" = String, description = "New version of the suite")
+        )),
+        (status = 404, description = "No such suite in the project", body = ErrorResponse),
+        (status = 412, description = "The suite changed since the `If-Match` version", body = ErrorResponse),
+        (status = 422, description = "Invalid fields", body = ErrorResponse)
+    )
+)]
+pub async fn update_suite(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    if_match: IfMatch,
+    JsonBody(req): JsonBody<UpdateSuiteRequest>,
+) -> Result<(ETag, Json<TestSuite>), ApiError> {
+    let current = fetch_suite(&pool, &project, id).await?;
+    let version = current.version;
+    if_match.check("Test suite", version)?;
+    let req = req.apply(current);
+    req.validate(&pool, &project).await?;
+
+    // Only applies if no other update got in since the suite was read
+    match sqlx::query_as::<_, SuiteRow>(&format!(
+        "UPDATE test_suites SET name = $1, description = $2, execution_mode = $3, labels = $4, test_definition_ids = $5, version = version + 1, updated_at = NOW() WHERE id = $6 AND project_id = $7 AND version = $8 RETURNING {}",
+        SUITE_LISTING.columns
+    ))
+    .bind(&req.name)
+    .bind(req.description.unwrap_or_default())
+    .bind(&req.execution_mode)
+    .bind(req.labels.unwrap_or_default())
+    .bind(&req.definition_ids)
+    .bind(id)
+    .bind(project.id)
+    .bind(version)
+    .fetch_optional(&pool)
+    .await
+    {
+        Ok(Some(row)) => {
+            let suite = suite_response(row);
+            Ok((ETag(suite.version), Json(suite)))
+        }
+        Ok(None) => Err(IfMatch::failed("Test suite")),
+        Err(e) => {
+            tracing::error!("Failed to update test suite {}: {}", id, e);
+            Err(ApiError::internal("Failed to update test suite"))
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/suites/{id}",
+    tag = "test suites",
+    params(("id" = Uuid, Path, description = "Test suite id")),
+    responses(
+        (status = 204, description = "The test suite was deleted"),
+        (status = 404, description = "No such suite in the project", body = ErrorResponse)
+    )
+)]
+pub async fn delete_suite(
     Path(id): Path<Uuid>,
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, Option<String>, String, String, Vec<String>, Vec<String>, Option<String>)>(
-        "SELECT id, name, description, image, default_command, supported_file_types, environment_variables, icon FROM test_executors WHERE id = $1"
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM test_suites WHERE id = $1 AND project_id = $2")
+        .bind(id)
+        .bind(project.id)
+        .execute(&pool)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            Err(ApiError::not_found(format!("Test suite {id} not found")))
+        }
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete test suite {}: {}", id, e);
+            Err(ApiError::internal("Failed to delete test suite"))
+        }
+    }
+}
+
+/// Start a run of every definition in a suite
+#[utoipa::path(
+    post,
+    path = "/api/v1/suites/{id}/run",
+    tag = "test suites",
+    params(("id" = Uuid, Path, description = "Test suite id")),
+    responses(
+        (status = 200, description = "The runs started", body = SuiteRun),
+        (status = 400, description = "The suite has no definitions", body = ErrorResponse),
+        (status = 404, description = "No such suite in the project", body = ErrorResponse)
+    )
+)]
+pub async fn run_suite(
+    Path(suite_id): Path<String>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Json<SuiteRun>, ApiError> {
+    // Parse the suite_id as UUID
+    let suite_uuid = match Uuid::parse_str(&suite_id) {
+        Ok(uuid) => uuid,
+        Err(_) => {
+            return Err(ApiError::bad_request(format!(
+                "Invalid test suite id {suite_id}"
+            )))
+        }
+    };
+
+    // First, get the suite details
+    let suite = match sqlx::query_as::<_, (Uuid, String, Option<String>, String, Vec<String>, Vec<Uuid>, chrono::DateTime<chrono::Utc>)>(
+        "SELECT id, name, description, execution_mode, labels, test_definition_ids, created_at FROM test_suites WHERE id = $1 AND project_id = $2"
+    )
+    .bind(suite_uuid)
+    .bind(project.id)
+    .fetch_optional(&pool)
+    .await
+    {
+        Ok(Some(suite)) => suite,
+        Ok(None) => return Err(ApiError::not_found(format!("Test suite {suite_id} not found"))),
+        Err(e) => {
+            tracing::error!("Failed to fetch suite {}: {}", suite_id, e);
+            return Err(ApiError::internal("Failed to fetch test suite"));
+        }
+    };
+
+    let (
+        suite_uuid,
+        suite_name,
+        _description,
+        execution_mode,
+        _labels,
+        test_definition_ids,
+        _created_at,
+    ) = suite;
+
+    // Get all test definitions for this suite
+    let definitions = match get_suite_definitions(&pool, project.id, &test_definition_ids).await {
+        Ok(defs) => defs,
+        Err(e) => {
+            tracing::error!("Failed to get definitions for suite {}: {}", suite_id, e);
+            return Err(ApiError::internal("Failed to fetch test definitions"));
+        }
+    };
+
+    if definitions.is_empty() {
+        return Err(
+            ApiError::bad_request("No test definitions found for this suite").with_details(
+                serde_json::json!({
+                    "suiteId": suite_uuid,
+                    "suiteName": suite_name
+                }),
+            ),
+        );
+    }
+
+    // Create runs for each definition
+    let mut created_runs = Vec::new();
+
+    for def in definitions {
+        let run_name = format!("{} - {}", suite_name, def.name);
+
+        let run = match launch_run(
+            &pool,
+            &project,
+            &run_name,
+            &def.image,
+            &def.commands,
+            RunAttribution {
+                definition_id: Some(def.id),
+                definition_version: Some(def.version),
+                created_by: identity.actor(),
+                ..Default::default()
+            },
+        )
+        .await
+        {
+            Ok(run) => run,
+            Err(e) => {
+                tracing::error!("Failed to insert test run for definition {}: {}", def.id, e);
+                continue;
+            }
+        };
+
+        created_runs.push(run);
+
+        // If sequential mode, we could add delays or wait for completion here
+        // For now, we'll create all jobs in parallel regardless of execution_mode
+    }
+
+    Ok(Json(SuiteRun {
+        suite_id: suite_uuid,
+        suite_name,
+        execution_mode,
+        runs: created_runs,
+    }))
+}
+
+type ScheduleRow = (
+    Uuid,
+    String,
+    String,
+    String,
+    Option<Uuid>,
+    Option<Uuid>,
+    String,
+    i32,
+    i32,
+    bool,
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<chrono::DateTime<chrono::Utc>>,
+    chrono::DateTime<chrono::Utc>,
+);
+
+const SCHEDULE_COLUMNS: &str = "id, name, cron_expression, timezone, test_definition_id, test_suite_id, concurrency_policy, successful_runs_history_limit, failed_runs_history_limit, enabled, last_run_at, next_run_at, created_at";
+
+fn schedule_response(row: ScheduleRow) -> TestSchedule {
+    let (
+        id,
+        name,
+        cron_expression,
+        timezone,
+        definition_id,
+        suite_id,
+        concurrency_policy,
+        successful_runs_history_limit,
+        failed_runs_history_limit,
+        enabled,
+        last_run_at,
+        next_run_at,
+        created_at,
+    ) = row;
+
+    TestSchedule {
+        id,
+        name,
+        cron_expression,
+        timezone,
+        definition_id,
+        suite_id,
+        concurrency_policy: concurrency_policy.parse().unwrap_or_default(),
+        successful_runs_history_limit,
+        failed_runs_history_limit,
+        enabled,
+        last_run_at,
+        next_run_at,
+        created_at,
+    }
+}
+
+/// Validate a schedule request and compute its next run time
+fn next_schedule_run(
+    req: &CreateScheduleRequest,
+) -> Result<Option<chrono::DateTime<chrono::Utc>>, ApiError> {
+    if req.definition_id.is_some() == req.suite_id.is_some() {
+        return Err(ApiError::bad_request(
+            "A schedule needs either definitionId or suiteId",
+        ));
+    }
+    if req.successful_runs_history_limit < 0 || req.failed_runs_history_limit < 0 {
+        return Err(ApiError::bad_request("History limits cannot be negative"));
+    }
+
+    let schedule = CronSchedule::parse(&req.cron_expression, Some(&req.timezone)).map_err(|e| {
+        tracing::warn!("Rejected schedule '{}': {}", req.name, e);
+        ApiError::bad_request(format!("Invalid cron expression: {e}"))
+    })?;
+
+    Ok(schedule.next_after(chrono::Utc::now()))
+}
+
+/// Reject schedules whose target definition or suite is not in the project
+async fn check_schedule_target(
+    pool: &PgPool,
+    project: &Project,
+    req: &CreateScheduleRequest,
+) -> Result<(), ApiError> {
+    let in_project = sqlx::query_scalar::<_, bool>(
+        "SELECT EXISTS (SELECT 1 FROM test_definitions WHERE id = $1 AND project_id = $3) OR EXISTS (SELECT 1 FROM test_suites WHERE id = $2 AND project_id = $3)",
+    )
+    .bind(req.definition_id)
+    .bind(req.suite_id)
+    .bind(project.id)
+    .fetch_one(pool)
+    .await
+    .map_err(schedule_write_error)?;
+
+    if in_project {
+        Ok(())
+    } else {
+        Err(ApiError::bad_request(
+            "The schedule's test definition or suite is not in this project",
+        ))
+    }
+}
+
+/// Map a schedule write error, treating a missing target as a bad request
+fn schedule_write_error(e: sqlx::Error) -> ApiError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_foreign_key_violation() => {
+            ApiError::bad_request("The schedule's test definition or suite does not exist")
+        }
+        _ => {
+            tracing::error!("Failed to write test schedule: {}", e);
+            ApiError::internal("Failed to write test schedule")
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules",
+    tag = "schedules",
+    responses((status = 200, description = "The project's schedules, newest first", body = [TestSchedule]))
+)]
+pub async fn get_schedules(
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<Vec<TestSchedule>>, ApiError> {
+    match sqlx::query_as::<_, ScheduleRow>(&format!(
+        "SELECT {SCHEDULE_COLUMNS} FROM test_schedules WHERE project_id = $1 ORDER BY created_at DESC"
+    ))
+    .bind(project.id)
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(Json(rows.into_iter().map(schedule_response).collect())),
+        Err(e) => {
+            tracing::error!("Failed to fetch test schedules: {}", e);
+            Err(ApiError::internal("Failed to fetch test schedules"))
+        }
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    params(("id" = Uuid, Path, description = "Schedule id")),
+    responses(
+        (status = 200, description = "The schedule", body = TestSchedule),
+        (status = 404, description = "No such schedule in the project", body = ErrorResponse)
     )
+)]
+pub async fn get_schedule(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<TestSchedule>, ApiError> {
+    match sqlx::query_as::<_, ScheduleRow>(&format!(
+        "SELECT {SCHEDULE_COLUMNS} FROM test_schedules WHERE id = $1 AND project_id = $2"
+    ))
     .bind(id)
+    .bind(project.id)
     .fetch_optional(&pool)
     .await
     {
-        Ok(Some((id, name, description, image, default_command, supported_file_types, environment_variables, icon))) => {
-            Ok(Json(serde_json::json!({
-                "id": id,
-                "name": name,
-                "description": description,
-                "image": image,
-                "defaultCommand": default_command,
-                "command": if default_command.is_empty() { Vec::<String>::new() } else { vec![default_command] },
-                "supportedFileTypes": supported_file_types,
-                "environmentVariables": environment_variables,
-                "env": env_vars_to_object(&environment_variables),
-                "icon": icon
-            })))
-        }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Ok(Some(row)) => Ok(Json(schedule_response(row))),
+        Ok(None) => Err(ApiError::not_found(format!("Schedule {id} not found"))),
         Err(e) => {
-            tracing::error!("Failed to fetch test executor {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to fetch test schedule {}: {}", id, e);
+            Err(ApiError::internal("Failed to fetch test schedule"))
         }
     }
 }
 
-pub async fn create_executor(
+#[utoipa::path(
+    post,
+    path = "/api/v1/schedules",
+    tag = "schedules",
+    request_body = CreateScheduleRequest,
+    responses(
+        (status = 200, description = "The created schedule", body = TestSchedule),
+        (status = 400, description = "Invalid cron expression or target", body = ErrorResponse)
+    )
+)]
+pub async fn create_schedule(
     Extension(pool): Extension<PgPool>,
-    JsonBody(req): JsonBody<CreateExecutorRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let id = Uuid::new_v4();
-    let default_command = normalize_default_command(&req);
-    let environment_variables = normalize_environment_variables(&req);
-    let supported_file_types = req.supported_file_types.unwrap_or_default();
+    Extension(project): Extension<Project>,
+    JsonBody(req): JsonBody<CreateScheduleRequest>,
+) -> Result<Json<TestSchedule>, ApiError> {
+    let next_run_at = next_schedule_run(&req)?;
+    check_schedule_target(&pool, &project, &req).await?;
+
+    let row = sqlx::query_as::<_, ScheduleRow>(&format!(
+        "INSERT INTO test_schedules (id, name, cron_expression, timezone, test_definition_id, test_suite_id, concurrency_policy, successful_runs_history_limit, failed_runs_history_limit, enabled, next_run_at, created_at, project_id) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13) RETURNING {SCHEDULE_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&req.name)
+    .bind(&req.cron_expression)
+    .bind(&req.timezone)
+    .bind(req.definition_id)
+    .bind(req.suite_id)
+    .bind(req.concurrency_policy.as_str())
+    .bind(req.successful_runs_history_limit)
+    .bind(req.failed_runs_history_limit)
+    .bind(req.enabled)
+    .bind(next_run_at)
+    .bind(chrono::Utc::now())
+    .bind(project.id)
+    .fetch_one(&pool)
+    .await
+    .map_err(schedule_write_error)?;
 
-    if let Err(e) = sqlx::query(
-        "INSERT INTO test_executors (id, name, description, image, default_command, supported_file_types, environment_variables, icon) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)"
+    Ok(Json(schedule_response(row)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    params(("id" = Uuid, Path, description = "Schedule id")),
+    request_body = CreateScheduleRequest,
+    responses(
+        (status = 200, description = "The updated schedule", body = TestSchedule),
+        (status = 400, description = "Invalid cron expression or target", body = ErrorResponse),
+        (status = 404, description = "No such schedule in the project", body = ErrorResponse)
     )
+)]
+pub async fn update_schedule(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    JsonBody(req): JsonBody<CreateScheduleRequest>,
+) -> Result<Json<TestSchedule>, ApiError> {
+    let next_run_at = next_schedule_run(&req)?;
+    check_schedule_target(&pool, &project, &req).await?;
+
+    let row = sqlx::query_as::<_, ScheduleRow>(&format!(
+        "UPDATE test_schedules SET name = $1, cron_expression = $2, timezone = $3, test_definition_id = $4, test_suite_id = $5, concurrency_policy = $6, successful_runs_history_limit = $7, failed_runs_history_limit = $8, enabled = $9, next_run_at = $10 WHERE id = $11 AND project_id = $12 RETURNING {SCHEDULE_COLUMNS}"
+    ))
+    .bind(&req.name)
+    .bind(&req.cron_expression)
+    .bind(&req.timezone)
+    .bind(req.definition_id)
+    .bind(req.suite_id)
+    .bind(req.concurrency_policy.as_str())
+    .bind(req.successful_runs_history_limit)
+    .bind(req.failed_runs_history_limit)
+    .bind(req.enabled)
+    .bind(next_run_at)
     .bind(id)
+    .bind(project.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(schedule_write_error)?;
+
+    match row {
+        Some(row) => Ok(Json(schedule_response(row))),
+        None => Err(ApiError::not_found(format!("Schedule {id} not found"))),
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/schedules/{id}",
+    tag = "schedules",
+    params(("id" = Uuid, Path, description = "Schedule id")),
+    responses(
+        (status = 204, description = "The schedule was deleted"),
+        (status = 404, description = "No such schedule in the project", body = ErrorResponse)
+    )
+)]
+pub async fn delete_schedule(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM test_schedules WHERE id = $1 AND project_id = $2")
+        .bind(id)
+        .bind(project.id)
+        .execute(&pool)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            Err(ApiError::not_found(format!("Schedule {id} not found")))
+        }
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to delete test schedule {}: {}", id, e);
+            Err(ApiError::internal("Failed to delete test schedule"))
+        }
+    }
+}
+
+type TokenRow = (
+    Uuid,
+    String,
+    String,
+    Vec<String>,
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<chrono::DateTime<chrono::Utc>>,
+    Option<chrono::DateTime<chrono::Utc>>,
+);
+
+const TOKEN_COLUMNS: &str =
+    "id, name, token_prefix, scopes, expires_at, last_used_at, revoked_at, created_at";
+
+fn token_response(row: TokenRow) -> TokenResponse {
+    let (id, name, token_prefix, scopes, expires_at, last_used_at, revoked_at, created_at) = row;
+
+    TokenResponse {
+        id,
+        name,
+        token_prefix,
+        scopes: scopes.iter().filter_map(|s| Scope::parse(s)).collect(),
+        expires_at,
+        last_used_at,
+        revoked_at,
+        created_at,
+        token: None,
+    }
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/tokens",
+    tag = "access",
+    responses((status = 200, description = "API tokens, newest first, including revoked ones", body = [TokenResponse]))
+)]
+pub async fn get_tokens(
+    Extension(pool): Extension<PgPool>,
+) -> Result<Json<Vec<TokenResponse>>, ApiError> {
+    match sqlx::query_as::<_, TokenRow>(&format!(
+        "SELECT {TOKEN_COLUMNS} FROM api_tokens ORDER BY created_at DESC"
+    ))
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(Json(rows.into_iter().map(token_response).collect())),
+        Err(e) => {
+            tracing::error!("Failed to fetch API tokens: {}", e);
+            Err(ApiError::internal("Failed to fetch API tokens"))
+        }
+    }
+}
+
+/// Create an API token; the token itself is only ever returned here
+#[utoipa::path(
+    post,
+    path = "/api/v1/tokens",
+    tag = "access",
+    request_body = CreateTokenRequest,
+    responses(
+        (status = 200, description = "The created token, including the token itself", body = TokenResponse),
+        (status = 400, description = "Missing name or scopes, or an expiry in the past", body = ErrorResponse)
+    )
+)]
+pub async fn create_token(
+    Extension(pool): Extension<PgPool>,
+    JsonBody(req): JsonBody<CreateTokenRequest>,
+) -> Result<Json<TokenResponse>, ApiError> {
+    if req.name.trim().is_empty() || req.scopes.is_empty() {
+        return Err(ApiError::bad_request("A token needs a name and scopes"));
+    }
+    if req.expires_at.is_some_and(|at| at <= chrono::Utc::now()) {
+        return Err(ApiError::bad_request("expiresAt must be in the future"));
+    }
+
+    let token = generate_token();
+    let scopes: Vec<&str> = req.scopes.iter().map(Scope::as_str).collect();
+
+    let row = sqlx::query_as::<_, TokenRow>(&format!(
+        "INSERT INTO api_tokens (id, name, token_hash, token_prefix, scopes, expires_at, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {TOKEN_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
     .bind(&req.name)
-    .bind(&req.description)
-    .bind(&req.image)
-    .bind(&default_command)
-    .bind(&supported_file_types)
-    .bind(&environment_variables)
-    .bind(&req.icon)
+    .bind(hash_token(&token))
+    .bind(&token[..API_TOKEN_PREFIX.len() + 8])
+    .bind(&scopes)
+    .bind(req.expires_at)
+    .bind(chrono::Utc::now())
+    .fetch_one(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to create API token: {}", e);
+        ApiError::internal("Failed to create API token")
+    })?;
+
+    Ok(Json(TokenResponse {
+        token: Some(token),
+        ..token_response(row)
+    }))
+}
+
+/// Revoke an API token, keeping it listed with its revocation time
+#[utoipa::path(
+    delete,
+    path = "/api/v1/tokens/{id}",
+    tag = "access",
+    params(("id" = Uuid, Path, description = "Token id")),
+    responses(
+        (status = 204, description = "The token was revoked"),
+        (status = 404, description = "No such unrevoked token", body = ErrorResponse)
+    )
+)]
+pub async fn revoke_token(
+    Path(id): Path<Uuid>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query(
+        "UPDATE api_tokens SET revoked_at = NOW() WHERE id = $1 AND revoked_at IS NULL",
+    )
+    .bind(id)
     .execute(&pool)
-    .await {
-        tracing::error!("Failed to create test executor: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
+    .await
+    {
+        Ok(result) if result.rows_affected() == 0 => {
+            Err(ApiError::not_found(format!("API token {id} not found")))
+        }
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!("Failed to revoke API token {}: {}", id, e);
+            Err(ApiError::internal("Failed to revoke API token"))
+        }
     }
+}
 
-    Ok(Json(serde_json::json!({
-        "id": id,
-        "name": req.name,
-        "description": req.description,
-        "image": req.image,
-        "defaultCommand": default_command,
-        "command": if default_command.is_empty() { Vec::<String>::new() } else { vec![default_command] },
-        "supportedFileTypes": supported_file_types,
-        "environmentVariables": environment_variables,
-        "env": env_vars_to_object(&environment_variables),
-        "icon": req.icon
-    })))
+/// Role assignment of a row, skipping roles this version does not know
+fn role_assignment_response(
+    (subject, role, created_at): (String, String, Option<chrono::DateTime<chrono::Utc>>),
+) -> Option<RoleAssignmentResponse> {
+    Some(RoleAssignmentResponse {
+        subject,
+        role: Role::parse(&role)?,
+        created_at,
+    })
 }
 
-pub async fn update_executor(
-    Path(id): Path<Uuid>,
+#[utoipa::path(
+    get,
+    path = "/api/v1/roles",
+    tag = "access",
+    responses((status = 200, description = "Global role assignments by subject", body = [RoleAssignmentResponse]))
+)]
+pub async fn get_role_assignments(
     Extension(pool): Extension<PgPool>,
-    JsonBody(req): JsonBody<CreateExecutorRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let default_command = normalize_default_command(&req);
-    let environment_variables = normalize_environment_variables(&req);
-    let supported_file_types = req.supported_file_types.unwrap_or_default();
+) -> Result<Json<Vec<RoleAssignmentResponse>>, ApiError> {
+    match sqlx::query_as::<_, (String, String, Option<chrono::DateTime<chrono::Utc>>)>(
+        "SELECT subject, role, created_at FROM role_assignments ORDER BY subject",
+    )
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(Json(
+            rows.into_iter()
+                .filter_map(role_assignment_response)
+                .collect(),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to fetch role assignments: {}", e);
+            Err(ApiError::internal("Failed to fetch role assignments"))
+        }
+    }
+}
 
-    match sqlx::query(
-        "UPDATE test_executors SET name = $1, description = $2, image = $3, default_command = $4, supported_file_types = $5, environment_variables = $6, icon = $7 WHERE id = $8"
+/// Assign a role to a subject, replacing any role it had
+#[utoipa::path(
+    put,
+    path = "/api/v1/roles/{subject}",
+    tag = "access",
+    params(("subject" = String, Path, description = "User, group or `token:<id>` subject")),
+    request_body = AssignRoleRequest,
+    responses((status = 200, description = "The assignment", body = RoleAssignmentResponse))
+)]
+pub async fn assign_role(
+    Path(subject): Path<String>,
+    Extension(pool): Extension<PgPool>,
+    JsonBody(req): JsonBody<AssignRoleRequest>,
+) -> Result<Json<RoleAssignmentResponse>, ApiError> {
+    if subject.trim().is_empty() {
+        return Err(ApiError::bad_request("The subject cannot be empty"));
+    }
+
+    match sqlx::query_scalar::<_, Option<chrono::DateTime<chrono::Utc>>>(
+        "INSERT INTO role_assignments (subject, role, created_at) VALUES ($1, $2, $3) ON CONFLICT (subject) DO UPDATE SET role = EXCLUDED.role RETURNING created_at",
     )
-    .bind(&req.name)
-    .bind(&req.description)
-    .bind(&req.image)
-    .bind(&default_command)
-    .bind(&supported_file_types)
-    .bind(&environment_variables)
-    .bind(&req.icon)
-    .bind(id)
-    .execute(&pool)
-    .await {
-        Ok(result) if result.rows_affected() == 0 => Err(StatusCode::NOT_FOUND),
-        Ok(_) => Ok(Json(serde_json::json!({
-            "id": id,
-            "name": req.name,
-            "description": req.description,
-            "image": req.image,
-            "defaultCommand": default_command,
-            "command": if default_command.is_empty() { Vec::<String>::new() } else { vec![default_command] },
-            "supportedFileTypes": supported_file_types,
-            "environmentVariables": environment_variables,
-            "env": env_vars_to_object(&environment_variables),
-            "icon": req.icon
-        }))),
+    .bind(&subject)
+    .bind(req.role.as_str())
+    .bind(chrono::Utc::now())
+    .fetch_one(&pool)
+    .await
+    {
+        Ok(created_at) => Ok(Json(RoleAssignmentResponse {
+            subject,
+            role: req.role,
+            created_at,
+        })),
         Err(e) => {
-            tracing::error!("Failed to update test executor {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to assign role to {}: {}", subject, e);
+            Err(ApiError::internal("Failed to assign role"))
         }
     }
 }
 
-pub async fn delete_executor(
-    Path(id): Path<Uuid>,
+#[utoipa::path(
+    delete,
+    path = "/api/v1/roles/{subject}",
+    tag = "access",
+    params(("subject" = String, Path, description = "User, group or `token:<id>` subject")),
+    responses(
+        (status = 204, description = "The assignment was deleted"),
+        (status = 404, description = "The subject has no role", body = ErrorResponse)
+    )
+)]
+pub async fn delete_role_assignment(
+    Path(subject): Path<String>,
     Extension(pool): Extension<PgPool>,
-) -> Result<StatusCode, StatusCode> {
-    match sqlx::query("DELETE FROM test_executors WHERE id = $1")
-        .bind(id)
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM role_assignments WHERE subject = $1")
+        .bind(&subject)
         .execute(&pool)
         .await
     {
-        Ok(result) if result.rows_affected() == 0 => Err(StatusCode::NOT_FOUND),
+        Ok(result) if result.rows_affected() == 0 => Err(ApiError::not_found(format!(
+            "No role is assigned to {subject}"
+        ))),
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => {
-            tracing::error!("Failed to delete test executor {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to delete role assignment of {}: {}", subject, e);
+            Err(ApiError::internal("Failed to delete role assignment"))
         }
     }
 }
 
-fn normalize_default_command(req: &CreateExecutorRequest) -> String {
-    req.default_command
-        .clone()
-        .or_else(|| {
-            req.command.as_ref().map(|command| {
-                command
-                    .iter()
-                    .map(String::as_str)
-                    .collect::<Vec<_>>()
-                    .join(" ")
-            })
-        })
-        .unwrap_or_default()
+#[derive(Deserialize, ToSchema)]
+pub struct CreateProjectRequest {
+    pub slug: String,
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "k8sNamespace", default = "default_k8s_namespace")]
+    pub k8s_namespace: String,
 }
 
-fn normalize_environment_variables(req: &CreateExecutorRequest) -> Vec<String> {
-    if let Some(vars) = &req.environment_variables {
-        return vars.clone();
-    }
+#[derive(Deserialize, ToSchema)]
+pub struct UpdateProjectRequest {
+    pub name: String,
+    pub description: Option<String>,
+    #[serde(rename = "k8sNamespace", default = "default_k8s_namespace")]
+    pub k8s_namespace: String,
+}
 
-    match &req.env {
-        Some(serde_json::Value::Object(map)) => map.keys().cloned().collect(),
-        _ => Vec::new(),
+fn default_k8s_namespace() -> String {
+    "default".to_string()
+}
+
+type ProjectRow = (
+    Uuid,
+    String,
+    String,
+    Option<String>,
+    String,
+    Option<String>,
+    Option<chrono::DateTime<chrono::Utc>>,
+);
+
+const PROJECT_COLUMNS: &str = "id, slug, name, description, k8s_namespace, created_by, created_at";
+
+fn project_response(row: ProjectRow) -> ProjectResponse {
+    let (id, slug, name, description, k8s_namespace, created_by, created_at) = row;
+
+    ProjectResponse {
+        id,
+        slug,
+        name,
+        description,
+        k8s_namespace,
+        created_by,
+        created_at,
     }
 }
 
-fn env_vars_to_object(environment_variables: &[String]) -> serde_json::Value {
-    let mut map = serde_json::Map::new();
-    for key in environment_variables {
-        map.insert(key.clone(), serde_json::Value::String(String::new()));
+/// Map a project write error, treating a taken or malformed slug as a client error
+fn project_write_error(e: sqlx::Error) -> ApiError {
+    match &e {
+        sqlx::Error::Database(db) if db.is_unique_violation() => {
+            ApiError::conflict("A project with this slug already exists")
+        }
+        sqlx::Error::Database(db) if db.is_check_violation() => {
+            ApiError::bad_request("Project slugs are lowercase letters, digits and dashes")
+        }
+        _ => {
+            tracing::error!("Failed to write project: {}", e);
+            ApiError::internal("Failed to write project")
+        }
     }
-    serde_json::Value::Object(map)
 }
 
-pub async fn get_suites(
+#[utoipa::path(
+    get,
+    path = "/api/v1/projects",
+    tag = "projects",
+    responses((status = 200, description = "Projects the caller can see, by slug", body = [ProjectResponse]))
+)]
+pub async fn get_projects(
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<Vec<serde_json::Value>>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, Option<String>, String, Vec<String>, Vec<Uuid>, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, name, description, execution_mode, labels, test_definition_ids, created_at FROM test_suites ORDER BY created_at DESC"
-    )
+    Extension(auth): Extension<AuthConfig>,
+    Extension(identity): Extension<Identity>,
+) -> Result<Json<Vec<ProjectResponse>>, ApiError> {
+    // Admins see every project, anyone else the default one and those they are a member of
+    let is_admin = effective_role(&identity, &auth) == Some(Role::Admin);
+    match sqlx::query_as::<_, ProjectRow>(&format!(
+        "SELECT {PROJECT_COLUMNS} FROM projects WHERE $1 OR id = $2 OR EXISTS (SELECT 1 FROM project_members m WHERE m.project_id = projects.id AND m.subject = ANY($3)) ORDER BY slug"
+    ))
+    .bind(is_admin)
+    .bind(DEFAULT_PROJECT_ID)
+    .bind(identity.subjects())
     .fetch_all(&pool)
     .await
     {
-        Ok(rows) => {
-            let suites: Vec<serde_json::Value> = rows
-                .into_iter()
-                .map(|(id, name, description, execution_mode, labels, test_definition_ids, created_at)| {
-                    serde_json::json!({
-                        "id": id,
-                        "name": name,
-                        "description": description,
-                        "executionMode": execution_mode,
-                        "labels": labels,
-                        "testDefinitionIds": test_definition_ids,
-                        "createdAt": created_at
-                    })
-                })
-                .collect();
-            Ok(Json(suites))
-        }
+        Ok(rows) => Ok(Json(rows.into_iter().map(project_response).collect())),
         Err(e) => {
-            tracing::error!("Failed to fetch test suites: {}", e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to fetch projects: {}", e);
+            Err(ApiError::internal("Failed to fetch projects"))
         }
     }
 }
 
-pub async fn get_suite(
-    Path(id): Path<Uuid>,
-    Extension(pool): Extension<PgPool>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    match sqlx::query_as::<_, (Uuid, String, Option<String>, String, Vec<String>, Vec<Uuid>, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, name, description, execution_mode, labels, test_definition_ids, created_at FROM test_suites WHERE id = $1"
+#[utoipa::path(
+    get,
+    path = "/api/v1/projects/{project}",
+    tag = "projects",
+    params(("project" = String, Path, description = "Project slug")),
+    responses(
+        (status = 200, description = "The project", body = ProjectResponse),
+        (status = 404, description = "No such project", body = ErrorResponse)
     )
-    .bind(id)
+)]
+pub async fn get_project(
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<ProjectResponse>, ApiError> {
+    match sqlx::query_as::<_, ProjectRow>(&format!(
+        "SELECT {PROJECT_COLUMNS} FROM projects WHERE id = $1"
+    ))
+    .bind(project.id)
     .fetch_optional(&pool)
     .await
     {
-        Ok(Some((id, name, description, execution_mode, labels, test_definition_ids, created_at))) => {
-            Ok(Json(serde_json::json!({
-                "id": id,
-                "name": name,
-                "description": description,
-                "executionMode": execution_mode,
-                "labels": labels,
-                "testDefinitionIds": test_definition_ids,
-                "createdAt": created_at
-            })))
-        }
-        Ok(None) => Err(StatusCode::NOT_FOUND),
+        Ok(Some(row)) => Ok(Json(project_response(row))),
+        Ok(None) => Err(ApiError::not_found(format!(
+            "Project {} not found",
+            project.slug
+        ))),
         Err(e) => {
-            tracing::error!("Failed to fetch test suite {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to fetch project {}: {}", project.slug, e);
+            Err(ApiError::internal("Failed to fetch project"))
         }
     }
 }
 
-pub async fn create_suite(
+#[utoipa::path(
+    post,
+    path = "/api/v1/projects",
+    tag = "projects",
+    request_body = CreateProjectRequest,
+    responses(
+        (status = 200, description = "The created project", body = ProjectResponse),
+        (status = 400, description = "Missing name or namespace, or a malformed slug", body = ErrorResponse),
+        (status = 409, description = "The slug is taken", body = ErrorResponse)
+    )
+)]
+pub async fn create_project(
     Extension(pool): Extension<PgPool>,
-    JsonBody(req): JsonBody<CreateSuiteRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let id = Uuid::new_v4();
-    let now = chrono::Utc::now();
-    let description = req.description.unwrap_or_default();
-    let labels = req.labels.unwrap_or_default();
+    Extension(identity): Extension<Identity>,
+    JsonBody(req): JsonBody<CreateProjectRequest>,
+) -> Result<Json<ProjectResponse>, ApiError> {
+    if req.name.trim().is_empty() || req.k8s_namespace.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            "A project needs a name and a Kubernetes namespace",
+        ));
+    }
 
-    if let Err(e) = sqlx::query(
-        "INSERT INTO test_suites (id, name, description, execution_mode, labels, test_definition_ids, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7)"
-    )
-    .bind(id)
+    let row = sqlx::query_as::<_, ProjectRow>(&format!(
+        "INSERT INTO projects (id, slug, name, description, k8s_namespace, created_by, created_at) VALUES ($1, $2, $3, $4, $5, $6, $7) RETURNING {PROJECT_COLUMNS}"
+    ))
+    .bind(Uuid::new_v4())
+    .bind(&req.slug)
     .bind(&req.name)
-    .bind(&description)
-    .bind(&req.execution_mode)
-    .bind(&labels)
-    .bind(&req.test_definition_ids)
-    .bind(now)
-    .execute(&pool)
-    .await {
-        tracing::error!("Failed to create test suite: {}", e);
-        return Err(StatusCode::INTERNAL_SERVER_ERROR);
-    }
+    .bind(&req.description)
+    .bind(&req.k8s_namespace)
+    .bind(identity.actor())
+    .bind(chrono::Utc::now())
+    .fetch_one(&pool)
+    .await
+    .map_err(project_write_error)?;
 
-    Ok(Json(serde_json::json!({
-        "id": id,
-        "name": req.name,
-        "description": description,
-        "executionMode": req.execution_mode,
-        "labels": labels,
-        "testDefinitionIds": req.test_definition_ids,
-        "createdAt": now
-    })))
+    Ok(Json(project_response(row)))
 }
 
-pub async fn update_suite(
-    Path(id): Path<Uuid>,
+#[utoipa::path(
+    put,
+    path = "/api/v1/projects/{project}",
+    tag = "projects",
+    params(("project" = String, Path, description = "Project slug")),
+    request_body = UpdateProjectRequest,
+    responses(
+        (status = 200, description = "The updated project", body = ProjectResponse),
+        (status = 404, description = "No such project", body = ErrorResponse)
+    )
+)]
+pub async fn update_project(
     Extension(pool): Extension<PgPool>,
-    JsonBody(req): JsonBody<CreateSuiteRequest>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let description = req.description.unwrap_or_default();
-    let labels = req.labels.unwrap_or_default();
+    Extension(project): Extension<Project>,
+    JsonBody(req): JsonBody<UpdateProjectRequest>,
+) -> Result<Json<ProjectResponse>, ApiError> {
+    if req.name.trim().is_empty() || req.k8s_namespace.trim().is_empty() {
+        return Err(ApiError::bad_request(
+            "A project needs a name and a Kubernetes namespace",
+        ));
+    }
 
-    match sqlx::query(
-        "UPDATE test_suites SET name = $1, description = $2, execution_mode = $3, labels = $4, test_definition_ids = $5 WHERE id = $6"
-    )
+    let row = sqlx::query_as::<_, ProjectRow>(&format!(
+        "UPDATE projects SET name = $1, description = $2, k8s_namespace = $3 WHERE id = $4 RETURNING {PROJECT_COLUMNS}"
+    ))
     .bind(&req.name)
-    .bind(&description)
-    .bind(&req.execution_mode)
-    .bind(&labels)
-    .bind(&req.test_definition_ids)
-    .bind(id)
-    .execute(&pool)
-    .await {
-        Ok(result) if result.rows_affected() == 0 => Err(StatusCode::NOT_FOUND),
-        Ok(_) => Ok(Json(serde_json::json!({
-            "id": id,
-            "name": req.name,
-            "description": description,
-            "executionMode": req.execution_mode,
-            "labels": labels,
-            "testDefinitionIds": req.test_definition_ids
-        }))),
-        Err(e) => {
-            tracing::error!("Failed to update test suite {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
-        }
+    .bind(&req.description)
+    .bind(&req.k8s_namespace)
+    .bind(project.id)
+    .fetch_optional(&pool)
+    .await
+    .map_err(project_write_error)?;
+
+    match row {
+        Some(row) => Ok(Json(project_response(row))),
+        None => Err(ApiError::not_found(format!(
+            "Project {} not found",
+            project.slug
+        ))),
     }
 }
 
-pub async fn delete_suite(
-    Path(id): Path<Uuid>,
+/// Delete a project and everything in it; the default project cannot be deleted
+#[utoipa::path(
+    delete,
+    path = "/api/v1/projects/{project}",
+    tag = "projects",
+    params(("project" = String, Path, description = "Project slug")),
+    responses(
+        (status = 204, description = "The project was deleted"),
+        (status = 400, description = "The default project cannot be deleted", body = ErrorResponse),
+        (status = 404, description = "No such project", body = ErrorResponse)
+    )
+)]
+pub async fn delete_project(
     Extension(pool): Extension<PgPool>,
-) -> Result<StatusCode, StatusCode> {
-    match sqlx::query("DELETE FROM test_suites WHERE id = $1")
-        .bind(id)
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    if project.id == DEFAULT_PROJECT_ID {
+        return Err(ApiError::bad_request(
+            "The default project cannot be deleted",
+        ));
+    }
+
+    match sqlx::query("DELETE FROM projects WHERE id = $1")
+        .bind(project.id)
         .execute(&pool)
         .await
     {
-        Ok(result) if result.rows_affected() == 0 => Err(StatusCode::NOT_FOUND),
+        Ok(result) if result.rows_affected() == 0 => Err(ApiError::not_found(format!(
+            "Project {} not found",
+            project.slug
+        ))),
         Ok(_) => Ok(StatusCode::NO_CONTENT),
         Err(e) => {
-            tracing::error!("Failed to delete test suite {}: {}", id, e);
-            Err(StatusCode::INTERNAL_SERVER_ERROR)
+            tracing::error!("Failed to delete project {}: {}", project.slug, e);
+            Err(ApiError::internal("Failed to delete project"))
         }
     }
 }
 
-pub async fn run_suite(
-    Path(suite_id): Path<String>,
+#[utoipa::path(
+    get,
+    path = "/api/v1/projects/{project}/members",
+    tag = "projects",
+    params(("project" = String, Path, description = "Project slug")),
+    responses((status = 200, description = "The project's members by subject", body = [RoleAssignmentResponse]))
+)]
+pub async fn get_project_members(
     Extension(pool): Extension<PgPool>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Parse the suite_id as UUID
-    let suite_uuid = match Uuid::parse_str(&suite_id) {
-        Ok(uuid) => uuid,
-        Err(_) => return Err(StatusCode::BAD_REQUEST),
-    };
+    Extension(project): Extension<Project>,
+) -> Result<Json<Vec<RoleAssignmentResponse>>, ApiError> {
+    match sqlx::query_as::<_, (String, String, Option<chrono::DateTime<chrono::Utc>>)>(
+        "SELECT subject, role, created_at FROM project_members WHERE project_id = $1 ORDER BY subject",
+    )
+    .bind(project.id)
+    .fetch_all(&pool)
+    .await
+    {
+        Ok(rows) => Ok(Json(
+            rows.into_iter()
+                .filter_map(role_assignment_response)
+                .collect(),
+        )),
+        Err(e) => {
+            tracing::error!("Failed to fetch members of project {}: {}", project.slug, e);
+            Err(ApiError::internal("Failed to fetch project members"))
+        }
+    }
+}
 
-    // First, get the suite details
-    let suite = match sqlx::query_as::<_, (Uuid, String, Option<String>, String, Vec<String>, Vec<Uuid>, chrono::DateTime<chrono::Utc>)>(
-        "SELECT id, name, description, execution_mode, labels, test_definition_ids, created_at FROM test_suites WHERE id = $1"
+/// Give a subject a role in the project, replacing any role it had there
+#[utoipa::path(
+    put,
+    path = "/api/v1/projects/{project}/members/{subject}",
+    tag = "projects",
+    params(
+        ("project" = String, Path, description = "Project slug"),
+        ("subject" = String, Path, description = "User, group or `token:<id>` subject")
+    ),
+    request_body = AssignRoleRequest,
+    responses((status = 200, description = "The membership", body = RoleAssignmentResponse))
+)]
+pub async fn assign_project_member(
+    Path((_, subject)): Path<(String, String)>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+    JsonBody(req): JsonBody<AssignRoleRequest>,
+) -> Result<Json<RoleAssignmentResponse>, ApiError> {
+    if subject.trim().is_empty() {
+        return Err(ApiError::bad_request("The subject cannot be empty"));
+    }
+
+    match sqlx::query_scalar::<_, Option<chrono::DateTime<chrono::Utc>>>(
+        "INSERT INTO project_members (project_id, subject, role, created_at) VALUES ($1, $2, $3, $4) ON CONFLICT (project_id, subject) DO UPDATE SET role = EXCLUDED.role RETURNING created_at",
     )
-    .bind(suite_uuid)
-    .fetch_optional(&pool)
+    .bind(project.id)
+    .bind(&subject)
+    .bind(req.role.as_str())
+    .bind(chrono::Utc::now())
+    .fetch_one(&pool)
     .await
     {
-        Ok(Some(suite)) => suite,
-        Ok(None) => return Err(StatusCode::NOT_FOUND),
+        Ok(created_at) => Ok(Json(RoleAssignmentResponse {
+            subject,
+            role: req.role,
+            created_at,
+        })),
         Err(e) => {
-            tracing::error!("Failed to fetch suite {}: {}", suite_id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!(
+                "Failed to add {} to project {}: {}",
+                subject,
+                project.slug,
+                e
+            );
+            Err(ApiError::internal("Failed to add project member"))
         }
-    };
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/v1/projects/{project}/members/{subject}",
+    tag = "projects",
+    params(
+        ("project" = String, Path, description = "Project slug"),
+        ("subject" = String, Path, description = "User, group or `token:<id>` subject")
+    ),
+    responses(
+        (status = 204, description = "The member was removed"),
+        (status = 404, description = "The subject is not a member", body = ErrorResponse)
+    )
+)]
+pub async fn delete_project_member(
+    Path((_, subject)): Path<(String, String)>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<StatusCode, ApiError> {
+    match sqlx::query("DELETE FROM project_members WHERE project_id = $1 AND subject = $2")
+        .bind(project.id)
+        .bind(&subject)
+        .execute(&pool)
+        .await
+    {
+        Ok(result) if result.rows_affected() == 0 => Err(ApiError::not_found(format!(
+            "{subject} is not a member of project {}",
+            project.slug
+        ))),
+        Ok(_) => Ok(StatusCode::NO_CONTENT),
+        Err(e) => {
+            tracing::error!(
+                "Failed to remove {} from project {}: {}",
+                subject,
+                project.slug,
+                e
+            );
+            Err(ApiError::internal("Failed to remove project member"))
+        }
+    }
+}
+
+/// Filters and page of `/api/audit`, newest events first
+#[derive(Debug, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub entity_type: Option<String>,
+    pub entity_id: Option<String>,
+    pub actor: Option<String>,
+    pub limit: Option<i64>,
+    pub offset: Option<i64>,
+}
 
+type AuditEventRow = (
+    Uuid,
+    Option<String>,
+    String,
+    String,
+    Option<String>,
+    Option<serde_json::Value>,
+    Option<serde_json::Value>,
+    Option<serde_json::Value>,
+    String,
+    i32,
+    chrono::DateTime<chrono::Utc>,
+);
+
+const AUDIT_FILTER: &str = "($1::text IS NULL OR entity_type = $1) AND ($2::text IS NULL OR entity_id = $2) AND ($3::text IS NULL OR actor = $3)";
+
+fn audit_event_response(row: AuditEventRow) -> AuditEventResponse {
     let (
-        suite_uuid,
-        suite_name,
-        _description,
-        execution_mode,
-        _labels,
-        test_definition_ids,
-        _created_at,
-    ) = suite;
+        id,
+        actor,
+        action,
+        entity_type,
+        entity_id,
+        before,
+        after,
+        changes,
+        request_id,
+        status_code,
+        created_at,
+    ) = row;
+
+    AuditEventResponse {
+        id,
+        actor,
+        action,
+        entity_type,
+        entity_id,
+        before,
+        after,
+        changes,
+        request_id,
+        status_code,
+        created_at,
+    }
+}
 
-    // Get all test definitions for this suite
-    let definitions = match get_suite_definitions(&pool, &test_definition_ids).await {
-        Ok(defs) => defs,
+#[utoipa::path(
+    get,
+    path = "/api/v1/audit",
+    tag = "access",
+    params(AuditQuery),
+    responses((status = 200, description = "A page of audit events, newest first", body = AuditEventsResponse))
+)]
+pub async fn get_audit_events(
+    Query(query): Query<AuditQuery>,
+    Extension(pool): Extension<PgPool>,
+) -> Result<Json<AuditEventsResponse>, ApiError> {
+    let limit = query.limit.unwrap_or(50).clamp(1, 200);
+    let offset = query.offset.unwrap_or(0).max(0);
+
+    let events_sql = format!(
+        "SELECT id, actor, action, entity_type, entity_id, before, after, changes, request_id, status_code, created_at FROM audit_events WHERE {AUDIT_FILTER} ORDER BY created_at DESC, id LIMIT $4 OFFSET $5"
+    );
+    let total_sql = format!("SELECT COUNT(*) FROM audit_events WHERE {AUDIT_FILTER}");
+
+    let events = sqlx::query_as::<_, AuditEventRow>(&events_sql)
+        .bind(&query.entity_type)
+        .bind(&query.entity_id)
+        .bind(&query.actor)
+        .bind(limit)
+        .bind(offset)
+        .fetch_all(&pool);
+    let total = sqlx::query_scalar::<_, i64>(&total_sql)
+        .bind(&query.entity_type)
+        .bind(&query.entity_id)
+        .bind(&query.actor)
+        .fetch_one(&pool);
+
+    match tokio::try_join!(events, total) {
+        Ok((events, total)) => Ok(Json(AuditEventsResponse {
+            events: events.into_iter().map(audit_event_response).collect(),
+            total,
+            limit,
+            offset,
+        })),
         Err(e) => {
-            tracing::error!("Failed to get definitions for suite {}: {}", suite_id, e);
-            return Err(StatusCode::INTERNAL_SERVER_ERROR);
+            tracing::error!("Failed to fetch audit events: {}", e);
+            Err(ApiError::internal("Failed to fetch audit events"))
         }
-    };
-
-    if definitions.is_empty() {
-        return Ok(Json(serde_json::json!({
-            "error": "No test definitions found for this suite",
-            "suiteId": suite_uuid,
-            "suiteName": suite_name
-        })));
     }
+}
 
-    // Create runs for each definition
-    let mut created_runs = Vec::new();
+/// Query of `/api/search`
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchQuery {
+    pub q: Option<String>,
+    /// Only return hits of this type
+    #[serde(rename = "type")]
+    pub kind: Option<SearchHitKind>,
+    pub limit: Option<i64>,
+}
 
-    for def in definitions {
-        let run_uuid = Uuid::new_v4();
-        let now = chrono::Utc::now();
-        let run_name = format!("{} - {}", suite_name, def.name);
+/// What a search hit is
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum SearchHitKind {
+    TestDefinition,
+    TestSuite,
+    TestRun,
+}
 
-        // Insert the run
-        if let Err(e) = sqlx::query(
-            "INSERT INTO test_runs (id, name, image, command, status, created_at, test_definition_id) VALUES ($1, $2, $3, $4, $5, $6, $7)"
-        )
-        .bind(run_uuid)
-        .bind(&run_name)
-        .bind(&def.image)
-        .bind(&def.commands)
-        .bind("running")
-        .bind(now)
-        .bind(def.id)
-        .execute(&pool)
-        .await {
-            tracing::error!("Failed to insert test run for definition {}: {}", def.id, e);
-            continue;
+impl SearchHitKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchHitKind::TestDefinition => "test_definition",
+            SearchHitKind::TestSuite => "test_suite",
+            SearchHitKind::TestRun => "test_run",
         }
+    }
 
-        // Create Kubernetes job
-        let job_name = format!("test-run-{run_uuid}");
-        let k8s_command: Vec<String> = if def.commands.len() == 1 {
-            vec!["sh".into(), "-c".into(), def.commands[0].clone()]
-        } else {
-            vec!["sh".into(), "-c".into(), def.commands.join(" && ")]
-        };
+    fn parse(kind: &str) -> Option<Self> {
+        [Self::TestDefinition, Self::TestSuite, Self::TestRun]
+            .into_iter()
+            .find(|k| k.as_str() == kind)
+    }
+}
 
-        let mut job_created = false;
-        if let Ok(client) = KubernetesClient::new().await {
-            if client
-                .submit_job(&job_name, &def.image, &k8s_command)
-                .await
-                .is_ok()
-            {
-                job_created = true;
-                // Spawn monitor task
-                let pool_clone = pool.clone();
-                let job_name_clone = job_name.clone();
-                tokio::spawn(async move {
-                    if let Err(e) =
-                        monitor_job_and_update_status(run_uuid, job_name_clone, pool_clone).await
-                    {
-                        tracing::error!("Job monitor failed for run {}: {}", run_uuid, e);
-                    }
-                });
-            } else {
-                // Mark as failed
-                sqlx::query("UPDATE test_runs SET status = $1 WHERE id = $2")
-                    .bind("failed")
-                    .bind(run_uuid)
-                    .execute(&pool)
-                    .await
-                    .ok();
+/// A definition, suite or run matching a search
+#[derive(Debug, Serialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    #[serde(rename = "type")]
+    pub kind: SearchHitKind,
+    pub id: Uuid,
+    pub name: String,
+    /// Matching text around the search terms, which are wrapped in `**`
+    pub snippet: String,
+    pub rank: f32,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+pub struct SearchResults {
+    pub query: String,
+    /// Best match first
+    pub hits: Vec<SearchHit>,
+}
+
+type SearchHitRow = (
+    String,
+    Uuid,
+    String,
+    String,
+    f32,
+    chrono::DateTime<chrono::Utc>,
+);
+
+/// Definitions by name, description and commands, suites by name and description,
+/// and runs by name, command and logs; `$1` is the project, `$2` the search, `$3` the type
+const SEARCH_SQL: &str = "WITH search AS (SELECT websearch_to_tsquery('simple', $2) AS query)
+SELECT kind, id, name, ts_headline('simple', body, (SELECT query FROM search), 'MinWords=5, MaxWords=20, MaxFragments=2, StartSel=**, StopSel=**'), rank, created_at FROM (
+    SELECT 'test_definition' AS kind, id, name, concat_ws(' ', description, search_text(commands)) AS body, ts_rank(search_vector, query) AS rank, created_at FROM test_definitions, search WHERE project_id = $1 AND search_vector @@ query AND ($3::text IS NULL OR $3 = 'test_definition')
+    UNION ALL
+    SELECT 'test_suite', id, name, coalesce(description, ''), ts_rank(search_vector, query), created_at FROM test_suites, search WHERE project_id = $1 AND search_vector @@ query AND ($3::text IS NULL OR $3 = 'test_suite')
+    UNION ALL
+    SELECT 'test_run', id, name, concat_ws(' ', search_text(command), left(search_text(logs), 262144)), ts_rank(search_vector, query), created_at FROM test_runs, search WHERE project_id = $1 AND search_vector @@ query AND ($3::text IS NULL OR $3 = 'test_run')
+) hits ORDER BY rank DESC, created_at DESC LIMIT $4";
+
+/// Full-text search over the project's definitions, suites and runs
+#[utoipa::path(
+    get,
+    path = "/api/v1/search",
+    tag = "search",
+    params(SearchQuery),
+    responses(
+        (status = 200, description = "Hits, best match first", body = SearchResults),
+        (status = 422, description = "Empty query or limit out of range", body = ErrorResponse)
+    )
+)]
+pub async fn search(
+    Query(query): Query<SearchQuery>,
+    Extension(pool): Extension<PgPool>,
+    Extension(project): Extension<Project>,
+) -> Result<Json<SearchResults>, ApiError> {
+    let q = query.q.unwrap_or_default();
+    let limit = query.limit.unwrap_or(20);
+    let mut validator = Validator::new();
+    validator.check("q", !q.trim().is_empty(), "Must not be empty");
+    validator.check(
+        "limit",
+        (1..=100).contains(&limit),
+        "Must be between 1 and 100",
+    );
+    validator.finish()?;
+
+    let rows = sqlx::query_as::<_, SearchHitRow>(SEARCH_SQL)
+        .bind(project.id)
+        .bind(&q)
+        .bind(query.kind.map(|kind| kind.as_str()))
+        .bind(limit)
+        .fetch_all(&pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to search for '{}': {}", q, e);
+            ApiError::internal("Failed to search")
+        })?;
+
+    let hits = rows
+        .into_iter()
+        .filter_map(|(kind, id, name, snippet, rank, created_at)| {
+            Some(SearchHit {
+                kind: SearchHitKind::parse(&kind)?,
+                id,
+                name,
+                snippet,
+                rank,
+                created_at,
+            })
+        })
+        .collect();
+
+    Ok(Json(SearchResults { query: q, hits }))
+}
+
+/// What a launched run is linked to and who launched it
+#[derive(Default)]
+pub(crate) struct RunAttribution {
+    pub definition_id: Option<Uuid>,
+    pub definition_version: Option<i32>,
+    pub executor_id: Option<Uuid>,
+    pub schedule_id: Option<Uuid>,
+    pub created_by: Option<String>,
+}
+
+/// Insert a run and submit its Kubernetes job, monitoring it in the background
+///
+/// Only the insert can fail; if the job cannot be submitted the run is marked
+/// as failed and returned as such.
+pub(crate) async fn launch_run(
+    pool: &PgPool,
+    project: &Project,
+    name: &str,
+    image: &str,
+    commands: &[String],
+    attribution: RunAttribution,
+) -> Result<TestRun, sqlx::Error> {
+    let run_uuid = Uuid::new_v4();
+    let now = chrono::Utc::now();
+
+    sqlx::query(
+        "INSERT INTO test_runs (id, name, image, command, status, created_at, test_definition_id, executor_id, schedule_id, created_by, project_id, definition_version) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12)"
+    )
+    .bind(run_uuid)
+    .bind(name)
+    .bind(image)
+    .bind(commands)
+    .bind("running")
+    .bind(now)
+    .bind(attribution.definition_id)
+    .bind(attribution.executor_id)
+    .bind(attribution.schedule_id)
+    .bind(&attribution.created_by)
+    .bind(project.id)
+    .bind(attribution.definition_version)
+    .execute(pool)
+    .await?;
+
+    let spec = RunSpec {
+        definition_id: attribution.definition_id.map(|id| id.to_string()),
+        ttl_seconds_after_finished: Some(JOB_TTL_SECONDS),
+        ..RunSpec::new(
+            run_uuid.to_string(),
+            image,
+            commands.to_vec(),
+            RunOrigin::Api,
+        )
+    };
+    let job_name = spec.job_name();
+
+    let job_created = match KubernetesClient::for_namespace(&project.k8s_namespace).await {
+        Ok(client) => match client.submit_job(&spec).await {
+            Ok(_) => true,
+            Err(e) => {
+                tracing::error!(
+                    "Failed to create Kubernetes job for run {}: {}",
+                    run_uuid,
+                    e
+                );
+                false
             }
+        },
+        Err(e) => {
+            tracing::error!("Failed to initialize Kubernetes client: {}", e);
+            false
         }
+    };
 
-        created_runs.push(serde_json::json!({
-            "id": run_uuid,
-            "name": run_name,
-            "image": def.image,
-            "command": def.commands,
-            "status": if job_created { "running" } else { "failed" },
-            "createdAt": now.to_rfc3339(),
-            "jobName": job_name,
-            "jobCreated": job_created,
-            "definitionId": def.id
-        }));
-
-        // If sequential mode, we could add delays or wait for completion here
-        // For now, we'll create all jobs in parallel regardless of execution_mode
+    if job_created {
+        let pool_clone = pool.clone();
+        let job_name_clone = job_name.clone();
+        let namespace = project.k8s_namespace.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                monitor_job_and_update_status(run_uuid, job_name_clone, namespace, pool_clone).await
+            {
+                tracing::error!("Job monitor failed for run {}: {}", run_uuid, e);
+            }
+        });
+    } else {
+        sqlx::query("UPDATE test_runs SET status = $1 WHERE id = $2")
+            .bind("failed")
+            .bind(run_uuid)
+            .execute(pool)
+            .await
+            .ok();
     }
 
-    Ok(Json(serde_json::json!({
-        "suiteId": suite_uuid,
-        "suiteName": suite_name,
-        "executionMode": execution_mode,
-        "runs": created_runs,
-        "message": format!("Created {} test runs for suite {}", created_runs.len(), suite_name)
-    })))
+    Ok(TestRun {
+        id: run_uuid,
+        name: name.to_string(),
+        image: image.to_string(),
+        commands: commands.to_vec(),
+        status: if job_created { "running" } else { "failed" }.to_string(),
+        created_at: now,
+        definition_id: attribution.definition_id,
+        executor_id: attribution.executor_id,
+        schedule_id: attribution.schedule_id,
+        duration: None,
+        logs: None,
+        k8s_job_name: Some(job_name),
+        origin: RunOrigin::Api,
+        k8s_ref: None,
+        created_by: attribution.created_by,
+        definition_version: attribution.definition_version,
+    })
 }
 
-async fn get_suite_definitions(
+pub(crate) async fn get_suite_definitions(
     pool: &PgPool,
+    project_id: Uuid,
     definition_ids: &[Uuid],
-) -> Result<Vec<TestDefinition>, sqlx::Error> {
+) -> Result<Vec<SuiteDefinition>, sqlx::Error> {
     let mut definitions = Vec::new();
 
     for &def_id in definition_ids {
-        if let Some(def) = sqlx::query_as::<_, (Uuid, String, String, Vec<String>)>(
-            "SELECT id, name, image, commands FROM test_definitions WHERE id = $1",
+        if let Some(def) = sqlx::query_as::<_, (Uuid, String, String, Vec<String>, i32)>(
+            "SELECT id, name, image, commands, version FROM test_definitions WHERE id = $1 AND project_id = $2",
         )
         .bind(def_id)
+        .bind(project_id)
         .fetch_optional(pool)
         .await?
         {
-            definitions.push(TestDefinition {
+            definitions.push(SuiteDefinition {
                 id: def.0,
                 name: def.1,
                 image: def.2,
                 commands: def.3,
+                version: def.4,
             });
         }
     }
@@ -1233,11 +3418,12 @@ async fn get_suite_definitions(
 }
 
 #[derive(Debug)]
-struct TestDefinition {
-    id: Uuid,
-    name: String,
-    image: String,
-    commands: Vec<String>,
+pub(crate) struct SuiteDefinition {
+    pub id: Uuid,
+    pub name: String,
+    pub image: String,
+    pub commands: Vec<String>,
+    pub version: i32,
 }
 
 #[cfg(test)]
@@ -1296,43 +3482,108 @@ mod tests {
 
     #[tokio::test]
     async fn test_k8s_health() {
-        let response = k8s_health().await;
-        let value = response.0;
-        assert!(value["kubernetes_connected"].is_boolean());
-        assert!(value["timestamp"].is_string());
-        assert!(value["error"].is_null() || value["error"].is_string());
+        match k8s_health(Extension(Project::default())).await {
+            Ok(response) => assert!(response.0.kubernetes_connected),
+            Err(error) => {
+                assert_eq!(error.status, StatusCode::SERVICE_UNAVAILABLE);
+                assert_eq!(error.code, "service_unavailable");
+            }
+        }
     }
 
     #[tokio::test]
     async fn test_get_job_logs() {
         let job_name = "test-job".to_string();
-        let response = get_job_logs(Path(job_name.clone())).await;
-        let value = response.0;
-        assert_eq!(value["job_name"], job_name);
-        // In test environment, Kubernetes is not available, so expect error
-        assert_eq!(value["status"], "error");
-        assert!(value["error"].is_string());
-        assert!(value["timestamp"].is_string());
+        let result = get_job_logs(Path(job_name.clone()), Extension(Project::default())).await;
+        // In test environment, Kubernetes is not available (or the job does not exist)
+        let error = result.unwrap_err();
+        assert!(error.status.is_client_error() || error.status.is_server_error());
+        assert!(!error.message.is_empty());
     }
 
     #[tokio::test]
     async fn test_get_job_status() {
         let job_name = "test-job".to_string();
-        let response = get_job_status(Path(job_name.clone())).await;
-        let value = response.0;
-        assert_eq!(value["job_name"], job_name);
-        // In test environment, Kubernetes is not available, so expect error
-        assert_eq!(value["status"], "error");
-        assert!(value["error"].is_string());
-        assert!(value["timestamp"].is_string());
+        let result = get_job_status(Path(job_name.clone()), Extension(Project::default())).await;
+        // In test environment, Kubernetes is not available (or the job does not exist)
+        let error = result.unwrap_err();
+        assert!(error.status.is_client_error() || error.status.is_server_error());
+        assert!(!error.message.is_empty());
     }
 
     #[tokio::test]
     async fn test_delete_job() {
         let job_name = "test-job".to_string();
-        let response = delete_job(Path(job_name.clone())).await;
-        let value = response.0;
-        assert!(value["timestamp"].is_string());
-        assert!(value["error"].is_string() || value["message"].is_string());
+        match delete_job(Path(job_name.clone()), Extension(Project::default())).await {
+            Ok(response) => assert!(response.0.message.contains(&job_name)),
+            Err(error) => assert!(!error.message.is_empty()),
+        }
+    }
+
+    #[test]
+    fn test_run_definition_overrides() {
+        let request = |body: serde_json::Value| -> RunDefinitionRequest {
+            serde_json::from_value(body).unwrap()
+        };
+        let commands = vec!["npm test".to_string()];
+
+        // Overrides cannot be combined with a specific version
+        let error = request(serde_json::json!({"version": 2, "commands": ["rm -rf /"]}))
+            .validate()
+            .unwrap_err();
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert!(error.details.unwrap()["fields"]["commands"].is_string());
+        assert!(request(serde_json::json!({"version": 2, "name": "Rerun"}))
+            .validate()
+            .is_ok());
+
+        // A run of the current version only records it when it runs it as is
+        let plain = request(serde_json::json!({"name": "Rerun"}));
+        assert_eq!(plain.executed_version(3, "node:18", &commands), Some(3));
+        let same = request(serde_json::json!({"commands": ["npm test"]}));
+        assert_eq!(same.executed_version(3, "node:18", &commands), Some(3));
+        let overridden = request(serde_json::json!({"commands": ["npm run lint"]}));
+        assert_eq!(overridden.executed_version(3, "node:18", &commands), None);
+        let other_image = request(serde_json::json!({"image": "node:20"}));
+        assert_eq!(other_image.executed_version(3, "node:18", &commands), None);
+    }
+
+    #[test]
+    fn test_next_schedule_run_validation() {
+        let mut req: CreateScheduleRequest = serde_json::from_value(serde_json::json!({
+            "name": "Nightly",
+            "cronExpression": "0 2 * * *",
+            "testDefinitionId": Uuid::new_v4()
+        }))
+        .unwrap();
+        assert_eq!(req.timezone, "UTC");
+        assert_eq!(req.concurrency_policy, ConcurrencyPolicy::Allow);
+        assert!(next_schedule_run(&req).unwrap().is_some());
+
+        req.suite_id = Some(Uuid::new_v4());
+        assert_eq!(
+            next_schedule_run(&req).unwrap_err().status,
+            StatusCode::BAD_REQUEST
+        );
+
+        req.suite_id = None;
+        req.cron_expression = "every night".to_string();
+        assert_eq!(
+            next_schedule_run(&req).unwrap_err().status,
+            StatusCode::BAD_REQUEST
+        );
+    }
+
+    #[test]
+    fn test_search_hit_kind() {
+        for kind in [
+            SearchHitKind::TestDefinition,
+            SearchHitKind::TestSuite,
+            SearchHitKind::TestRun,
+        ] {
+            assert_eq!(SearchHitKind::parse(kind.as_str()), Some(kind));
+            assert_eq!(serde_json::to_value(kind).unwrap(), kind.as_str());
+        }
+        assert_eq!(SearchHitKind::parse("schedule"), None);
     }
 }
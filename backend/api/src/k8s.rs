@@ -1,18 +1,22 @@
 use anyhow::{Context, Result};
 use chrono::Utc;
-use k8s_openapi::api::batch::v1::{Job, JobSpec};
-use k8s_openapi::api::core::v1::{Container, Pod, PodSpec, PodTemplateSpec};
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use k8s_openapi::api::batch::v1::Job;
+use k8s_openapi::api::core::v1::Pod;
 use kube::{
     api::{Api, ListParams, LogParams, PostParams},
     Client, Error as KubeError,
 };
 use serde::{Deserialize, Serialize};
+use sparktest_core::RunSpec;
 use sqlx::PgPool;
 use tokio::time::{sleep, Duration};
 use tracing::{info, warn};
+use utoipa::ToSchema;
 use uuid::Uuid;
 
+/// Seconds a finished Job started through the API is kept before cleanup
+pub const JOB_TTL_SECONDS: i32 = 3600;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct KubeConfig {
     pub namespace: String,
@@ -30,7 +34,8 @@ impl Default for KubeConfig {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Debug, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "camelCase")]
 pub struct JobLogs {
     pub job_name: String,
     pub pod_name: String,
@@ -46,52 +51,14 @@ pub struct KubernetesError {
     pub details: Option<String>,
 }
 
-/// Build a Kubernetes Job manifest (pure function)
-pub fn build_job(job_name: &str, image: &str, command: &[String]) -> Job {
-    Job {
-        metadata: ObjectMeta {
-            name: Some(job_name.to_string()),
-            labels: Some(std::collections::BTreeMap::from([
-                ("app".to_string(), "sparktest".to_string()),
-                ("component".to_string(), "test-runner".to_string()),
-            ])),
-            ..Default::default()
-        },
-        spec: Some(JobSpec {
-            template: PodTemplateSpec {
-                metadata: Some(ObjectMeta {
-                    labels: Some(std::collections::BTreeMap::from([
-                        ("job-name".to_string(), job_name.to_string()),
-                        ("app".to_string(), "sparktest".to_string()),
-                    ])),
-                    ..Default::default()
-                }),
-                spec: Some(PodSpec {
-                    containers: vec![Container {
-                        name: job_name.to_string(),
-                        image: Some(image.to_string()),
-                        command: Some(command.to_vec()),
-                        ..Default::default()
-                    }],
-                    restart_policy: Some("Never".to_string()),
-                    ..Default::default()
-                }),
-            },
-            backoff_limit: Some(0),
-            ttl_seconds_after_finished: Some(3600),
-            ..Default::default()
-        }),
-        ..Default::default()
-    }
-}
-
 pub async fn monitor_job_and_update_status(
     run_id: Uuid,
     job_name: String,
+    namespace: String,
     pool: PgPool,
 ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
     let client = Client::try_default().await?;
-    let jobs: Api<Job> = Api::namespaced(client.clone(), "default");
+    let jobs: Api<Job> = Api::namespaced(client.clone(), &namespace);
 
     let start_time = Utc::now();
     let mut status = "running".to_string();
@@ -145,9 +112,9 @@ impl KubernetesClient {
     }
 
     /// Build and submit a Job to the cluster
-    pub async fn submit_job(&self, job_name: &str, image: &str, command: &[String]) -> Result<()> {
+    pub async fn submit_job(&self, spec: &RunSpec) -> Result<()> {
         let jobs: Api<Job> = Api::namespaced(self.client.clone(), &self.config.namespace);
-        let job = build_job(job_name, image, command);
+        let job = spec.build_job();
         jobs.create(&PostParams::default(), &job).await?;
         Ok(())
     }
@@ -158,6 +125,15 @@ impl KubernetesClient {
         Ok(Self { client, config })
     }
 
+    /// Create a new Kubernetes client working in `namespace`
+    pub async fn for_namespace(namespace: &str) -> Result<Self> {
+        Self::new_with_config(KubeConfig {
+            namespace: namespace.to_string(),
+            ..KubeConfig::default()
+        })
+        .await
+    }
+
     /// Create authenticated Kubernetes client with fallback mechanisms
     async fn create_authenticated_client() -> Result<Client> {
         // If override server set, attempt to load kubeconfig, patch server, and return
@@ -464,7 +440,7 @@ mod tests {
     async fn test_job_name_generation() {
         // Test that job names are generated correctly for test runs
         let run_id = uuid::Uuid::new_v4();
-        let job_name = format!("test-run-{run_id}");
+        let job_name = sparktest_core::job_name(&run_id.to_string());
 
         assert!(job_name.starts_with("test-run-"));
         assert_eq!(job_name.len(), 45); // "test-run-" (9) + UUID (36)
@@ -0,0 +1,682 @@
+use crate::auth::Identity;
+use crate::error::ApiError;
+use crate::etag::{ETag, IfMatch};
+use crate::handlers::{
+    self, CreateDefinitionRequest, CreateExecutorRequest, CreateRunRequest, CreateScheduleRequest,
+    CreateSuiteRequest, RunDefinitionRequest, UpdateDefinitionRequest, UpdateExecutorRequest,
+    UpdateSuiteRequest,
+};
+use crate::k8s::JobLogs;
+use crate::pagination::{ListQuery, Page};
+use crate::projects::{Project, ProjectRef};
+use crate::responses::{JobStatus, KubernetesHealth};
+use axum::{
+    extract::{Path, Query, Request},
+    http::{header, HeaderValue},
+    middleware::Next,
+    response::{Json, Response},
+    Extension, Json as JsonBody,
+};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+use sparktest_core::{
+    job_name, Executor, SuiteRun, TestDefinition, TestRun, TestSchedule, TestSuite,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Legacy route segments renamed in `/api/v1`
+const RENAMED: [(&str, &str); 4] = [
+    ("test-runs", "runs"),
+    ("test-definitions", "definitions"),
+    ("test-executors", "executors"),
+    ("test-suites", "suites"),
+];
+
+/// The `/api/v1` URL path replacing the legacy route `path`, relative to `/api`
+pub fn successor_path(path: &str, project: Option<&str>) -> String {
+    let path = path.trim_start_matches('/');
+    let (first, rest) = path.split_once('/').unwrap_or((path, ""));
+    let first = RENAMED
+        .iter()
+        .find(|(old, _)| *old == first)
+        .map_or(first, |(_, new)| new);
+    let project = project
+        .map(|slug| format!("/projects/{slug}"))
+        .unwrap_or_default();
+
+    if rest.is_empty() {
+        format!("/api/v1{project}/{first}")
+    } else {
+        format!("/api/v1{project}/{first}/{rest}")
+    }
+}
+
+/// Mark a response of a legacy route as deprecated, linking to its successor
+///
+/// The legacy routes are the unversioned `/api/...` ones from before `/api/v1`.
+/// They call the v1 handlers and convert the results back to the old field names.
+pub async fn deprecated(request: Request, next: Next) -> Response {
+    let project = request
+        .extensions()
+        .get::<ProjectRef>()
+        .map(|ProjectRef(slug)| slug.clone());
+    let successor = successor_path(request.uri().path(), project.as_deref());
+
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("true"));
+    if let Ok(link) = HeaderValue::from_str(&format!("<{successor}>; rel=\"successor-version\"")) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+/// The `TestRun` resource a run was created from
+#[derive(Debug, Clone, Serialize)]
+pub struct K8sRefResponse {
+    pub namespace: String,
+    pub name: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RunResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub image: String,
+    pub command: Vec<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub duration: Option<i32>,
+    pub logs: Option<Vec<String>>,
+    pub test_definition_id: Option<Uuid>,
+    pub definition_version: Option<i32>,
+    pub executor_id: Option<Uuid>,
+    pub schedule_id: Option<Uuid>,
+    pub created_by: Option<String>,
+    pub origin: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub k8s_ref: Option<K8sRefResponse>,
+}
+
+impl From<TestRun> for RunResponse {
+    fn from(run: TestRun) -> Self {
+        Self {
+            id: run.id,
+            name: run.name,
+            image: run.image,
+            command: run.commands,
+            status: run.status,
+            created_at: run.created_at,
+            duration: run.duration,
+            logs: run.logs,
+            test_definition_id: run.definition_id,
+            definition_version: run.definition_version,
+            executor_id: run.executor_id,
+            schedule_id: run.schedule_id,
+            created_by: run.created_by,
+            origin: run.origin.as_str().to_string(),
+            k8s_ref: run.k8s_ref.map(|k8s_ref| K8sRefResponse {
+                namespace: k8s_ref.namespace,
+                name: k8s_ref.name,
+            }),
+        }
+    }
+}
+
+/// A run that was just started, with the Kubernetes job it was submitted as
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LaunchedRunResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub image: String,
+    pub command: Vec<String>,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+    pub test_definition_id: Option<Uuid>,
+    pub definition_version: Option<i32>,
+    pub executor_id: Option<Uuid>,
+    pub created_by: Option<String>,
+    pub job_name: String,
+    pub job_created: bool,
+    /// Same as `testDefinitionId`; only set on the runs of a suite
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub definition_id: Option<Uuid>,
+}
+
+impl From<TestRun> for LaunchedRunResponse {
+    fn from(run: TestRun) -> Self {
+        Self {
+            job_name: run
+                .k8s_job_name
+                .unwrap_or_else(|| job_name(&run.id.to_string())),
+            // A run whose job could not be submitted is failed straight away
+            job_created: run.status != "failed",
+            id: run.id,
+            name: run.name,
+            image: run.image,
+            command: run.commands,
+            status: run.status,
+            created_at: run.created_at,
+            test_definition_id: run.definition_id,
+            definition_version: run.definition_version,
+            executor_id: run.executor_id,
+            created_by: run.created_by,
+            definition_id: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub image: String,
+    pub commands: Vec<String>,
+    pub description: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub executor_id: Option<Uuid>,
+    pub labels: Vec<String>,
+    pub created_by: Option<String>,
+}
+
+impl From<TestDefinition> for DefinitionResponse {
+    fn from(definition: TestDefinition) -> Self {
+        Self {
+            id: definition.id,
+            name: definition.name,
+            image: definition.image,
+            commands: definition.commands,
+            description: Some(definition.description),
+            created_at: definition.created_at,
+            executor_id: definition.executor_id,
+            labels: definition.labels,
+            created_by: definition.created_by,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExecutorResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub image: String,
+    pub default_command: String,
+    /// `defaultCommand` as a list, empty when there is none
+    pub command: Vec<String>,
+    pub supported_file_types: Vec<String>,
+    pub environment_variables: Vec<String>,
+    /// `environmentVariables` as an object with empty values
+    pub env: Value,
+    pub icon: Option<String>,
+}
+
+impl From<Executor> for ExecutorResponse {
+    fn from(executor: Executor) -> Self {
+        let default_command = executor.default_command.unwrap_or_default();
+        let env = executor
+            .environment_variables
+            .iter()
+            .map(|key| (key.clone(), Value::String(String::new())))
+            .collect();
+
+        Self {
+            id: executor.id,
+            name: executor.name,
+            description: executor.description,
+            image: executor.image,
+            command: if default_command.is_empty() {
+                Vec::new()
+            } else {
+                vec![default_command.clone()]
+            },
+            default_command,
+            supported_file_types: executor.supported_file_types,
+            environment_variables: executor.environment_variables,
+            env: Value::Object(env),
+            icon: executor.icon,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuiteResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    pub execution_mode: String,
+    pub labels: Vec<String>,
+    pub test_definition_ids: Vec<Uuid>,
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+}
+
+impl From<TestSuite> for SuiteResponse {
+    fn from(suite: TestSuite) -> Self {
+        Self {
+            id: suite.id,
+            name: suite.name,
+            description: Some(suite.description),
+            execution_mode: suite.execution_mode,
+            labels: suite.labels,
+            test_definition_ids: suite.definition_ids,
+            created_at: suite.created_at,
+            created_by: suite.created_by,
+        }
+    }
+}
+
+/// The runs started for a suite, one per definition
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SuiteRunResponse {
+    pub suite_id: Uuid,
+    pub suite_name: String,
+    pub execution_mode: String,
+    pub runs: Vec<LaunchedRunResponse>,
+    pub message: String,
+}
+
+impl From<SuiteRun> for SuiteRunResponse {
+    fn from(suite_run: SuiteRun) -> Self {
+        Self {
+            message: format!(
+                "Created {} test runs for suite {}",
+                suite_run.runs.len(),
+                suite_run.suite_name
+            ),
+            suite_id: suite_run.suite_id,
+            suite_name: suite_run.suite_name,
+            execution_mode: suite_run.execution_mode,
+            runs: suite_run
+                .runs
+                .into_iter()
+                .map(|run| LaunchedRunResponse {
+                    definition_id: run.definition_id,
+                    ..run.into()
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleResponse {
+    pub id: Uuid,
+    pub name: String,
+    pub cron_expression: String,
+    pub timezone: String,
+    pub test_definition_id: Option<Uuid>,
+    pub test_suite_id: Option<Uuid>,
+    pub concurrency_policy: String,
+    pub successful_runs_history_limit: i32,
+    pub failed_runs_history_limit: i32,
+    pub enabled: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub created_at: Option<DateTime<Utc>>,
+}
+
+impl From<TestSchedule> for ScheduleResponse {
+    fn from(schedule: TestSchedule) -> Self {
+        Self {
+            id: schedule.id,
+            name: schedule.name,
+            cron_expression: schedule.cron_expression,
+            timezone: schedule.timezone,
+            test_definition_id: schedule.definition_id,
+            test_suite_id: schedule.suite_id,
+            concurrency_policy: schedule.concurrency_policy.as_str().to_string(),
+            successful_runs_history_limit: schedule.successful_runs_history_limit,
+            failed_runs_history_limit: schedule.failed_runs_history_limit,
+            enabled: schedule.enabled,
+            last_run_at: schedule.last_run_at,
+            next_run_at: schedule.next_run_at,
+            created_at: Some(schedule.created_at),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct KubernetesHealthResponse {
+    pub kubernetes_connected: bool,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobStatusResponse {
+    pub job_name: String,
+    pub status: String,
+    pub timestamp: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct JobLogsResponse {
+    pub job_name: String,
+    pub pod_name: String,
+    pub logs: String,
+    pub timestamp: DateTime<Utc>,
+    pub status: String,
+}
+
+pub async fn get_runs(
+    query: Query<ListQuery>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Page<RunResponse>, ApiError> {
+    Ok(handlers::get_runs(query, pool, project)
+        .await?
+        .map(RunResponse::from))
+}
+
+pub async fn create_run(
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    identity: Extension<Identity>,
+    body: JsonBody<CreateRunRequest>,
+) -> Result<Json<LaunchedRunResponse>, ApiError> {
+    let Json(run) = handlers::create_run(pool, project, identity, body).await?;
+    Ok(Json(run.into()))
+}
+
+pub async fn get_run(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Json<RunResponse>, ApiError> {
+    let Json(run) = handlers::get_run(id, pool, project).await?;
+    Ok(Json(run.into()))
+}
+
+pub async fn get_definitions(
+    query: Query<ListQuery>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Page<DefinitionResponse>, ApiError> {
+    Ok(handlers::get_definitions(query, pool, project)
+        .await?
+        .map(DefinitionResponse::from))
+}
+
+pub async fn get_definition(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<(ETag, Json<DefinitionResponse>), ApiError> {
+    let (etag, Json(definition)) = handlers::get_definition(id, pool, project).await?;
+    Ok((etag, Json(definition.into())))
+}
+
+pub async fn create_definition(
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    identity: Extension<Identity>,
+    body: JsonBody<CreateDefinitionRequest>,
+) -> Result<(ETag, Json<DefinitionResponse>), ApiError> {
+    let (etag, Json(definition)) =
+        handlers::create_definition(pool, project, identity, body).await?;
+    Ok((etag, Json(definition.into())))
+}
+
+pub async fn update_definition(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    identity: Extension<Identity>,
+    if_match: IfMatch,
+    body: JsonBody<UpdateDefinitionRequest>,
+) -> Result<(ETag, Json<DefinitionResponse>), ApiError> {
+    let (etag, Json(definition)) =
+        handlers::update_definition(id, pool, project, identity, if_match, body).await?;
+    Ok((etag, Json(definition.into())))
+}
+
+pub async fn run_definition(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    identity: Extension<Identity>,
+    body: JsonBody<RunDefinitionRequest>,
+) -> Result<Json<LaunchedRunResponse>, ApiError> {
+    let Json(run) = handlers::run_definition(id, pool, project, identity, body).await?;
+    Ok(Json(run.into()))
+}
+
+pub async fn get_executors(
+    query: Query<ListQuery>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Page<ExecutorResponse>, ApiError> {
+    Ok(handlers::get_executors(query, pool, project)
+        .await?
+        .map(ExecutorResponse::from))
+}
+
+pub async fn get_executor(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<(ETag, Json<ExecutorResponse>), ApiError> {
+    let (etag, Json(executor)) = handlers::get_executor(id, pool, project).await?;
+    Ok((etag, Json(executor.into())))
+}
+
+pub async fn create_executor(
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    body: JsonBody<CreateExecutorRequest>,
+) -> Result<(ETag, Json<ExecutorResponse>), ApiError> {
+    let (etag, Json(executor)) = handlers::create_executor(pool, project, body).await?;
+    Ok((etag, Json(executor.into())))
+}
+
+pub async fn update_executor(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    if_match: IfMatch,
+    body: JsonBody<UpdateExecutorRequest>,
+) -> Result<(ETag, Json<ExecutorResponse>), ApiError> {
+    let (etag, Json(executor)) =
+        handlers::update_executor(id, pool, project, if_match, body).await?;
+    Ok((etag, Json(executor.into())))
+}
+
+pub async fn get_suites(
+    query: Query<ListQuery>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Page<SuiteResponse>, ApiError> {
+    Ok(handlers::get_suites(query, pool, project)
+        .await?
+        .map(SuiteResponse::from))
+}
+
+pub async fn get_suite(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<(ETag, Json<SuiteResponse>), ApiError> {
+    let (etag, Json(suite)) = handlers::get_suite(id, pool, project).await?;
+    Ok((etag, Json(suite.into())))
+}
+
+pub async fn create_suite(
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    identity: Extension<Identity>,
+    body: JsonBody<CreateSuiteRequest>,
+) -> Result<(ETag, Json<SuiteResponse>), ApiError> {
+    let (etag, Json(suite)) = handlers::create_suite(pool, project, identity, body).await?;
+    Ok((etag, Json(suite.into())))
+}
+
+pub async fn update_suite(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    if_match: IfMatch,
+    body: JsonBody<UpdateSuiteRequest>,
+) -> Result<(ETag, Json<SuiteResponse>), ApiError> {
+    let (etag, Json(suite)) = handlers::update_suite(id, pool, project, if_match, body).await?;
+    Ok((etag, Json(suite.into())))
+}
+
+pub async fn run_suite(
+    id: Path<String>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    identity: Extension<Identity>,
+) -> Result<Json<SuiteRunResponse>, ApiError> {
+    let Json(suite_run) = handlers::run_suite(id, pool, project, identity).await?;
+    Ok(Json(suite_run.into()))
+}
+
+pub async fn get_schedules(
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Json<Vec<ScheduleResponse>>, ApiError> {
+    let Json(schedules) = handlers::get_schedules(pool, project).await?;
+    Ok(Json(schedules.into_iter().map(Into::into).collect()))
+}
+
+pub async fn get_schedule(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let Json(schedule) = handlers::get_schedule(id, pool, project).await?;
+    Ok(Json(schedule.into()))
+}
+
+pub async fn create_schedule(
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    body: JsonBody<CreateScheduleRequest>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let Json(schedule) = handlers::create_schedule(pool, project, body).await?;
+    Ok(Json(schedule.into()))
+}
+
+pub async fn update_schedule(
+    id: Path<Uuid>,
+    pool: Extension<PgPool>,
+    project: Extension<Project>,
+    body: JsonBody<CreateScheduleRequest>,
+) -> Result<Json<ScheduleResponse>, ApiError> {
+    let Json(schedule) = handlers::update_schedule(id, pool, project, body).await?;
+    Ok(Json(schedule.into()))
+}
+
+pub async fn k8s_health(
+    project: Extension<Project>,
+) -> Result<Json<KubernetesHealthResponse>, ApiError> {
+    let Json(KubernetesHealth {
+        kubernetes_connected,
+        timestamp,
+    }) = handlers::k8s_health(project).await?;
+    Ok(Json(KubernetesHealthResponse {
+        kubernetes_connected,
+        timestamp,
+    }))
+}
+
+pub async fn get_job_logs(
+    job_name: Path<String>,
+    project: Extension<Project>,
+) -> Result<Json<JobLogsResponse>, ApiError> {
+    let Json(JobLogs {
+        job_name,
+        pod_name,
+        logs,
+        timestamp,
+        status,
+    }) = handlers::get_job_logs(job_name, project).await?;
+    Ok(Json(JobLogsResponse {
+        job_name,
+        pod_name,
+        logs,
+        timestamp,
+        status,
+    }))
+}
+
+pub async fn get_job_status(
+    job_name: Path<String>,
+    project: Extension<Project>,
+) -> Result<Json<JobStatusResponse>, ApiError> {
+    let Json(JobStatus {
+        job_name,
+        status,
+        timestamp,
+    }) = handlers::get_job_status(job_name, project).await?;
+    Ok(Json(JobStatusResponse {
+        job_name,
+        status,
+        timestamp,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sparktest_core::RunOrigin;
+
+    #[test]
+    fn test_successor_path() {
+        assert_eq!(successor_path("/test-runs/1", None), "/api/v1/runs/1");
+        assert_eq!(
+            successor_path("/test-definitions/1/run", None),
+            "/api/v1/definitions/1/run"
+        );
+        assert_eq!(successor_path("/runs", None), "/api/v1/runs");
+        assert_eq!(successor_path("/k8s/jobs", None), "/api/v1/k8s/jobs");
+        assert_eq!(
+            successor_path("/test-suites", Some("team-a")),
+            "/api/v1/projects/team-a/suites"
+        );
+    }
+
+    #[test]
+    fn test_legacy_run_fields() {
+        let run = TestRun {
+            id: Uuid::new_v4(),
+            name: "Smoke".to_string(),
+            image: "alpine".to_string(),
+            commands: vec!["true".to_string()],
+            status: "failed".to_string(),
+            created_at: Utc::now(),
+            definition_id: Some(Uuid::new_v4()),
+            executor_id: None,
+            schedule_id: None,
+            duration: None,
+            logs: None,
+            k8s_job_name: None,
+            origin: RunOrigin::Crd,
+            k8s_ref: None,
+            created_by: None,
+            definition_version: Some(3),
+        };
+
+        let legacy = serde_json::to_value(RunResponse::from(run.clone())).unwrap();
+        assert_eq!(legacy["command"], serde_json::json!(["true"]));
+        assert_eq!(
+            legacy["testDefinitionId"],
+            run.definition_id.unwrap().to_string()
+        );
+        assert_eq!(legacy["origin"], "crd");
+        assert_eq!(legacy["definitionVersion"], 3);
+
+        let launched = LaunchedRunResponse::from(run.clone());
+        assert!(!launched.job_created);
+        assert_eq!(launched.job_name, job_name(&run.id.to_string()));
+    }
+}
@@ -1,7 +1,32 @@
+pub mod audit;
+pub mod auth;
+pub mod authz;
+pub mod error;
+pub mod etag;
 pub mod handlers;
 pub mod k8s;
+pub mod legacy;
+pub mod oidc;
+pub mod openapi;
+pub mod pagination;
+pub mod projects;
+pub mod responses;
 pub mod routes;
+pub mod scheduler;
+pub mod validation;
 
+pub use audit::*;
+pub use auth::*;
+pub use authz::*;
+pub use error::*;
+pub use etag::*;
 pub use handlers::*;
 pub use k8s::*;
+pub use oidc::*;
+pub use openapi::*;
+pub use pagination::*;
+pub use projects::*;
+pub use responses::*;
 pub use routes::*;
+pub use scheduler::*;
+pub use validation::*;
@@ -0,0 +1,290 @@
+use crate::authz::Role;
+use anyhow::{bail, Context, Result};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use jsonwebtoken::{
+    decode, decode_header,
+    jwk::{Jwk, JwkSet},
+    Algorithm, DecodingKey, Validation,
+};
+use serde::Deserialize;
+use serde_json::Value;
+use std::time::{Duration, Instant};
+use tokio::sync::RwLock;
+
+/// How long a fetched JWKS is used before it is fetched again
+const JWKS_TTL: Duration = Duration::from_secs(600);
+
+/// Minimum time between refetches caused by tokens with an unknown key id
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(30);
+
+/// The OIDC issuer whose ID and access tokens the API accepts
+#[derive(Debug, Clone)]
+pub struct OidcConfig {
+    /// Issuer URL, as in the `iss` claim; discovery is fetched below it
+    pub issuer: String,
+    /// Expected `aud` claim, usually the client id
+    pub audience: String,
+    /// Claim holding the name runs and definitions are recorded under
+    pub username_claim: String,
+    /// Claim holding the user's groups, matched against `group:` role assignments
+    pub groups_claim: String,
+    /// Role of users without a matching role assignment
+    pub default_role: Option<Role>,
+}
+
+impl OidcConfig {
+    /// Read `OIDC_ISSUER`, `OIDC_AUDIENCE`, `OIDC_USERNAME_CLAIM`, `OIDC_GROUPS_CLAIM`
+    /// and `OIDC_DEFAULT_ROLE`; None unless an issuer is set
+    pub fn from_env() -> Option<Self> {
+        let var = |name| std::env::var(name).ok().filter(|v: &String| !v.is_empty());
+        Some(Self {
+            issuer: var("OIDC_ISSUER")?.trim_end_matches('/').to_string(),
+            audience: var("OIDC_AUDIENCE").unwrap_or_else(|| "sparktest".to_string()),
+            username_claim: var("OIDC_USERNAME_CLAIM").unwrap_or_else(|| "email".to_string()),
+            groups_claim: var("OIDC_GROUPS_CLAIM").unwrap_or_else(|| "groups".to_string()),
+            default_role: var("OIDC_DEFAULT_ROLE").and_then(|r| Role::parse(&r)),
+        })
+    }
+}
+
+/// A user whose token the issuer signed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OidcUser {
+    pub subject: String,
+    /// The username claim, or the subject if the token has none
+    pub username: String,
+    pub groups: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct Discovery {
+    jwks_uri: String,
+}
+
+struct CachedJwks {
+    keys: JwkSet,
+    fetched_at: Instant,
+}
+
+/// Validates tokens against the issuer's signing keys, cached from its JWKS
+pub struct OidcVerifier {
+    pub config: OidcConfig,
+    http: reqwest::Client,
+    jwks: RwLock<Option<CachedJwks>>,
+}
+
+impl OidcVerifier {
+    pub fn new(config: OidcConfig) -> Self {
+        Self {
+            config,
+            http: reqwest::Client::new(),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    /// Whether `token` claims to come from this issuer, before checking its signature
+    pub fn claims_issuer(&self, token: &str) -> bool {
+        unverified_issuer(token).is_some_and(|iss| iss.trim_end_matches('/') == self.config.issuer)
+    }
+
+    /// Check the token's signature, issuer, audience and expiry
+    pub async fn verify(&self, token: &str) -> Result<OidcUser> {
+        let header = decode_header(token)?;
+        // Only signatures made with the issuer's private keys are accepted
+        if matches!(
+            header.alg,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            bail!("Unsupported token algorithm {:?}", header.alg);
+        }
+        let kid = header.kid.context("Token has no key id")?;
+        let key = DecodingKey::from_jwk(&self.key(&kid).await?)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.config.issuer]);
+        validation.set_audience(&[&self.config.audience]);
+        let claims = decode::<serde_json::Map<String, Value>>(token, &key, &validation)?.claims;
+
+        let subject = claims
+            .get("sub")
+            .and_then(Value::as_str)
+            .context("Token has no sub claim")?
+            .to_string();
+        let username = claims
+            .get(&self.config.username_claim)
+            .and_then(Value::as_str)
+            .map_or_else(|| subject.clone(), String::from);
+        let groups = claims
+            .get(&self.config.groups_claim)
+            .and_then(Value::as_array)
+            .map(|groups| {
+                groups
+                    .iter()
+                    .filter_map(Value::as_str)
+                    .map(String::from)
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        Ok(OidcUser {
+            subject,
+            username,
+            groups,
+        })
+    }
+
+    /// Signing key `kid`, refetching the JWKS when it is stale or the key is new
+    async fn key(&self, kid: &str) -> Result<Jwk> {
+        if let Some(cached) = self.jwks.read().await.as_ref() {
+            let fresh = cached.fetched_at.elapsed() < JWKS_TTL;
+            if let Some(jwk) = cached.keys.find(kid).filter(|_| fresh) {
+                return Ok(jwk.clone());
+            }
+            if cached.fetched_at.elapsed() < JWKS_MIN_REFRESH {
+                bail!("Unknown signing key {kid}");
+            }
+        }
+
+        let keys = self.fetch_jwks().await?;
+        let jwk = keys.find(kid).cloned();
+        *self.jwks.write().await = Some(CachedJwks {
+            keys,
+            fetched_at: Instant::now(),
+        });
+        jwk.with_context(|| format!("Unknown signing key {kid}"))
+    }
+
+    async fn fetch_jwks(&self) -> Result<JwkSet> {
+        let discovery_url = format!("{}/.well-known/openid-configuration", self.config.issuer);
+        let discovery: Discovery = self
+            .http
+            .get(&discovery_url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Invalid OIDC discovery document at {discovery_url}"))?;
+
+        tracing::info!("Fetching OIDC signing keys from {}", discovery.jwks_uri);
+        let keys = self
+            .http
+            .get(&discovery.jwks_uri)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await
+            .with_context(|| format!("Invalid JWKS at {}", discovery.jwks_uri))?;
+        Ok(keys)
+    }
+}
+
+/// The `iss` claim of a JWT, read without verifying it
+fn unverified_issuer(token: &str) -> Option<String> {
+    let payload = URL_SAFE_NO_PAD.decode(token.split('.').nth(1)?).ok()?;
+    let claims: Value = serde_json::from_slice(&payload).ok()?;
+    claims.get("iss")?.as_str().map(String::from)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::{routing::get, Json, Router};
+    use jsonwebtoken::{encode, EncodingKey, Header};
+
+    const TEST_KEY_ID: &str = "test-key";
+    const TEST_KEY_MODULUS: &str = "0cPPt1nCkTLyVdYuS87MfdLg2Uh5nXo-lD1XTAacqjUAPouqKs8BJJl32Un7mDMErI-6aH-vJFJL_wyaFv01O3VXQfAhHRaVF2S2CPjkH37H03eYdTffIbImq9VOADP1fiAdnr-UtA_yZlsdjI4uQ42XCA_4nQaUkhBoB4zJqe-z8R7fmN_XHZVysyjghBFNyxwIl9uJWr153lX5Jm29mDSzBUvSbUcJrBBMCqn8ScCo9a3e-tVmo17mYjbkWE3WI6NCHdp3HUiqG4Big0HcCl2UmxXEf7VlrSG2nLbbaQuaz9KApqUVA1O00-07Dt8PPcSp7PRyxIoDKSvdvqPrjQ";
+
+    /// Serve discovery and the JWKS of the test key on a local port, returning the issuer URL
+    async fn start_test_issuer() -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let issuer = format!("http://{}", listener.local_addr().unwrap());
+
+        let jwks_uri = format!("{issuer}/jwks");
+        let app = Router::new()
+            .route(
+                "/.well-known/openid-configuration",
+                get(move || async move { Json(serde_json::json!({ "jwks_uri": jwks_uri })) }),
+            )
+            .route(
+                "/jwks",
+                get(|| async {
+                    Json(serde_json::json!({
+                        "keys": [{
+                            "kty": "RSA",
+                            "kid": TEST_KEY_ID,
+                            "alg": "RS256",
+                            "use": "sig",
+                            "n": TEST_KEY_MODULUS,
+                            "e": "AQAB"
+                        }]
+                    }))
+                }),
+            );
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        issuer
+    }
+
+    fn sign(claims: Value) -> String {
+        let key =
+            EncodingKey::from_rsa_pem(include_bytes!("../testdata/oidc_test_key.pem")).unwrap();
+        let header = Header {
+            kid: Some(TEST_KEY_ID.to_string()),
+            ..Header::new(Algorithm::RS256)
+        };
+        encode(&header, &claims, &key).unwrap()
+    }
+
+    fn verifier(issuer: &str) -> OidcVerifier {
+        OidcVerifier::new(OidcConfig {
+            issuer: issuer.to_string(),
+            audience: "sparktest".to_string(),
+            username_claim: "email".to_string(),
+            groups_claim: "groups".to_string(),
+            default_role: None,
+        })
+    }
+
+    #[tokio::test]
+    async fn test_verify_with_local_issuer() {
+        let issuer = start_test_issuer().await;
+        let verifier = verifier(&issuer);
+        let exp = chrono::Utc::now().timestamp() + 300;
+
+        let token = sign(serde_json::json!({
+            "iss": issuer,
+            "aud": "sparktest",
+            "sub": "user-1",
+            "email": "ada@example.com",
+            "groups": ["qa", "platform"],
+            "exp": exp
+        }));
+        assert!(verifier.claims_issuer(&token));
+        assert_eq!(
+            verifier.verify(&token).await.unwrap(),
+            OidcUser {
+                subject: "user-1".to_string(),
+                username: "ada@example.com".to_string(),
+                groups: vec!["qa".to_string(), "platform".to_string()],
+            }
+        );
+
+        let wrong_audience = sign(serde_json::json!({
+            "iss": issuer, "aud": "other", "sub": "user-1", "exp": exp
+        }));
+        assert!(verifier.verify(&wrong_audience).await.is_err());
+
+        let expired = sign(serde_json::json!({
+            "iss": issuer, "aud": "sparktest", "sub": "user-1", "exp": exp - 3600
+        }));
+        assert!(verifier.verify(&expired).await.is_err());
+
+        let other_issuer = sign(serde_json::json!({
+            "iss": "https://other.example.com", "aud": "sparktest", "sub": "user-1", "exp": exp
+        }));
+        assert!(!verifier.claims_issuer(&other_issuer));
+        assert!(verifier.verify(&other_issuer).await.is_err());
+    }
+}
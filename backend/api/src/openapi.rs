@@ -0,0 +1,229 @@
+use crate::handlers::{self, *};
+use crate::k8s::JobLogs;
+use crate::responses::*;
+use crate::{auth::Scope, authz::Role};
+use axum::response::{Html, Json};
+use sparktest_core::{
+    ConcurrencyPolicy, DefinitionVersion, Executor, FieldChange, K8sRef, RunOrigin, SuiteRun,
+    TestDefinition, TestRun, TestSchedule, TestSuite,
+};
+use utoipa::{
+    openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme},
+    Modify, OpenApi,
+};
+
+/// Page serving Swagger UI for [`openapi_json`]; the assets come from a CDN so
+/// the server does not have to bundle them
+const SWAGGER_UI: &str = r##"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8" />
+  <title>SparkTest API</title>
+  <link rel="stylesheet" href="https://unpkg.com/swagger-ui-dist@5/swagger-ui.css" />
+</head>
+<body>
+  <div id="swagger-ui"></div>
+  <script src="https://unpkg.com/swagger-ui-dist@5/swagger-ui-bundle.js" crossorigin></script>
+  <script>
+    window.ui = SwaggerUIBundle({ url: "/api/openapi.json", dom_id: "#swagger-ui" });
+  </script>
+</body>
+</html>
+"##;
+
+/// OpenAPI 3 description of the API, generated from the handlers and their types
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "SparkTest API",
+        description = "Runs tests as Kubernetes jobs. Every route under `/api/v1` acts on the default project; prefix it with `/api/v1/projects/{project}` to act on another one, e.g. `/api/v1/projects/payments/runs`.\n\nThe unversioned routes from before `/api/v1`, such as `/api/test-runs`, still work with their old field names but are deprecated: their responses carry a `Deprecation` header and a `Link` to the route replacing them."
+    ),
+    paths(
+        handlers::health_check,
+        handlers::get_runs,
+        handlers::create_run,
+        handlers::get_run,
+        handlers::delete_run,
+        handlers::get_definitions,
+        handlers::create_definition,
+        handlers::get_definition,
+        handlers::update_definition,
+        handlers::delete_definition,
+        handlers::run_definition,
+        handlers::get_definition_versions,
+        handlers::get_definition_version,
+        handlers::rollback_definition,
+        handlers::get_executors,
+        handlers::create_executor,
+        handlers::get_executor,
+        handlers::update_executor,
+        handlers::delete_executor,
+        handlers::get_suites,
+        handlers::create_suite,
+        handlers::get_suite,
+        handlers::update_suite,
+        handlers::delete_suite,
+        handlers::run_suite,
+        handlers::get_schedules,
+        handlers::create_schedule,
+        handlers::get_schedule,
+        handlers::update_schedule,
+        handlers::delete_schedule,
+        handlers::k8s_health,
+        handlers::get_job_logs,
+        handlers::get_job_status,
+        handlers::list_jobs,
+        handlers::delete_job,
+        handlers::search,
+        handlers::get_tokens,
+        handlers::create_token,
+        handlers::revoke_token,
+        handlers::get_role_assignments,
+        handlers::assign_role,
+        handlers::delete_role_assignment,
+        handlers::get_audit_events,
+        handlers::get_projects,
+        handlers::create_project,
+        handlers::get_project,
+        handlers::update_project,
+        handlers::delete_project,
+        handlers::get_project_members,
+        handlers::assign_project_member,
+        handlers::delete_project_member,
+    ),
+    components(schemas(
+        ErrorResponse,
+        HealthResponse,
+        K8sRefInput,
+        K8sRef,
+        RunOrigin,
+        CreateRunRequest,
+        TestRun,
+        CreateDefinitionRequest,
+        UpdateDefinitionRequest,
+        RunDefinitionRequest,
+        TestDefinition,
+        DefinitionVersion,
+        FieldChange,
+        CreateExecutorRequest,
+        UpdateExecutorRequest,
+        Executor,
+        CreateSuiteRequest,
+        UpdateSuiteRequest,
+        TestSuite,
+        SuiteRun,
+        CreateScheduleRequest,
+        ConcurrencyPolicy,
+        TestSchedule,
+        KubernetesHealth,
+        JobLogs,
+        JobStatus,
+        JobListResponse,
+        MessageResponse,
+        SearchHitKind,
+        SearchHit,
+        SearchResults,
+        Scope,
+        Role,
+        CreateTokenRequest,
+        TokenResponse,
+        AssignRoleRequest,
+        RoleAssignmentResponse,
+        AuditEventResponse,
+        AuditEventsResponse,
+        CreateProjectRequest,
+        UpdateProjectRequest,
+        ProjectResponse,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "runs", description = "Test runs and their results"),
+        (name = "test definitions", description = "Reusable tests and running them"),
+        (name = "test executors", description = "Images tests run in"),
+        (name = "test suites", description = "Groups of definitions run together"),
+        (name = "schedules", description = "Cron schedules of definitions and suites"),
+        (name = "kubernetes", description = "The project's Kubernetes jobs"),
+        (name = "search", description = "Full-text search"),
+        (name = "access", description = "API tokens, roles and the audit log"),
+        (name = "projects", description = "Projects and their members"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Require a bearer token, an API token or an OIDC token, on every operation
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .description(Some(
+                        "An API token from `/api/tokens` or a token from the OIDC provider",
+                    ))
+                    .build(),
+            ),
+        );
+        openapi.security = Some(vec![utoipa::openapi::security::SecurityRequirement::new(
+            "bearer",
+            Vec::<String>::new(),
+        )]);
+    }
+}
+
+/// The OpenAPI document, for generating clients
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Swagger UI for browsing and trying out the API
+pub async fn swagger_ui() -> Html<&'static str> {
+    Html(SWAGGER_UI)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_openapi_document() {
+        let doc = serde_json::to_value(ApiDoc::openapi()).unwrap();
+        assert!(doc["openapi"].as_str().unwrap().starts_with("3."));
+
+        assert!(doc["paths"]["/api/test-runs"].is_null());
+        assert!(doc["paths"]["/api/v1/definitions/{id}/run"].is_object());
+        assert!(
+            doc["paths"]["/api/v1/definitions/{id}/versions/{version}/rollback"]["post"]
+                .is_object()
+        );
+        assert_eq!(
+            doc["paths"]["/api/v1/suites/{id}"]["patch"]["responses"]["412"]["content"]
+                ["application/json"]["schema"]["$ref"],
+            "#/components/schemas/ErrorResponse"
+        );
+
+        let runs = &doc["paths"]["/api/v1/runs"];
+        assert!(runs["get"]["parameters"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .any(|p| p["name"] == "definitionId"));
+        assert_eq!(
+            runs["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+            "#/components/schemas/CreateRunRequest"
+        );
+
+        let schemas = &doc["components"]["schemas"];
+        assert!(schemas["TestRun"]["properties"]["createdAt"].is_object());
+        assert!(schemas["TestSuite"]["properties"]["definitionIds"].is_object());
+        assert!(schemas["ErrorResponse"]["properties"]["requestId"].is_object());
+        assert!(doc["components"]["securitySchemes"]["bearer"].is_object());
+        assert_eq!(
+            doc["paths"]["/api/health"]["get"]["security"],
+            serde_json::json!([{}])
+        );
+    }
+}
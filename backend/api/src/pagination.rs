@@ -0,0 +1,344 @@
+use crate::error::ApiError;
+use crate::validation::{Validator, RUN_ORIGINS};
+use axum::{
+    http::HeaderValue,
+    response::{IntoResponse, Response},
+    Json,
+};
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine};
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use sqlx::{
+    postgres::{PgArguments, PgRow},
+    Arguments, FromRow, PgPool,
+};
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+/// Header carrying the number of rows matching a list request's filters
+pub const TOTAL_COUNT_HEADER: &str = "x-total-count";
+
+/// Header carrying the cursor of the next page, when there is one
+pub const NEXT_CURSOR_HEADER: &str = "x-next-cursor";
+
+const DEFAULT_PAGE_SIZE: i64 = 100;
+const MAX_PAGE_SIZE: i64 = 500;
+
+/// Keyset order of rows listed newest first
+pub const NEWEST_FIRST: &str = "($11::text IS NULL OR (created_at, id) < ($11::timestamptz, $12)) ORDER BY created_at DESC, id DESC LIMIT $13";
+
+/// Keyset order of rows listed by name
+pub const BY_NAME: &str =
+    "($11::text IS NULL OR (name, id) > ($11, $12)) ORDER BY name, id LIMIT $13";
+
+/// How one entity is listed
+#[derive(Debug, Clone, Copy)]
+pub struct Listing {
+    /// Plural name used in error messages
+    pub name: &'static str,
+    pub table: &'static str,
+    pub columns: &'static str,
+    /// Condition over the [`ListQuery::arguments`]
+    pub filter: &'static str,
+    /// Filters of [`ListQuery`] the condition uses
+    pub filters: &'static [&'static str],
+    /// [`NEWEST_FIRST`] or [`BY_NAME`]
+    pub order: &'static str,
+}
+
+impl Listing {
+    /// Up to one row more than a page of rows, the total number of matching rows and the page size
+    pub async fn fetch<R>(
+        &self,
+        pool: &PgPool,
+        project_id: Uuid,
+        query: &ListQuery,
+    ) -> Result<(Vec<R>, i64, i64), ApiError>
+    where
+        R: for<'r> FromRow<'r, PgRow> + Send + Unpin,
+    {
+        let (limit, cursor) = query.validate(self.filters)?;
+        let Listing {
+            name,
+            table,
+            columns,
+            filter,
+            order,
+            ..
+        } = self;
+
+        let rows_sql = format!("SELECT {columns} FROM {table} WHERE {filter} AND {order}");
+        let total_sql = format!("SELECT COUNT(*) FROM {table} WHERE {filter}");
+        let rows = sqlx::query_as_with::<_, R, _>(
+            &rows_sql,
+            query.page_arguments(project_id, limit, cursor.as_ref()),
+        )
+        .fetch_all(pool);
+        let total = sqlx::query_scalar_with::<_, i64, _>(&total_sql, query.arguments(project_id))
+            .fetch_one(pool);
+
+        match tokio::try_join!(rows, total) {
+            Ok((rows, total)) => Ok((rows, total, limit)),
+            Err(e) => {
+                tracing::error!("Failed to fetch {}: {}", name, e);
+                Err(ApiError::internal(format!("Failed to fetch {name}")))
+            }
+        }
+    }
+}
+
+/// Query string of the list endpoints
+///
+/// The filters are bound as `$1`..`$10` by [`ListQuery::arguments`], in field
+/// order after the project id, so every list's filter SQL can use the same numbers.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[serde(rename_all = "camelCase")]
+#[into_params(parameter_in = Query)]
+pub struct ListQuery {
+    /// Page size, 100 by default and at most 500
+    pub limit: Option<i64>,
+    /// `x-next-cursor` of the previous page
+    pub cursor: Option<String>,
+    pub status: Option<String>,
+    pub origin: Option<String>,
+    pub definition_id: Option<Uuid>,
+    pub suite_id: Option<Uuid>,
+    pub executor_id: Option<Uuid>,
+    pub label: Option<String>,
+    pub created_after: Option<DateTime<Utc>>,
+    pub created_before: Option<DateTime<Utc>>,
+    /// Case-insensitive search in names
+    pub q: Option<String>,
+}
+
+impl ListQuery {
+    /// Page size, then the page's position; rejects filters `filters` does not list
+    pub fn validate(&self, filters: &[&str]) -> Result<(i64, Option<Cursor>), ApiError> {
+        let mut validator = Validator::new();
+        let given = [
+            ("status", self.status.is_some()),
+            ("origin", self.origin.is_some()),
+            ("definitionId", self.definition_id.is_some()),
+            ("suiteId", self.suite_id.is_some()),
+            ("executorId", self.executor_id.is_some()),
+            ("label", self.label.is_some()),
+            ("createdAfter", self.created_after.is_some()),
+            ("createdBefore", self.created_before.is_some()),
+        ];
+        for (filter, _) in given
+            .iter()
+            .filter(|(f, given)| *given && !filters.contains(f))
+        {
+            validator.error(filter, "Not supported by this list");
+        }
+
+        let limit = self.limit.unwrap_or(DEFAULT_PAGE_SIZE);
+        validator.check(
+            "limit",
+            (1..=MAX_PAGE_SIZE).contains(&limit),
+            format!("Must be between 1 and {MAX_PAGE_SIZE}"),
+        );
+        if let Some(origin) = &self.origin {
+            validator.check(
+                "origin",
+                RUN_ORIGINS.contains(&origin.as_str()),
+                format!("Must be one of {}", RUN_ORIGINS.join(", ")),
+            );
+        }
+        let cursor = self.cursor.as_deref().map(Cursor::decode);
+        validator.check(
+            "cursor",
+            !matches!(cursor, Some(None)),
+            "Not a cursor returned by this API",
+        );
+
+        validator.finish()?;
+        Ok((limit, cursor.flatten()))
+    }
+
+    /// The project id and the filters, as `$1`..`$10`
+    pub fn arguments(&self, project_id: Uuid) -> PgArguments {
+        let mut arguments = PgArguments::default();
+        arguments.add(project_id);
+        arguments.add(&self.status);
+        arguments.add(&self.origin);
+        arguments.add(self.definition_id);
+        arguments.add(self.suite_id);
+        arguments.add(self.executor_id);
+        arguments.add(&self.label);
+        arguments.add(self.created_after);
+        arguments.add(self.created_before);
+        arguments.add(&self.q);
+        arguments
+    }
+
+    /// [`ListQuery::arguments`], then the cursor as `$11` and `$12` and the row limit as `$13`
+    ///
+    /// One row more than the page size is fetched, to tell whether there is a next page.
+    pub fn page_arguments(
+        &self,
+        project_id: Uuid,
+        limit: i64,
+        cursor: Option<&Cursor>,
+    ) -> PgArguments {
+        let mut arguments = self.arguments(project_id);
+        arguments.add(cursor.map(|cursor| cursor.key.clone()));
+        arguments.add(cursor.map(|cursor| cursor.id));
+        arguments.add(limit + 1);
+        arguments
+    }
+}
+
+/// Position after the last row of a page: its sort key and id
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cursor {
+    pub key: String,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn new(key: impl Into<String>, id: Uuid) -> Self {
+        Self {
+            key: key.into(),
+            id,
+        }
+    }
+
+    /// Cursor of a row sorted by creation time
+    pub fn created_at(created_at: DateTime<Utc>, id: Uuid) -> Self {
+        Self::new(created_at.to_rfc3339_opts(SecondsFormat::Micros, true), id)
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}\n{}", self.id, self.key))
+    }
+
+    pub fn decode(cursor: &str) -> Option<Self> {
+        let bytes = URL_SAFE_NO_PAD.decode(cursor).ok()?;
+        let (id, key) = std::str::from_utf8(&bytes).ok()?.split_once('\n')?;
+        Some(Self::new(key, Uuid::parse_str(id).ok()?))
+    }
+}
+
+/// One page of a list, sent as a JSON array with the total count and next cursor in headers
+#[derive(Debug)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    pub total: i64,
+    pub next_cursor: Option<String>,
+}
+
+impl<T> Page<T> {
+    /// Page of up to `limit` of `rows`, fetched with [`ListQuery::page_arguments`]
+    pub fn new<R>(
+        mut rows: Vec<R>,
+        limit: i64,
+        total: i64,
+        cursor: impl Fn(&R) -> Cursor,
+        item: impl FnMut(R) -> T,
+    ) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit.max(0) as usize);
+        let next_cursor = rows
+            .last()
+            .filter(|_| has_more)
+            .map(|row| cursor(row).encode());
+
+        Self {
+            items: rows.into_iter().map(item).collect(),
+            total,
+            next_cursor,
+        }
+    }
+
+    /// The same page with each item converted by `item`
+    pub fn map<U>(self, item: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(item).collect(),
+            total: self.total,
+            next_cursor: self.next_cursor,
+        }
+    }
+}
+
+impl<T: Serialize> IntoResponse for Page<T> {
+    fn into_response(self) -> Response {
+        let mut response = Json(self.items).into_response();
+        let headers = response.headers_mut();
+        headers.insert(TOTAL_COUNT_HEADER, HeaderValue::from(self.total));
+        if let Some(cursor) = self
+            .next_cursor
+            .and_then(|c| HeaderValue::from_str(&c).ok())
+        {
+            headers.insert(NEXT_CURSOR_HEADER, cursor);
+        }
+        response
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cursor_round_trip() {
+        let cursor = Cursor::created_at(Utc::now(), Uuid::new_v4());
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+
+        let cursor = Cursor::new("Node.js\nexecutor", Uuid::new_v4());
+        assert_eq!(Cursor::decode(&cursor.encode()), Some(cursor));
+
+        assert_eq!(Cursor::decode("not a cursor"), None);
+        assert_eq!(Cursor::decode(&URL_SAFE_NO_PAD.encode("x\ny")), None);
+    }
+
+    #[test]
+    fn test_page() {
+        let rows: Vec<(Uuid, i64)> = (0..4).map(|i| (Uuid::new_v4(), i)).collect();
+        let last = rows[2].0;
+        let page = Page::new(
+            rows,
+            3,
+            10,
+            |row| Cursor::new(row.1.to_string(), row.0),
+            |row| row.1,
+        );
+        assert_eq!(page.items, vec![0, 1, 2]);
+        assert_eq!(page.total, 10);
+        assert_eq!(
+            page.next_cursor.as_deref().and_then(Cursor::decode),
+            Some(Cursor::new("2", last))
+        );
+
+        let page = Page::new(
+            vec![1, 2],
+            3,
+            2,
+            |_| Cursor::new("", Uuid::nil()),
+            |row| row,
+        );
+        assert_eq!(page.next_cursor, None);
+    }
+
+    #[test]
+    fn test_list_query_validation() {
+        let query: ListQuery =
+            serde_json::from_value(serde_json::json!({ "status": "failed", "limit": 20 })).unwrap();
+        assert_eq!(query.validate(&["status"]).unwrap(), (20, None));
+
+        let error = query.validate(&["label"]).unwrap_err();
+        assert_eq!(
+            error.details.unwrap()["fields"]["status"],
+            "Not supported by this list"
+        );
+
+        let query = ListQuery {
+            limit: Some(0),
+            cursor: Some("garbage".to_string()),
+            ..Default::default()
+        };
+        let fields = query.validate(&[]).unwrap_err().details.unwrap()["fields"].clone();
+        assert!(fields["limit"].is_string());
+        assert!(fields["cursor"].is_string());
+    }
+}
@@ -0,0 +1,298 @@
+use crate::auth::{AuthConfig, Identity};
+use crate::authz::{effective_role, Role};
+use crate::error::ApiError;
+use crate::routes::api_path;
+use axum::{
+    extract::{MatchedPath, RawPathParams, Request},
+    http::{Method, Uri},
+    middleware::Next,
+    response::Response,
+    Extension,
+};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Slug of the project the unscoped `/api/...` routes act on
+pub const DEFAULT_PROJECT: &str = "default";
+
+/// Id of the default project, which every row created before projects belongs to
+pub const DEFAULT_PROJECT_ID: Uuid = Uuid::from_u128(1);
+
+/// Top-level routes whose entities belong to a project
+const PROJECT_SCOPED: [&str; 11] = [
+    "runs",
+    "test-runs",
+    "definitions",
+    "test-definitions",
+    "executors",
+    "test-executors",
+    "suites",
+    "test-suites",
+    "schedules",
+    "k8s",
+    "search",
+];
+
+/// The project a request acts on
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Project {
+    pub id: Uuid,
+    pub slug: String,
+    /// Namespace the project's Kubernetes jobs run in
+    pub k8s_namespace: String,
+}
+
+impl Default for Project {
+    fn default() -> Self {
+        Self {
+            id: DEFAULT_PROJECT_ID,
+            slug: DEFAULT_PROJECT.to_string(),
+            k8s_namespace: "default".to_string(),
+        }
+    }
+}
+
+impl Project {
+    /// Look up a project by id, for work that is not tied to a request
+    pub async fn load(pool: &PgPool, id: Uuid) -> Result<Option<Self>, sqlx::Error> {
+        sqlx::query_as::<_, (Uuid, String, String)>(
+            "SELECT id, slug, k8s_namespace FROM projects WHERE id = $1",
+        )
+        .bind(id)
+        .fetch_optional(pool)
+        .await
+        .map(|row| {
+            row.map(|(id, slug, k8s_namespace)| Self {
+                id,
+                slug,
+                k8s_namespace,
+            })
+        })
+    }
+}
+
+/// Slug of the project named in a `/api/projects/:project/...` URL
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProjectRef(pub String);
+
+/// Whether the route `path`, relative to `/api` or `/api/v1`, acts on a single project
+pub fn is_project_scoped(path: &str) -> bool {
+    let path = api_path(path);
+    let first = path.trim_start_matches('/').split('/').next();
+    path == "/projects/:project"
+        || path.starts_with("/projects/:project/")
+        || first.is_some_and(|s| PROJECT_SCOPED.contains(&s))
+}
+
+/// Role a caller with the role `own` acts as in a project, or None if they are kept out
+///
+/// The default project is open to every caller, with a member role raising their own.
+/// Other projects only let in their members, who act as their member role, and admins.
+pub fn project_role(
+    own: Option<Role>,
+    member: Option<Role>,
+    default_project: bool,
+) -> Option<Role> {
+    if default_project {
+        own.max(member)
+    } else if own == Some(Role::Admin) {
+        own
+    } else {
+        member
+    }
+}
+
+/// `/api/<rest>` and the project slug of a project-scoped URL path
+///
+/// `/api/projects/team-a/test-runs/1` becomes `("team-a", "/api/test-runs/1")`
+/// and `/api/v1/projects/team-a/runs/1` becomes `("team-a", "/api/v1/runs/1")`;
+/// paths under a project that are not project-scoped entities are left alone.
+pub fn unscope_path(path: &str) -> Option<(&str, String)> {
+    let (prefix, rest) = match path.strip_prefix("/api/v1/projects/") {
+        Some(rest) => ("/api/v1", rest),
+        None => ("/api", path.strip_prefix("/api/projects/")?),
+    };
+    let (slug, rest) = rest.split_once('/')?;
+    let first = rest.split('/').next()?;
+    PROJECT_SCOPED
+        .contains(&first)
+        .then(|| (slug, format!("{prefix}/{rest}")))
+}
+
+/// Serve `/api/projects/:project/...` with the routes under `/api`
+///
+/// The request is rewritten onto the unscoped route, so the same handlers,
+/// roles and audit entries apply, and the project slug travels as a [`ProjectRef`].
+pub async fn rewrite_project_path(mut request: Request, next: Next) -> Response {
+    if let Some((slug, path)) = unscope_path(request.uri().path()) {
+        let path_and_query = match request.uri().query() {
+            Some(query) => format!("{path}?{query}"),
+            None => path,
+        };
+        let slug = slug.to_string();
+
+        let mut parts = request.uri().clone().into_parts();
+        if let Ok(path_and_query) = path_and_query.parse() {
+            parts.path_and_query = Some(path_and_query);
+            if let Ok(uri) = Uri::from_parts(parts) {
+                *request.uri_mut() = uri;
+                request.extensions_mut().insert(ProjectRef(slug));
+            }
+        }
+    }
+
+    next.run(request).await
+}
+
+/// Resolve the request's project, applying the caller's role as a member of it
+///
+/// Unscoped routes act on the default project. Membership only counts on routes
+/// that act on a single project, never on tokens, roles or the audit log. Callers
+/// kept out of a project are told it does not exist.
+pub async fn resolve_project(
+    Extension(pool): Extension<PgPool>,
+    Extension(auth): Extension<AuthConfig>,
+    matched_path: MatchedPath,
+    params: RawPathParams,
+    mut request: Request,
+    next: Next,
+) -> Result<Response, ApiError> {
+    let slug = params
+        .iter()
+        .find(|(key, _)| *key == "project")
+        .map(|(_, value)| value.to_string())
+        .or_else(|| {
+            request
+                .extensions()
+                .get::<ProjectRef>()
+                .map(|ProjectRef(slug)| slug.clone())
+        })
+        .unwrap_or_else(|| DEFAULT_PROJECT.to_string());
+    let identity = request
+        .extensions()
+        .get::<Identity>()
+        .cloned()
+        .unwrap_or(Identity::Anonymous);
+
+    let row = sqlx::query_as::<_, (Uuid, String, String, Vec<String>)>(
+        "SELECT p.id, p.slug, p.k8s_namespace, ARRAY(SELECT m.role FROM project_members m WHERE m.project_id = p.id AND m.subject = ANY($2)) FROM projects p WHERE p.slug = $1",
+    )
+    .bind(&slug)
+    .bind(identity.subjects())
+    .fetch_optional(&pool)
+    .await
+    .map_err(|e| {
+        tracing::error!("Failed to look up project {}: {}", slug, e);
+        ApiError::internal("Failed to look up project")
+    })?;
+    let Some((id, slug, k8s_namespace, member_roles)) = row else {
+        return Err(ApiError::not_found(format!("Project {slug} not found")));
+    };
+
+    if is_project_scoped(matched_path.as_str()) {
+        let mut member_role = member_roles.iter().filter_map(|r| Role::parse(r)).max();
+        // Only admins of every project update or delete one, which could move it into any namespace
+        if api_path(matched_path.as_str()) == "/projects/:project"
+            && request.method() != Method::GET
+        {
+            member_role = member_role.map(|role| role.min(Role::Editor));
+        }
+        let own_role = effective_role(&identity, &auth);
+        match project_role(own_role, member_role, id == DEFAULT_PROJECT_ID) {
+            Some(role) if Some(role) != own_role => {
+                request.extensions_mut().insert(identity.in_project(role));
+            }
+            Some(_) => {}
+            // Without any role in the default project, authorization refuses the request
+            None if id == DEFAULT_PROJECT_ID => {}
+            None => {
+                tracing::warn!("Rejected {:?}: not a member of project {}", identity, slug);
+                return Err(ApiError::not_found(format!("Project {slug} not found")));
+            }
+        }
+    }
+    request.extensions_mut().insert(Project {
+        id,
+        slug,
+        k8s_namespace,
+    });
+
+    Ok(next.run(request).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unscope_path() {
+        assert_eq!(
+            unscope_path("/api/projects/team-a/test-runs/1"),
+            Some(("team-a", "/api/test-runs/1".to_string()))
+        );
+        assert_eq!(
+            unscope_path("/api/projects/team-a/k8s/jobs"),
+            Some(("team-a", "/api/k8s/jobs".to_string()))
+        );
+        assert_eq!(
+            unscope_path("/api/projects/team-a/search"),
+            Some(("team-a", "/api/search".to_string()))
+        );
+        assert_eq!(
+            unscope_path("/api/v1/projects/team-a/definitions/1/run"),
+            Some(("team-a", "/api/v1/definitions/1/run".to_string()))
+        );
+        assert_eq!(unscope_path("/api/projects/team-a/members"), None);
+        assert_eq!(unscope_path("/api/v1/projects/team-a/members"), None);
+        assert_eq!(unscope_path("/api/projects/team-a/tokens"), None);
+        assert_eq!(unscope_path("/api/projects/team-a"), None);
+        assert_eq!(unscope_path("/api/test-runs"), None);
+    }
+
+    #[test]
+    fn test_is_project_scoped() {
+        assert!(is_project_scoped("/api/test-definitions/:id"));
+        assert!(is_project_scoped("/k8s/jobs/:job_name"));
+        assert!(is_project_scoped("/projects/:project/members"));
+        assert!(is_project_scoped("/api/v1/suites/:id/run"));
+        assert!(is_project_scoped("/api/v1/projects/:project/members"));
+        assert!(is_project_scoped("/api/v1/projects/:project"));
+        assert!(is_project_scoped("/projects/:project"));
+        assert!(!is_project_scoped("/projects"));
+        assert!(!is_project_scoped("/roles/:subject"));
+        assert!(!is_project_scoped("/audit"));
+    }
+
+    #[test]
+    fn test_project_role() {
+        // Everyone keeps their own role in the default project
+        assert_eq!(
+            project_role(Some(Role::Editor), None, true),
+            Some(Role::Editor)
+        );
+        assert_eq!(
+            project_role(Some(Role::Viewer), Some(Role::Runner), true),
+            Some(Role::Runner)
+        );
+        assert_eq!(project_role(None, None, true), None);
+
+        // Non-members are kept out of other projects, whatever their own role
+        assert_eq!(project_role(Some(Role::Editor), None, false), None);
+        assert_eq!(project_role(Some(Role::Viewer), None, false), None);
+        assert_eq!(project_role(None, None, false), None);
+
+        // Members act as their member role there, and admins are let in everywhere
+        assert_eq!(
+            project_role(Some(Role::Editor), Some(Role::Viewer), false),
+            Some(Role::Viewer)
+        );
+        assert_eq!(
+            project_role(None, Some(Role::Runner), false),
+            Some(Role::Runner)
+        );
+        assert_eq!(
+            project_role(Some(Role::Admin), None, false),
+            Some(Role::Admin)
+        );
+    }
+}
@@ -1,50 +1,209 @@
+use crate::audit::{audit, request_id};
+use crate::auth::{authenticate, AuthConfig};
+use crate::authz::authorize;
+use crate::error::json_errors;
 use crate::handlers::*;
+use crate::legacy::{self, deprecated};
+use crate::openapi::{openapi_json, swagger_ui};
+use crate::projects::{resolve_project, rewrite_project_path};
 use axum::{
-    routing::{delete, get, post},
+    middleware,
+    routing::{delete, get, post, put},
     Extension, Router,
 };
 use sqlx::PgPool;
 use tower_http::cors::CorsLayer;
 
-pub fn create_app(pool: PgPool) -> Router {
+/// Route `path` relative to `/api/v1`, or to `/api` for the legacy routes
+///
+/// Roles, audit targets and project scoping are decided on this, so both
+/// surfaces are treated the same.
+pub fn api_path(path: &str) -> &str {
+    let path = path.strip_prefix("/api").unwrap_or(path);
+    match path.strip_prefix("/v1") {
+        Some(rest) if rest.is_empty() || rest.starts_with('/') => rest,
+        _ => path,
+    }
+}
+
+pub fn create_app(pool: PgPool, auth: AuthConfig) -> Router {
+    // Every route acts on a project, the default one unless the URL names another,
+    // and needs the role `authz::required_role` gives it. Authorized POST, PUT and
+    // DELETE requests are recorded in `audit_events`
+    let authenticated_routes = Router::new()
+        .nest("/v1", v1_routes())
+        .merge(legacy_routes())
+        .route_layer(middleware::from_fn(audit))
+        .route_layer(middleware::from_fn(authorize))
+        .route_layer(middleware::from_fn(resolve_project))
+        .layer(middleware::from_fn(authenticate));
+
     let api_routes = Router::new()
         .route("/health", get(health_check))
+        .route("/openapi.json", get(openapi_json))
+        .route("/docs", get(swagger_ui))
+        .merge(authenticated_routes);
+
+    let app = Router::new()
+        .nest("/api", api_routes)
+        .layer(Extension(pool))
+        .layer(Extension(auth))
+        .layer(middleware::from_fn(json_errors))
+        .layer(middleware::from_fn(request_id))
+        .layer(CorsLayer::permissive());
+
+    // `/api/projects/:project/...` is rewritten onto the routes above before they are matched
+    Router::new()
+        .fallback_service(app)
+        .layer(middleware::from_fn(rewrite_project_path))
+}
+
+/// The current API, served under `/api/v1`
+fn v1_routes() -> Router {
+    Router::new()
         .route("/runs", get(get_runs).post(create_run))
         .route("/runs/:id", get(get_run).delete(delete_run))
-        .route("/test-runs", get(get_runs).post(create_run))
-        .route("/test-runs/:id", get(get_run).delete(delete_run))
-        .route(
-            "/test-definitions",
-            get(get_definitions).post(create_definition),
-        )
+        .route("/definitions", get(get_definitions).post(create_definition))
         .route(
-            "/test-definitions/:id",
+            "/definitions/:id",
             get(get_definition)
                 .put(update_definition)
+                .patch(update_definition)
                 .delete(delete_definition),
         )
-        .route("/test-definitions/:id/run", post(run_definition))
-        .route("/test-executors", get(get_executors).post(create_executor))
+        .route("/definitions/:id/run", post(run_definition))
+        .route("/definitions/:id/versions", get(get_definition_versions))
         .route(
-            "/test-executors/:id",
+            "/definitions/:id/versions/:version",
+            get(get_definition_version),
+        )
+        .route(
+            "/definitions/:id/versions/:version/rollback",
+            post(rollback_definition),
+        )
+        .route("/executors", get(get_executors).post(create_executor))
+        .route(
+            "/executors/:id",
             get(get_executor)
                 .put(update_executor)
+                .patch(update_executor)
                 .delete(delete_executor),
         )
-        .route("/test-suites", get(get_suites).post(create_suite))
+        .route("/suites", get(get_suites).post(create_suite))
         .route(
-            "/test-suites/:id",
-            get(get_suite).put(update_suite).delete(delete_suite),
+            "/suites/:id",
+            get(get_suite)
+                .put(update_suite)
+                .patch(update_suite)
+                .delete(delete_suite),
+        )
+        .route("/suites/:id/run", post(run_suite))
+        .route("/schedules", get(get_schedules).post(create_schedule))
+        .route(
+            "/schedules/:id",
+            get(get_schedule)
+                .put(update_schedule)
+                .delete(delete_schedule),
         )
-        .route("/test-suites/:id/run", post(run_suite))
         .route("/k8s/health", get(k8s_health))
         .route("/k8s/logs/:job_name", get(get_job_logs))
         .route("/k8s/status/:job_name", get(get_job_status))
-        .route("/k8s/jobs", get(list_jobs))
-        .route("/k8s/jobs/:job_name", delete(delete_job));
+        .merge(unchanged_routes())
+}
 
+/// The unversioned routes from before `/api/v1`, with their old field names
+fn legacy_routes() -> Router {
     Router::new()
-        .nest("/api", api_routes)
-        .layer(Extension(pool))
-        .layer(CorsLayer::permissive())
+        .route("/runs", get(legacy::get_runs).post(legacy::create_run))
+        .route("/runs/:id", get(legacy::get_run).delete(delete_run))
+        .route("/test-runs", get(legacy::get_runs).post(legacy::create_run))
+        .route("/test-runs/:id", get(legacy::get_run).delete(delete_run))
+        .route(
+            "/test-definitions",
+            get(legacy::get_definitions).post(legacy::create_definition),
+        )
+        .route(
+            "/test-definitions/:id",
+            get(legacy::get_definition)
+                .put(legacy::update_definition)
+                .patch(legacy::update_definition)
+                .delete(delete_definition),
+        )
+        .route("/test-definitions/:id/run", post(legacy::run_definition))
+        .route(
+            "/test-definitions/:id/versions",
+            get(get_definition_versions),
+        )
+        .route(
+            "/test-definitions/:id/versions/:version",
+            get(get_definition_version),
+        )
+        .route(
+            "/test-definitions/:id/versions/:version/rollback",
+            post(rollback_definition),
+        )
+        .route(
+            "/test-executors",
+            get(legacy::get_executors).post(legacy::create_executor),
+        )
+        .route(
+            "/test-executors/:id",
+            get(legacy::get_executor)
+                .put(legacy::update_executor)
+                .patch(legacy::update_executor)
+                .delete(delete_executor),
+        )
+        .route(
+            "/test-suites",
+            get(legacy::get_suites).post(legacy::create_suite),
+        )
+        .route(
+            "/test-suites/:id",
+            get(legacy::get_suite)
+                .put(legacy::update_suite)
+                .patch(legacy::update_suite)
+                .delete(delete_suite),
+        )
+        .route("/test-suites/:id/run", post(legacy::run_suite))
+        .route(
+            "/schedules",
+            get(legacy::get_schedules).post(legacy::create_schedule),
+        )
+        .route(
+            "/schedules/:id",
+            get(legacy::get_schedule)
+                .put(legacy::update_schedule)
+                .delete(delete_schedule),
+        )
+        .route("/k8s/health", get(legacy::k8s_health))
+        .route("/k8s/logs/:job_name", get(legacy::get_job_logs))
+        .route("/k8s/status/:job_name", get(legacy::get_job_status))
+        .merge(unchanged_routes())
+        .layer(middleware::from_fn(deprecated))
+}
+
+/// Routes whose requests and responses are the same in both surfaces
+fn unchanged_routes() -> Router {
+    Router::new()
+        .route("/k8s/jobs", get(list_jobs))
+        .route("/k8s/jobs/:job_name", delete(delete_job))
+        .route("/tokens", get(get_tokens).post(create_token))
+        .route("/tokens/:id", delete(revoke_token))
+        .route("/roles", get(get_role_assignments))
+        .route(
+            "/roles/:subject",
+            put(assign_role).delete(delete_role_assignment),
+        )
+        .route("/audit", get(get_audit_events))
+        .route("/search", get(search))
+        .route("/projects", get(get_projects).post(create_project))
+        .route(
+            "/projects/:project",
+            get(get_project).put(update_project).delete(delete_project),
+        )
+        .route("/projects/:project/members", get(get_project_members))
+        .route(
+            "/projects/:project/members/:subject",
+            put(assign_project_member).delete(delete_project_member),
+        )
 }
@@ -0,0 +1,203 @@
+use crate::handlers::{get_suite_definitions, launch_run, RunAttribution};
+use crate::k8s::KubernetesClient;
+use crate::projects::Project;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use sparktest_core::{job_name, ConcurrencyPolicy, CronSchedule};
+use sqlx::PgPool;
+use std::time::Duration;
+use uuid::Uuid;
+
+/// How often the scheduler looks for due schedules
+const TICK_INTERVAL: Duration = Duration::from_secs(30);
+
+type DueScheduleRow = (
+    Uuid,
+    String,
+    String,
+    String,
+    Option<Uuid>,
+    Option<Uuid>,
+    String,
+    i32,
+    i32,
+    DateTime<Utc>,
+    Uuid,
+);
+
+/// Fire due test schedules forever
+pub async fn run_scheduler(pool: PgPool) {
+    tracing::info!("Starting test scheduler");
+
+    let mut interval = tokio::time::interval(TICK_INTERVAL);
+    loop {
+        interval.tick().await;
+        if let Err(e) = run_due_schedules(&pool).await {
+            tracing::error!("Failed to run due test schedules: {}", e);
+        }
+    }
+}
+
+/// Start runs for every enabled schedule whose next run time has passed
+pub async fn run_due_schedules(pool: &PgPool) -> Result<()> {
+    let now = Utc::now();
+    let due = sqlx::query_as::<_, DueScheduleRow>(
+        "SELECT id, name, cron_expression, timezone, test_definition_id, test_suite_id, concurrency_policy, successful_runs_history_limit, failed_runs_history_limit, next_run_at, project_id FROM test_schedules WHERE enabled AND next_run_at <= $1"
+    )
+    .bind(now)
+    .fetch_all(pool)
+    .await?;
+
+    for (
+        id,
+        name,
+        cron_expression,
+        timezone,
+        definition_id,
+        suite_id,
+        concurrency_policy,
+        successful_limit,
+        failed_limit,
+        next_run_at,
+        project_id,
+    ) in due
+    {
+        let next = CronSchedule::parse(&cron_expression, Some(&timezone))
+            .ok()
+            .and_then(|schedule| schedule.next_after(now));
+
+        // Claim this firing so several API replicas never start it twice
+        let claimed = sqlx::query(
+            "UPDATE test_schedules SET last_run_at = $1, next_run_at = $2 WHERE id = $3 AND next_run_at = $4",
+        )
+        .bind(now)
+        .bind(next)
+        .bind(id)
+        .bind(next_run_at)
+        .execute(pool)
+        .await?
+        .rows_affected()
+            > 0;
+
+        if !claimed {
+            continue;
+        }
+
+        let Some(project) = Project::load(pool, project_id).await? else {
+            continue;
+        };
+        let policy = concurrency_policy.parse().unwrap_or_default();
+        if let Err(e) =
+            fire_schedule(pool, &project, id, &name, policy, definition_id, suite_id).await
+        {
+            tracing::error!("Failed to start runs for schedule {}: {}", name, e);
+        }
+
+        prune_history(pool, id, "succeeded", successful_limit).await?;
+        prune_history(pool, id, "failed", failed_limit).await?;
+    }
+
+    Ok(())
+}
+
+async fn fire_schedule(
+    pool: &PgPool,
+    project: &Project,
+    schedule_id: Uuid,
+    schedule_name: &str,
+    policy: ConcurrencyPolicy,
+    definition_id: Option<Uuid>,
+    suite_id: Option<Uuid>,
+) -> Result<()> {
+    let active: Vec<Uuid> = sqlx::query_scalar(
+        "SELECT id FROM test_runs WHERE schedule_id = $1 AND status = 'running'",
+    )
+    .bind(schedule_id)
+    .fetch_all(pool)
+    .await?;
+
+    if !active.is_empty() {
+        match policy {
+            ConcurrencyPolicy::Allow => {}
+            ConcurrencyPolicy::Forbid => {
+                tracing::info!(
+                    "Skipping schedule {}: {} run(s) still active",
+                    schedule_name,
+                    active.len()
+                );
+                return Ok(());
+            }
+            ConcurrencyPolicy::Replace => cancel_runs(pool, project, &active).await?,
+        }
+    }
+
+    let definition_ids = match (definition_id, suite_id) {
+        (Some(definition_id), _) => vec![definition_id],
+        (None, Some(suite_id)) => sqlx::query_scalar::<_, Vec<Uuid>>(
+            "SELECT test_definition_ids FROM test_suites WHERE id = $1 AND project_id = $2",
+        )
+        .bind(suite_id)
+        .bind(project.id)
+        .fetch_optional(pool)
+        .await?
+        .ok_or_else(|| anyhow!("Test suite {suite_id} not found"))?,
+        (None, None) => return Err(anyhow!("Schedule has no target")),
+    };
+
+    for def in get_suite_definitions(pool, project.id, &definition_ids).await? {
+        let run_name = format!("{} - {}", schedule_name, def.name);
+        let run = launch_run(
+            pool,
+            project,
+            &run_name,
+            &def.image,
+            &def.commands,
+            RunAttribution {
+                definition_id: Some(def.id),
+                definition_version: Some(def.version),
+                schedule_id: Some(schedule_id),
+                ..Default::default()
+            },
+        )
+        .await?;
+
+        tracing::info!("Schedule {} started run {}", schedule_name, run.id);
+    }
+
+    Ok(())
+}
+
+/// Stop the jobs of active runs and mark the runs as failed
+async fn cancel_runs(pool: &PgPool, project: &Project, run_ids: &[Uuid]) -> Result<()> {
+    match KubernetesClient::for_namespace(&project.k8s_namespace).await {
+        Ok(client) => {
+            for run_id in run_ids {
+                if let Err(e) = client.delete_job(&job_name(&run_id.to_string())).await {
+                    tracing::warn!("Failed to delete job for replaced run {}: {}", run_id, e);
+                }
+            }
+        }
+        Err(e) => tracing::warn!("Failed to initialize Kubernetes client: {}", e),
+    }
+
+    sqlx::query("UPDATE test_runs SET status = 'failed' WHERE id = ANY($1)")
+        .bind(run_ids)
+        .execute(pool)
+        .await?;
+
+    Ok(())
+}
+
+/// Delete the oldest finished runs of a schedule beyond its history limit
+async fn prune_history(pool: &PgPool, schedule_id: Uuid, status: &str, limit: i32) -> Result<()> {
+    sqlx::query(
+        "DELETE FROM test_runs WHERE id IN (SELECT id FROM test_runs WHERE schedule_id = $1 AND status = $2 ORDER BY created_at DESC OFFSET $3)",
+    )
+    .bind(schedule_id)
+    .bind(status)
+    .bind(i64::from(limit))
+    .execute(pool)
+    .await?;
+
+    Ok(())
+}
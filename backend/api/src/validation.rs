@@ -0,0 +1,331 @@
+use crate::error::ApiError;
+use axum::http::StatusCode;
+use serde_json::{Map, Value};
+use sqlx::PgPool;
+use uuid::Uuid;
+
+/// Execution modes a test suite may use
+pub const EXECUTION_MODES: [&str; 2] = ["sequential", "parallel"];
+
+/// Origins a run may be created with
+pub const RUN_ORIGINS: [&str; 2] = ["api", "crd"];
+
+/// Longest name accepted for definitions, executors, suites and runs
+const MAX_NAME_LENGTH: usize = 255;
+
+/// Longest label accepted, matching the web app's limit
+const MAX_LABEL_LENGTH: usize = 100;
+
+/// Collects field-level errors in a request body
+///
+/// Each field keeps its first error. [`Validator::finish`] turns the errors into
+/// a 422 whose details map each field to its message:
+///
+/// ```json
+/// {"code": "validation_failed", "details": {"fields": {"image": "..."}}, ...}
+/// ```
+#[derive(Debug, Default)]
+pub struct Validator {
+    errors: Map<String, Value>,
+}
+
+impl Validator {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record `message` for `field` unless the field already has an error
+    pub fn error(&mut self, field: &str, message: impl Into<String>) {
+        if !self.errors.contains_key(field) {
+            self.errors
+                .insert(field.to_string(), Value::String(message.into()));
+        }
+    }
+
+    pub fn check(&mut self, field: &str, valid: bool, message: impl Into<String>) {
+        if !valid {
+            self.error(field, message);
+        }
+    }
+
+    pub fn name(&mut self, field: &str, name: &str) {
+        self.check(field, !name.trim().is_empty(), "Must not be empty");
+        self.check(
+            field,
+            name.len() <= MAX_NAME_LENGTH,
+            format!("Must be at most {MAX_NAME_LENGTH} characters"),
+        );
+    }
+
+    pub fn image(&mut self, field: &str, image: &str) {
+        self.check(
+            field,
+            is_image_reference(image),
+            format!("'{image}' is not a valid image reference"),
+        );
+    }
+
+    pub fn commands(&mut self, field: &str, commands: &[String]) {
+        self.check(
+            field,
+            !commands.is_empty(),
+            "Must have at least one command",
+        );
+        self.check(
+            field,
+            commands.iter().all(|c| !c.trim().is_empty()),
+            "Commands must not be empty",
+        );
+    }
+
+    pub fn labels(&mut self, field: &str, labels: &[String]) {
+        if let Some(label) = labels.iter().find(|label| !is_label(label)) {
+            self.error(
+                field,
+                format!(
+                    "'{label}' is not a valid label; use up to {MAX_LABEL_LENGTH} letters, digits, '-', '_' or '.'"
+                ),
+            );
+        }
+    }
+
+    pub fn execution_mode(&mut self, field: &str, mode: &str) {
+        self.check(
+            field,
+            EXECUTION_MODES.contains(&mode),
+            format!("Must be one of {}", EXECUTION_MODES.join(", ")),
+        );
+    }
+
+    /// Record an error unless every id has a row in `table` in the project
+    ///
+    /// `table` is one of our own table names, never user input.
+    pub async fn existing_ids(
+        &mut self,
+        pool: &PgPool,
+        field: &str,
+        table: &str,
+        project_id: Uuid,
+        ids: &[Uuid],
+    ) -> Result<(), ApiError> {
+        if ids.is_empty() {
+            return Ok(());
+        }
+
+        let missing = sqlx::query_scalar::<_, Uuid>(&format!(
+            "SELECT id FROM UNNEST($1::uuid[]) AS ids(id) WHERE NOT EXISTS (SELECT 1 FROM {table} t WHERE t.id = ids.id AND t.project_id = $2)"
+        ))
+        .bind(ids)
+        .bind(project_id)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| {
+            tracing::error!("Failed to look up {} for validation: {}", table, e);
+            ApiError::internal("Failed to validate request")
+        })?;
+
+        if !missing.is_empty() {
+            let missing: Vec<String> = missing.iter().map(Uuid::to_string).collect();
+            self.error(
+                field,
+                format!("Not found in this project: {}", missing.join(", ")),
+            );
+        }
+        Ok(())
+    }
+
+    /// A 422 listing every field error, or Ok if there were none
+    pub fn finish(self) -> Result<(), ApiError> {
+        if self.errors.is_empty() {
+            return Ok(());
+        }
+
+        let mut error = ApiError::new(
+            StatusCode::UNPROCESSABLE_ENTITY,
+            "The request has invalid fields",
+        )
+        .with_details(serde_json::json!({ "fields": self.errors }));
+        error.code = "validation_failed";
+        Err(error)
+    }
+}
+
+/// Whether `image` is a container image reference such as
+/// `ghcr.io/org/app:1.2`, `localhost:5000/app` or `node@sha256:<digest>`
+pub fn is_image_reference(image: &str) -> bool {
+    let (name, digest) = match image.split_once('@') {
+        Some((name, digest)) => (name, Some(digest)),
+        None => (image, None),
+    };
+    // A colon after the last slash starts the tag; earlier ones belong to a registry port
+    let (name, tag) = match name.rfind(':') {
+        Some(i) if !name[i..].contains('/') => (&name[..i], Some(&name[i + 1..])),
+        _ => (name, None),
+    };
+
+    if name.is_empty() || name.len() > MAX_NAME_LENGTH {
+        return false;
+    }
+    if !tag.is_none_or(is_tag) || !digest.is_none_or(is_digest) {
+        return false;
+    }
+
+    let mut components: Vec<&str> = name.split('/').collect();
+    let first = components[0];
+    if components.len() > 1 && (first.contains(['.', ':']) || first == "localhost") {
+        if !is_registry(first) {
+            return false;
+        }
+        components.remove(0);
+    }
+    components.into_iter().all(is_path_component)
+}
+
+fn is_tag(tag: &str) -> bool {
+    tag.len() <= 128
+        && tag
+            .chars()
+            .next()
+            .is_some_and(|c| c.is_ascii_alphanumeric() || c == '_')
+        && tag
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '.' | '-'))
+}
+
+fn is_digest(digest: &str) -> bool {
+    let Some((algorithm, hex)) = digest.split_once(':') else {
+        return false;
+    };
+    !algorithm.is_empty()
+        && algorithm.chars().all(|c| {
+            c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '+' | '.' | '_' | '-')
+        })
+        && hex.len() >= 32
+        && hex.chars().all(|c| c.is_ascii_hexdigit())
+}
+
+fn is_registry(registry: &str) -> bool {
+    let (host, port) = match registry.split_once(':') {
+        Some((host, port)) => (host, Some(port)),
+        None => (registry, None),
+    };
+    let host_ok = host.split('.').all(|label| {
+        !label.is_empty()
+            && !label.starts_with('-')
+            && !label.ends_with('-')
+            && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+    });
+    let port_ok = port.is_none_or(|port| {
+        !port.is_empty() && port.len() <= 5 && port.chars().all(|c| c.is_ascii_digit())
+    });
+    host_ok && port_ok
+}
+
+/// A lowercase repository path component such as `my-app` or `app_v2`
+fn is_path_component(component: &str) -> bool {
+    let alphanumeric = |c: char| c.is_ascii_lowercase() || c.is_ascii_digit();
+    component.starts_with(alphanumeric)
+        && component.ends_with(alphanumeric)
+        && !component.contains("..")
+        && component
+            .chars()
+            .all(|c| alphanumeric(c) || matches!(c, '.' | '_' | '-'))
+}
+
+/// Whether `label` is a valid definition or suite label
+pub fn is_label(label: &str) -> bool {
+    !label.is_empty()
+        && label.len() <= MAX_LABEL_LENGTH
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+}
+
+/// Whether `name` is a valid Kubernetes object name, such as a Job's
+///
+/// That is a DNS-1123 subdomain: at most 253 characters of dot-separated
+/// labels, each lowercase alphanumeric with inner dashes.
+pub fn is_dns_subdomain(name: &str) -> bool {
+    name.len() <= 253
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= 63
+                && label
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_image_reference() {
+        for image in [
+            "node",
+            "node:18-alpine",
+            "mcr.microsoft.com/playwright:v1.40.0-jammy",
+            "localhost:5000/team/app:latest",
+            "ghcr.io/org/my_app.v2",
+            "alpine@sha256:c5b1261d6d3e43071626931fc004f70149baeba2c8ec672bd4f27761f8e1ad6b",
+        ] {
+            assert!(is_image_reference(image), "{image}");
+        }
+        for image in [
+            "",
+            "Node",
+            "node:",
+            "node:-x",
+            "team//app",
+            "-app",
+            "app with spaces",
+            "node@sha256:xyz",
+            "registry.example.com:port/app",
+        ] {
+            assert!(!is_image_reference(image), "{image}");
+        }
+    }
+
+    #[test]
+    fn test_is_dns_subdomain() {
+        assert!(is_dns_subdomain("test-run-smoke-2"));
+        assert!(is_dns_subdomain("test-run-nightly.smoke-1"));
+        assert!(!is_dns_subdomain(""));
+        assert!(!is_dns_subdomain("Test-Run"));
+        assert!(!is_dns_subdomain("-smoke"));
+        assert!(!is_dns_subdomain("nightly..smoke"));
+        assert!(!is_dns_subdomain("nightly.-smoke"));
+        assert!(!is_dns_subdomain(".smoke"));
+        assert!(!is_dns_subdomain(&"a".repeat(64)));
+        assert!(is_dns_subdomain(&vec!["a".repeat(63); 3].join(".")));
+        assert!(!is_dns_subdomain(&vec!["a".repeat(63); 4].join(".")));
+    }
+
+    #[test]
+    fn test_validator() {
+        let mut validator = Validator::new();
+        validator.name("name", " ");
+        validator.image("image", "node:18");
+        validator.commands("commands", &[]);
+        validator.labels("labels", &["smoke".to_string(), "has space".to_string()]);
+        validator.execution_mode("executionMode", "random");
+
+        let error = validator.finish().unwrap_err();
+        assert_eq!(error.status, StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(error.code, "validation_failed");
+        let fields = &error.details.unwrap()["fields"];
+        assert_eq!(fields["name"], "Must not be empty");
+        assert!(fields["image"].is_null());
+        assert_eq!(fields["commands"], "Must have at least one command");
+        assert!(fields["labels"].as_str().unwrap().contains("has space"));
+        assert_eq!(
+            fields["executionMode"],
+            "Must be one of sequential, parallel"
+        );
+
+        assert!(Validator::new().finish().is_ok());
+    }
+}
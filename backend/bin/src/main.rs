@@ -1,4 +1,4 @@
-use sparktest_api::create_app;
+use sparktest_api::{create_app, run_scheduler, AuthConfig};
 use sqlx::postgres::PgPoolOptions;
 use std::net::SocketAddr;
 use tokio::net::TcpListener;
@@ -38,7 +38,17 @@ async fn main() -> anyhow::Result<()> {
         .await
         .expect("Failed to run PostgreSQL migrations");
 
-    let app = create_app(pool);
+    // Fire scheduled test runs in the background
+    tokio::spawn(run_scheduler(pool.clone()));
+
+    let auth = AuthConfig::from_env();
+    if !auth.controller_auth_enabled() {
+        tracing::warn!(
+            "Neither CONTROLLER_TOKEN nor CONTROLLER_SERVICE_ACCOUNT is set, CRD-origin runs will be rejected"
+        );
+    }
+
+    let app = create_app(pool, auth);
 
     // Get port from environment
     let port = std::env::var("PORT")
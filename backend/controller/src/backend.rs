@@ -0,0 +1,88 @@
+use reqwest::RequestBuilder;
+use std::path::PathBuf;
+
+/// HTTP client for the SparkTest backend API, authenticated as the controller
+///
+/// The bearer token is read from `token_file` on every request, so rotated
+/// Secrets and projected service account tokens are picked up without a restart.
+#[derive(Clone)]
+pub struct BackendClient {
+    pub url: String,
+    token_file: Option<PathBuf>,
+    http: reqwest::Client,
+}
+
+impl BackendClient {
+    pub fn new(url: String, token_file: Option<PathBuf>) -> Self {
+        Self {
+            url,
+            token_file,
+            http: reqwest::Client::new(),
+        }
+    }
+
+    /// Client for `url` using the token file in `SPARKTEST_BACKEND_TOKEN_FILE`, if set
+    pub fn from_env(url: String) -> Self {
+        let token_file = std::env::var("SPARKTEST_BACKEND_TOKEN_FILE")
+            .ok()
+            .filter(|path| !path.is_empty())
+            .map(PathBuf::from);
+        Self::new(url, token_file)
+    }
+
+    pub fn get(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.http.get(format!("{}{path}", self.url)))
+    }
+
+    pub fn post(&self, path: &str) -> RequestBuilder {
+        self.authorize(self.http.post(format!("{}{path}", self.url)))
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match self.token() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Current token, or None when unconfigured or unreadable
+    fn token(&self) -> Option<String> {
+        let path = self.token_file.as_ref()?;
+        match std::fs::read_to_string(path) {
+            Ok(token) => Some(token.trim().to_string()).filter(|t| !t.is_empty()),
+            Err(e) => {
+                tracing::warn!(
+                    "Failed to read backend token from {}: {}",
+                    path.display(),
+                    e
+                );
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_bearer_token_from_file() {
+        let path = std::env::temp_dir().join(format!("sparktest-token-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, "secret-token\n").unwrap();
+
+        let backend = BackendClient::new("http://backend/api".to_string(), Some(path.clone()));
+        let request = backend.get("/test-suites/1").build().unwrap();
+        assert_eq!(request.url().as_str(), "http://backend/api/test-suites/1");
+        assert_eq!(
+            request.headers()[reqwest::header::AUTHORIZATION],
+            "Bearer secret-token"
+        );
+
+        std::fs::remove_file(&path).unwrap();
+        let request = backend.get("/test-suites/1").build().unwrap();
+        assert!(!request
+            .headers()
+            .contains_key(reqwest::header::AUTHORIZATION));
+    }
+}
@@ -1,6 +1,7 @@
 use kube::CustomResource;
 use schemars::JsonSchema;
 use serde::{Deserialize, Serialize};
+use sparktest_core::ConcurrencyPolicy;
 use std::collections::BTreeMap;
 
 /// TestRun is a custom resource that represents a test run in SparkTest
@@ -12,24 +13,137 @@ use std::collections::BTreeMap;
     plural = "testruns",
     shortname = "strun",
     status = "TestRunStatus",
-    namespaced
+    namespaced,
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase","description":"Current phase of the test run"}"#,
+    printcolumn = r#"{"name":"Definition","type":"string","jsonPath":".spec.definitionId","description":"Test definition ID"}"#,
+    printcolumn = r#"{"name":"Image","type":"string","jsonPath":".spec.image","description":"Inline test image","priority":1}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
 )]
 #[serde(rename_all = "camelCase")]
 pub struct TestRunSpec {
-    /// UUID of the test definition to run
-    pub definition_id: String,
+    /// UUID of the test definition to run (mutually exclusive with `image`/`commands`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition_id: Option<String>,
+
+    /// Container image for an inline test run without a backend definition
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub image: Option<String>,
+
+    /// Commands for an inline test run, joined with `&&`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(length(min = 1))]
+    pub commands: Vec<String>,
 
     /// Environment variables to inject into the test run
     #[serde(default)]
     pub env: BTreeMap<String, String>,
 
+    /// Compute resources for the test container
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub resources: Option<TestRunResources>,
+
     /// Maximum duration in seconds before timing out the test
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1))]
     pub timeout_seconds: Option<i32>,
 
     /// Seconds to keep the Job after it finishes before cleanup
     #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0))]
     pub ttl_seconds_after_finished: Option<i32>,
+
+    /// Arbitrary value; changing it re-runs the test as a new attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub run_id: Option<String>,
+}
+
+/// Compute resource requests and limits, as Kubernetes quantity strings
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRunResources {
+    /// Minimum resources required, e.g. `cpu: 100m`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub requests: BTreeMap<String, String>,
+
+    /// Maximum resources allowed, e.g. `memory: 512Mi`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub limits: BTreeMap<String, String>,
+}
+
+/// Where a TestRun gets its image and commands from
+#[derive(Debug, Clone, PartialEq)]
+pub enum TestSource {
+    /// Fetch image and commands from a backend test definition
+    Definition(String),
+    /// Use the image and commands declared in the spec
+    Inline {
+        image: String,
+        commands: Vec<String>,
+    },
+}
+
+impl TestRunSpec {
+    /// Resolve the test source, enforcing that `definitionId` and the inline
+    /// `image`/`commands` are mutually exclusive
+    pub fn source(&self) -> Result<TestSource, String> {
+        let has_inline = self.image.is_some() || !self.commands.is_empty();
+
+        match (&self.definition_id, has_inline) {
+            (Some(_), true) => {
+                Err("definitionId cannot be combined with image/commands".to_string())
+            }
+            (Some(id), false) => Ok(TestSource::Definition(id.clone())),
+            (None, true) => match &self.image {
+                Some(image) if !self.commands.is_empty() => Ok(TestSource::Inline {
+                    image: image.clone(),
+                    commands: self.commands.clone(),
+                }),
+                Some(_) => Err("inline test runs require commands".to_string()),
+                None => Err("inline test runs require an image".to_string()),
+            },
+            (None, false) => {
+                Err("either definitionId or image and commands must be set".to_string())
+            }
+        }
+    }
+
+    /// Check the whole spec, reporting every problem found
+    pub fn validate(&self) -> Result<(), String> {
+        let mut problems = Vec::new();
+
+        if let Err(message) = self.source() {
+            problems.push(message);
+        }
+        if let Some(id) = &self.definition_id {
+            if uuid::Uuid::parse_str(id).is_err() {
+                problems.push(format!("definitionId {id:?} is not a UUID"));
+            }
+        }
+        if self.timeout_seconds.is_some_and(|s| s <= 0) {
+            problems.push("timeoutSeconds must be positive".to_string());
+        }
+        if self.ttl_seconds_after_finished.is_some_and(|s| s < 0) {
+            problems.push("ttlSecondsAfterFinished cannot be negative".to_string());
+        }
+        for name in self.env.keys().filter(|name| !is_env_name(name)) {
+            problems.push(format!("env name {name:?} is not a valid identifier"));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems.join("; "))
+        }
+    }
+}
+
+/// Whether `name` is a valid environment variable name, like `[A-Za-z_][A-Za-z0-9_]*`
+fn is_env_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
 }
 
 /// Status of the TestRun
@@ -51,6 +165,54 @@ pub struct TestRunStatus {
     /// List of status conditions
     #[serde(default)]
     pub conditions: Vec<TestRunCondition>,
+
+    /// Number of the current attempt, starting at 1
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub attempt: i32,
+
+    /// Name of the Job running the current attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_name: Option<String>,
+
+    /// Generation of the spec the current attempt runs
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_generation: Option<i64>,
+
+    /// Value of the rerun annotation when the current attempt started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub observed_rerun: Option<String>,
+
+    /// Results of previous attempts, oldest first
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<TestRunAttempt>,
+}
+
+/// Result of a previous attempt of a TestRun
+#[derive(Debug, Clone, Deserialize, Serialize, JsonSchema, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TestRunAttempt {
+    /// Number of the attempt
+    pub attempt: i32,
+
+    /// Name of the Job that ran the attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub job_name: Option<String>,
+
+    /// Last phase of the attempt
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub phase: Option<TestRunPhase>,
+
+    /// Timestamp when the attempt started
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+
+    /// Timestamp when the attempt finished
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+fn is_zero(value: &i32) -> bool {
+    *value == 0
 }
 
 /// Phase of the test run
@@ -94,6 +256,11 @@ impl TestRunStatus {
             started_at: None,
             finished_at: None,
             conditions: Vec::new(),
+            attempt: 0,
+            job_name: None,
+            observed_generation: None,
+            observed_rerun: None,
+            history: Vec::new(),
         }
     }
 
@@ -121,3 +288,375 @@ impl Default for TestRunStatus {
         Self::new()
     }
 }
+
+/// TestSuiteRun runs a whole suite of test definitions as child TestRuns
+#[derive(CustomResource, Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "sparktest.dev",
+    version = "v1alpha1",
+    kind = "TestSuiteRun",
+    plural = "testsuiteruns",
+    shortname = "stsrun",
+    status = "TestSuiteRunStatus",
+    namespaced,
+    printcolumn = r#"{"name":"Phase","type":"string","jsonPath":".status.phase","description":"Aggregated phase of the suite run"}"#,
+    printcolumn = r#"{"name":"Succeeded","type":"integer","jsonPath":".status.succeeded"}"#,
+    printcolumn = r#"{"name":"Failed","type":"integer","jsonPath":".status.failed"}"#,
+    printcolumn = r#"{"name":"Total","type":"integer","jsonPath":".status.total"}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSuiteRunSpec {
+    /// UUID of the backend test suite to run (mutually exclusive with `definitionIds`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suite_id: Option<String>,
+
+    /// UUIDs of the test definitions to run, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    #[schemars(length(min = 1))]
+    pub definition_ids: Vec<String>,
+
+    /// How child TestRuns are scheduled; defaults to the suite's mode, or sequential
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub execution_mode: Option<ExecutionMode>,
+
+    /// Environment variables to inject into every child TestRun
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Maximum duration in seconds for each child TestRun
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1))]
+    pub timeout_seconds: Option<i32>,
+
+    /// Seconds to keep each child Job after it finishes before cleanup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0))]
+    pub ttl_seconds_after_finished: Option<i32>,
+}
+
+/// Execution mode of a suite, matching the backend's `executionMode` values
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, JsonSchema, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ExecutionMode {
+    #[default]
+    Sequential,
+    Parallel,
+}
+
+/// Status of the TestSuiteRun, aggregated from its child TestRuns
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TestSuiteRunStatus {
+    /// Aggregated phase of the suite run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub phase: Option<TestRunPhase>,
+
+    /// Execution mode resolved from the spec or the backend suite
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub execution_mode: Option<ExecutionMode>,
+
+    /// Test definitions resolved from the spec or the backend suite
+    #[serde(default)]
+    pub definition_ids: Vec<String>,
+
+    /// Number of child TestRuns the suite expands to
+    #[serde(default)]
+    pub total: i32,
+
+    /// Number of child TestRuns not yet started
+    #[serde(default)]
+    pub pending: i32,
+
+    /// Number of child TestRuns currently running
+    #[serde(default)]
+    pub running: i32,
+
+    /// Number of child TestRuns that succeeded
+    #[serde(default)]
+    pub succeeded: i32,
+
+    /// Number of child TestRuns that failed or timed out
+    #[serde(default)]
+    pub failed: i32,
+
+    /// Timestamp when the suite run started
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<String>,
+
+    /// Timestamp when the last child TestRun finished
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub finished_at: Option<String>,
+}
+
+impl TestSuiteRunSpec {
+    /// Validate that exactly one of `suiteId` and `definitionIds` is set
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.suite_id, self.definition_ids.is_empty()) {
+            (Some(_), false) => Err("suiteId cannot be combined with definitionIds".to_string()),
+            (None, true) => Err("either suiteId or definitionIds must be set".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// TestSchedule creates TestRuns or TestSuiteRuns on a cron schedule
+#[derive(CustomResource, Debug, Clone, Deserialize, Serialize, JsonSchema)]
+#[kube(
+    group = "sparktest.dev",
+    version = "v1alpha1",
+    kind = "TestSchedule",
+    plural = "testschedules",
+    shortname = "stsched",
+    status = "TestScheduleStatus",
+    namespaced,
+    printcolumn = r#"{"name":"Schedule","type":"string","jsonPath":".spec.schedule"}"#,
+    printcolumn = r#"{"name":"Suspend","type":"boolean","jsonPath":".spec.suspend"}"#,
+    printcolumn = r#"{"name":"Last Schedule","type":"date","jsonPath":".status.lastScheduleTime"}"#,
+    printcolumn = r#"{"name":"Next Schedule","type":"string","jsonPath":".status.nextScheduleTime","priority":1}"#,
+    printcolumn = r#"{"name":"Age","type":"date","jsonPath":".metadata.creationTimestamp"}"#
+)]
+#[serde(rename_all = "camelCase")]
+pub struct TestScheduleSpec {
+    /// Cron expression, e.g. `0 2 * * *`
+    pub schedule: String,
+
+    /// IANA timezone the schedule is evaluated in; defaults to UTC
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub time_zone: Option<String>,
+
+    /// UUID of the test definition to run (mutually exclusive with `suiteId`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub definition_id: Option<String>,
+
+    /// UUID of the backend test suite to run as a TestSuiteRun
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub suite_id: Option<String>,
+
+    /// Environment variables to inject into every scheduled run
+    #[serde(default)]
+    pub env: BTreeMap<String, String>,
+
+    /// Maximum duration in seconds for each scheduled TestRun
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 1))]
+    pub timeout_seconds: Option<i32>,
+
+    /// Seconds to keep each Job after it finishes before cleanup
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[schemars(range(min = 0))]
+    pub ttl_seconds_after_finished: Option<i32>,
+
+    /// What to do when the schedule fires while a previous run is still active
+    #[serde(default)]
+    pub concurrency_policy: ConcurrencyPolicy,
+
+    /// Number of succeeded runs to keep
+    #[serde(default = "default_successful_runs_history_limit")]
+    #[schemars(range(min = 0))]
+    pub successful_runs_history_limit: i32,
+
+    /// Number of failed runs to keep
+    #[serde(default = "default_failed_runs_history_limit")]
+    #[schemars(range(min = 0))]
+    pub failed_runs_history_limit: i32,
+
+    /// Stop creating new runs while true
+    #[serde(default)]
+    pub suspend: bool,
+}
+
+fn default_successful_runs_history_limit() -> i32 {
+    3
+}
+
+fn default_failed_runs_history_limit() -> i32 {
+    1
+}
+
+/// Status of the TestSchedule
+#[derive(Debug, Clone, Default, Deserialize, Serialize, JsonSchema)]
+#[serde(rename_all = "camelCase")]
+pub struct TestScheduleStatus {
+    /// Scheduled time of the most recent run
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_schedule_time: Option<String>,
+
+    /// Next time the schedule fires
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_schedule_time: Option<String>,
+
+    /// Names of the runs that have not finished yet
+    #[serde(default)]
+    pub active: Vec<String>,
+}
+
+impl TestScheduleSpec {
+    /// Validate that exactly one of `definitionId` and `suiteId` is set
+    pub fn validate(&self) -> Result<(), String> {
+        match (&self.definition_id, &self.suite_id) {
+            (Some(_), Some(_)) => Err("definitionId cannot be combined with suiteId".to_string()),
+            (None, None) => Err("either definitionId or suiteId must be set".to_string()),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec() -> TestRunSpec {
+        TestRunSpec {
+            definition_id: None,
+            image: None,
+            commands: Vec::new(),
+            env: BTreeMap::new(),
+            resources: None,
+            timeout_seconds: None,
+            ttl_seconds_after_finished: None,
+            run_id: None,
+        }
+    }
+
+    #[test]
+    fn test_source_from_definition() {
+        let spec = TestRunSpec {
+            definition_id: Some("b7e6c1e2-1a2b-4c3d-8e9f-100000000001".to_string()),
+            ..spec()
+        };
+        assert_eq!(
+            spec.source(),
+            Ok(TestSource::Definition(
+                "b7e6c1e2-1a2b-4c3d-8e9f-100000000001".to_string()
+            ))
+        );
+    }
+
+    #[test]
+    fn test_source_inline() {
+        let spec = TestRunSpec {
+            image: Some("node:18-alpine".to_string()),
+            commands: vec!["npm test".to_string()],
+            ..spec()
+        };
+        assert_eq!(
+            spec.source(),
+            Ok(TestSource::Inline {
+                image: "node:18-alpine".to_string(),
+                commands: vec!["npm test".to_string()],
+            })
+        );
+    }
+
+    #[test]
+    fn test_source_rejects_definition_with_inline_fields() {
+        let spec = TestRunSpec {
+            definition_id: Some("b7e6c1e2-1a2b-4c3d-8e9f-100000000001".to_string()),
+            image: Some("node:18-alpine".to_string()),
+            ..spec()
+        };
+        assert!(spec.source().is_err());
+    }
+
+    #[test]
+    fn test_source_rejects_incomplete_inline() {
+        let missing_commands = TestRunSpec {
+            image: Some("node:18-alpine".to_string()),
+            ..spec()
+        };
+        assert!(missing_commands.source().is_err());
+
+        let missing_image = TestRunSpec {
+            commands: vec!["npm test".to_string()],
+            ..spec()
+        };
+        assert!(missing_image.source().is_err());
+
+        assert!(spec().source().is_err());
+    }
+
+    #[test]
+    fn test_validate() {
+        let valid = TestRunSpec {
+            definition_id: Some("b7e6c1e2-1a2b-4c3d-8e9f-100000000001".to_string()),
+            env: BTreeMap::from([("API_URL".to_string(), "http://api".to_string())]),
+            timeout_seconds: Some(300),
+            ttl_seconds_after_finished: Some(0),
+            ..spec()
+        };
+        assert_eq!(valid.validate(), Ok(()));
+
+        let invalid = TestRunSpec {
+            definition_id: Some("not-a-uuid".to_string()),
+            env: BTreeMap::from([
+                ("1BAD".to_string(), String::new()),
+                ("WITH-DASH".to_string(), String::new()),
+            ]),
+            timeout_seconds: Some(-1),
+            ttl_seconds_after_finished: Some(-5),
+            ..spec()
+        };
+        let message = invalid.validate().unwrap_err();
+        assert!(message.contains("not a UUID"));
+        assert!(message.contains("timeoutSeconds"));
+        assert!(message.contains("ttlSecondsAfterFinished"));
+        assert!(message.contains("\"1BAD\""));
+        assert!(message.contains("\"WITH-DASH\""));
+    }
+
+    #[test]
+    fn test_inline_spec_deserialization() {
+        let json = r#"{
+            "image": "python:3.11-slim",
+            "commands": ["pytest"],
+            "resources": {"limits": {"memory": "512Mi"}}
+        }"#;
+        let spec: TestRunSpec = serde_json::from_str(json).unwrap();
+        assert!(spec.definition_id.is_none());
+        assert_eq!(spec.image.as_deref(), Some("python:3.11-slim"));
+        assert_eq!(spec.resources.unwrap().limits["memory"], "512Mi");
+    }
+
+    #[test]
+    fn test_suite_run_spec_validation() {
+        let spec = |suite_id: Option<&str>, definition_ids: Vec<&str>| TestSuiteRunSpec {
+            suite_id: suite_id.map(String::from),
+            definition_ids: definition_ids.into_iter().map(String::from).collect(),
+            execution_mode: None,
+            env: BTreeMap::new(),
+            timeout_seconds: None,
+            ttl_seconds_after_finished: None,
+        };
+
+        assert!(spec(Some("suite"), vec![]).validate().is_ok());
+        assert!(spec(None, vec!["a", "b"]).validate().is_ok());
+        assert!(spec(Some("suite"), vec!["a"]).validate().is_err());
+        assert!(spec(None, vec![]).validate().is_err());
+    }
+
+    #[test]
+    fn test_execution_mode_matches_backend_values() {
+        let mode: ExecutionMode = serde_json::from_str("\"parallel\"").unwrap();
+        assert_eq!(mode, ExecutionMode::Parallel);
+        assert_eq!(
+            serde_json::to_string(&ExecutionMode::Sequential).unwrap(),
+            "\"sequential\""
+        );
+    }
+
+    #[test]
+    fn test_schedule_spec_defaults() {
+        let json = r#"{"schedule": "0 2 * * *", "definitionId": "abc"}"#;
+        let spec: TestScheduleSpec = serde_json::from_str(json).unwrap();
+        assert_eq!(spec.concurrency_policy, ConcurrencyPolicy::Allow);
+        assert_eq!(spec.successful_runs_history_limit, 3);
+        assert_eq!(spec.failed_runs_history_limit, 1);
+        assert!(!spec.suspend);
+        assert!(spec.validate().is_ok());
+
+        let json = r#"{"schedule": "0 2 * * *", "definitionId": "abc", "suiteId": "def"}"#;
+        let spec: TestScheduleSpec = serde_json::from_str(json).unwrap();
+        assert!(spec.validate().is_err());
+    }
+}
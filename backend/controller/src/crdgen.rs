@@ -0,0 +1,132 @@
+use crate::crd::{TestRun, TestSchedule, TestSuiteRun};
+use anyhow::{Context, Result};
+use k8s_openapi::apiextensions_apiserver::pkg::apis::apiextensions::v1::{
+    CustomResourceDefinition, JSONSchemaProps,
+};
+use kube::{
+    api::{Api, Patch, PatchParams},
+    runtime::wait::{await_condition, conditions},
+    Client, CustomResourceExt,
+};
+use serde_json::json;
+use std::time::Duration;
+
+/// Field manager used when server-side applying the CRDs
+const FIELD_MANAGER: &str = "sparktest-controller";
+
+/// All SparkTest CRDs, generated from the Rust definitions
+///
+/// Schemars cannot express mutually exclusive fields, so the `oneOf`
+/// constraints that `validate()` enforces are added to the spec schemas here.
+pub fn crds() -> Vec<CustomResourceDefinition> {
+    vec![
+        with_one_of(
+            TestRun::crd(),
+            json!([
+                {
+                    "required": ["definitionId"],
+                    "not": {"anyOf": [{"required": ["image"]}, {"required": ["commands"]}]}
+                },
+                {"required": ["image", "commands"], "not": {"required": ["definitionId"]}}
+            ]),
+        ),
+        with_one_of(
+            TestSuiteRun::crd(),
+            json!([
+                {"required": ["suiteId"], "not": {"required": ["definitionIds"]}},
+                {"required": ["definitionIds"], "not": {"required": ["suiteId"]}}
+            ]),
+        ),
+        with_one_of(
+            TestSchedule::crd(),
+            json!([
+                {"required": ["definitionId"], "not": {"required": ["suiteId"]}},
+                {"required": ["suiteId"], "not": {"required": ["definitionId"]}}
+            ]),
+        ),
+    ]
+}
+
+fn with_one_of(
+    mut crd: CustomResourceDefinition,
+    one_of: serde_json::Value,
+) -> CustomResourceDefinition {
+    let one_of: Vec<JSONSchemaProps> =
+        serde_json::from_value(one_of).expect("oneOf constraints are valid schemas");
+
+    for version in &mut crd.spec.versions {
+        let spec = version
+            .schema
+            .as_mut()
+            .and_then(|s| s.open_api_v3_schema.as_mut())
+            .and_then(|s| s.properties.as_mut())
+            .and_then(|p| p.get_mut("spec"));
+        if let Some(spec) = spec {
+            spec.one_of = Some(one_of.clone());
+        }
+    }
+    crd
+}
+
+/// The CRDs as a multi-document YAML stream, optionally only the one whose
+/// singular name (`testrun`, `testsuiterun` or `testschedule`) is `only`
+pub fn to_yaml(only: Option<&str>) -> Result<String> {
+    let documents = crds()
+        .iter()
+        .filter(|crd| only.is_none_or(|name| crd.spec.names.singular.as_deref() == Some(name)))
+        .map(serde_yaml::to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    if documents.is_empty() {
+        anyhow::bail!("Unknown CRD {}", only.unwrap_or_default());
+    }
+    Ok(documents.join("---\n"))
+}
+
+/// Server-side apply the CRDs and wait until they are established
+pub async fn install(client: Client) -> Result<()> {
+    let api: Api<CustomResourceDefinition> = Api::all(client);
+    let params = PatchParams::apply(FIELD_MANAGER).force();
+
+    for crd in crds() {
+        let name = crd.metadata.name.clone().unwrap_or_default();
+        api.patch(&name, &params, &Patch::Apply(&crd))
+            .await
+            .with_context(|| format!("Failed to apply CRD {name}"))?;
+
+        tokio::time::timeout(
+            Duration::from_secs(30),
+            await_condition(api.clone(), &name, conditions::is_crd_established()),
+        )
+        .await
+        .with_context(|| format!("Timed out waiting for CRD {name} to be established"))??;
+        println!("customresourcedefinition.apiextensions.k8s.io/{name} applied");
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Fails when `k8s/crd` drifts from the Rust definitions; regenerate a file with
+    /// `cargo run -p sparktest-controller -- crd print testrun > k8s/crd/testrun.yaml`
+    #[test]
+    fn test_checked_in_crds_match_generated() {
+        let checked_in = [
+            include_str!("../../../k8s/crd/testrun.yaml"),
+            include_str!("../../../k8s/crd/testsuiterun.yaml"),
+            include_str!("../../../k8s/crd/testschedule.yaml"),
+        ];
+
+        for (yaml, crd) in checked_in.iter().zip(crds()) {
+            let name = crd.metadata.name.clone().unwrap();
+            let checked_in: serde_json::Value = serde_yaml::from_str(yaml).unwrap();
+            assert_eq!(
+                checked_in,
+                serde_json::to_value(&crd).unwrap(),
+                "k8s/crd YAML for {name} is out of date, regenerate it with `sparktest-controller crd print`"
+            );
+        }
+    }
+}
@@ -0,0 +1,137 @@
+use crate::crd::TestRunPhase;
+use kube::{
+    client::Client,
+    runtime::events::{Event, EventType, Recorder, Reporter},
+    Resource, ResourceExt,
+};
+use reqwest::StatusCode;
+
+/// Name the controller reports itself as on published Events
+pub const CONTROLLER_NAME: &str = "sparktest-controller";
+
+pub const REASON_JOB_CREATED: &str = "JobCreated";
+pub const REASON_DEFINITION_NOT_FOUND: &str = "DefinitionNotFound";
+pub const REASON_BACKEND_ERROR: &str = "BackendError";
+pub const REASON_SUITE_NOT_FOUND: &str = "SuiteNotFound";
+pub const REASON_TEST_RUN_CREATED: &str = "TestRunCreated";
+pub const REASON_BACKEND_REGISTRATION_FAILED: &str = "BackendRegistrationFailed";
+pub const REASON_INVALID_SPEC: &str = "InvalidSpec";
+pub const REASON_RUN_SCHEDULED: &str = "RunScheduled";
+pub const REASON_RUN_SKIPPED: &str = "RunSkipped";
+pub const REASON_RUN_REPLACED: &str = "RunReplaced";
+pub const REASON_RERUN: &str = "Rerun";
+pub const REASON_SUCCEEDED: &str = "Succeeded";
+pub const REASON_FAILED: &str = "Failed";
+pub const REASON_TIMED_OUT: &str = "TimedOut";
+
+/// Build the reporter identity for this controller instance
+///
+/// The instance is taken from `POD_NAME` when set (via the downward API),
+/// so Events from different replicas can be told apart.
+pub fn reporter() -> Reporter {
+    Reporter {
+        controller: CONTROLLER_NAME.to_string(),
+        instance: std::env::var("POD_NAME").ok(),
+    }
+}
+
+/// Event type and reason to publish when a TestRun enters a terminal phase
+pub fn terminal_phase_event(phase: &TestRunPhase) -> Option<(EventType, &'static str)> {
+    match phase {
+        TestRunPhase::Succeeded => Some((EventType::Normal, REASON_SUCCEEDED)),
+        TestRunPhase::Failed => Some((EventType::Warning, REASON_FAILED)),
+        TestRunPhase::TimedOut => Some((EventType::Warning, REASON_TIMED_OUT)),
+        TestRunPhase::Pending | TestRunPhase::Running => None,
+    }
+}
+
+/// Reason to publish when the backend answers a lookup with `status`
+///
+/// Only a 404 means the entity is missing; anything else, such as a rejected
+/// token, rate limiting or a server error, is reported as a backend error.
+pub fn lookup_failure_reason(status: StatusCode, not_found: &'static str) -> &'static str {
+    if status == StatusCode::NOT_FOUND {
+        not_found
+    } else {
+        REASON_BACKEND_ERROR
+    }
+}
+
+/// Publish an Event on a SparkTest custom resource
+///
+/// Failures are logged and swallowed: Events are diagnostic only and must
+/// never block reconciliation.
+pub async fn publish<K>(
+    client: &Client,
+    reporter: &Reporter,
+    resource: &K,
+    type_: EventType,
+    reason: &str,
+    note: String,
+) where
+    K: Resource<DynamicType = ()>,
+{
+    let recorder = Recorder::new(client.clone(), reporter.clone(), resource.object_ref(&()));
+
+    let event = Event {
+        type_,
+        reason: reason.to_string(),
+        note: Some(note),
+        action: "Reconcile".to_string(),
+        secondary: None,
+    };
+
+    if let Err(e) = recorder.publish(event).await {
+        tracing::warn!(
+            "Failed to publish {} event for {} {}: {}",
+            reason,
+            K::kind(&()),
+            resource.name_any(),
+            e
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_terminal_phase_event() {
+        assert_eq!(
+            terminal_phase_event(&TestRunPhase::Succeeded),
+            Some((EventType::Normal, REASON_SUCCEEDED))
+        );
+        assert_eq!(
+            terminal_phase_event(&TestRunPhase::Failed),
+            Some((EventType::Warning, REASON_FAILED))
+        );
+        assert_eq!(
+            terminal_phase_event(&TestRunPhase::TimedOut),
+            Some((EventType::Warning, REASON_TIMED_OUT))
+        );
+        assert_eq!(terminal_phase_event(&TestRunPhase::Pending), None);
+        assert_eq!(terminal_phase_event(&TestRunPhase::Running), None);
+    }
+
+    #[test]
+    fn test_lookup_failure_reason() {
+        assert_eq!(
+            lookup_failure_reason(StatusCode::NOT_FOUND, REASON_DEFINITION_NOT_FOUND),
+            REASON_DEFINITION_NOT_FOUND
+        );
+        for status in [
+            StatusCode::UNAUTHORIZED,
+            StatusCode::FORBIDDEN,
+            StatusCode::TOO_MANY_REQUESTS,
+            StatusCode::INTERNAL_SERVER_ERROR,
+            StatusCode::SERVICE_UNAVAILABLE,
+        ] {
+            assert_eq!(
+                lookup_failure_reason(status, REASON_DEFINITION_NOT_FOUND),
+                REASON_BACKEND_ERROR,
+                "{status}"
+            );
+        }
+    }
+}
@@ -0,0 +1,231 @@
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use k8s_openapi::api::coordination::v1::{Lease, LeaseSpec};
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::MicroTime;
+use kube::{
+    api::{Api, ObjectMeta, PostParams},
+    Client,
+};
+use serde::Serialize;
+use std::sync::RwLock;
+use std::time::{Duration, Instant};
+
+/// Name of the Lease the controller replicas compete for
+pub const LEASE_NAME: &str = "sparktest-controller";
+
+/// How long a lease is valid without being renewed
+const LEASE_DURATION: Duration = Duration::from_secs(15);
+
+/// How long the leader keeps reconciling while renewals are failing
+const RENEW_DEADLINE: Duration = Duration::from_secs(10);
+
+/// How often the lease is renewed, or acquisition retried
+const RETRY_PERIOD: Duration = Duration::from_secs(2);
+
+/// Leader election state as reported by the health endpoint
+#[derive(Debug, Clone, Default, Serialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct LeaderStatus {
+    /// Identity of this replica
+    pub identity: String,
+    /// Whether this replica currently holds the lease
+    pub is_leader: bool,
+    /// Identity of the current lease holder, if known
+    pub leader: Option<String>,
+}
+
+/// Lease-based leader election so only one replica reconciles at a time
+pub struct LeaderElector {
+    leases: Api<Lease>,
+    identity: String,
+    status: RwLock<LeaderStatus>,
+}
+
+impl LeaderElector {
+    pub fn new(client: Client, namespace: &str, identity: String) -> Self {
+        Self {
+            leases: Api::namespaced(client, namespace),
+            status: RwLock::new(LeaderStatus {
+                identity: identity.clone(),
+                ..Default::default()
+            }),
+            identity,
+        }
+    }
+
+    pub fn identity(&self) -> &str {
+        &self.identity
+    }
+
+    pub fn status(&self) -> LeaderStatus {
+        self.status.read().unwrap().clone()
+    }
+
+    /// Wait until this replica holds the lease
+    pub async fn acquire(&self) {
+        loop {
+            match self.try_acquire_or_renew().await {
+                Ok(true) => return,
+                Ok(false) => {}
+                Err(e) => tracing::warn!("Failed to acquire lease {}: {}", LEASE_NAME, e),
+            }
+            tokio::time::sleep(RETRY_PERIOD).await;
+        }
+    }
+
+    /// Keep renewing the lease, returning once leadership is lost
+    pub async fn renew_until_lost(&self) {
+        let mut last_renewed = Instant::now();
+
+        loop {
+            tokio::time::sleep(RETRY_PERIOD).await;
+
+            match self.try_acquire_or_renew().await {
+                Ok(true) => last_renewed = Instant::now(),
+                Ok(false) => return,
+                Err(e) => {
+                    tracing::warn!("Failed to renew lease {}: {}", LEASE_NAME, e);
+                    if last_renewed.elapsed() > RENEW_DEADLINE {
+                        self.set_status(false, None);
+                        return;
+                    }
+                }
+            }
+        }
+    }
+
+    /// Give up the lease so a standby replica can take over immediately
+    pub async fn release(&self) -> Result<(), kube::Error> {
+        let Some(mut lease) = self.leases.get_opt(LEASE_NAME).await? else {
+            return Ok(());
+        };
+        let mut spec = lease.spec.take().unwrap_or_default();
+        if spec.holder_identity.as_deref() != Some(self.identity.as_str()) {
+            return Ok(());
+        }
+
+        let now = MicroTime(Utc::now());
+        spec.holder_identity = None;
+        spec.lease_duration_seconds = Some(1);
+        spec.acquire_time = Some(now.clone());
+        spec.renew_time = Some(now);
+        lease.spec = Some(spec);
+
+        self.leases
+            .replace(LEASE_NAME, &PostParams::default(), &lease)
+            .await?;
+        self.set_status(false, None);
+        tracing::info!("Released lease {}", LEASE_NAME);
+        Ok(())
+    }
+
+    /// Take the lease if it is free or expired, or renew it if already held
+    async fn try_acquire_or_renew(&self) -> Result<bool, kube::Error> {
+        let now = Utc::now();
+
+        let result = match self.leases.get_opt(LEASE_NAME).await? {
+            None => {
+                let lease = Lease {
+                    metadata: ObjectMeta {
+                        name: Some(LEASE_NAME.to_string()),
+                        ..Default::default()
+                    },
+                    spec: Some(self.next_spec(&LeaseSpec::default(), now)),
+                };
+                self.leases.create(&PostParams::default(), &lease).await
+            }
+            Some(mut lease) => {
+                let spec = lease.spec.take().unwrap_or_default();
+                if !can_acquire(&spec, &self.identity, now) {
+                    self.set_status(false, spec.holder_identity);
+                    return Ok(false);
+                }
+
+                // The resource version makes a concurrent takeover fail with a conflict
+                lease.spec = Some(self.next_spec(&spec, now));
+                self.leases
+                    .replace(LEASE_NAME, &PostParams::default(), &lease)
+                    .await
+            }
+        };
+
+        match result {
+            Ok(_) => {
+                if !self.status().is_leader {
+                    tracing::info!("Acquired lease {} as {}", LEASE_NAME, self.identity);
+                }
+                self.set_status(true, Some(self.identity.clone()));
+                Ok(true)
+            }
+            Err(kube::Error::Api(e)) if e.code == 409 => {
+                self.set_status(false, None);
+                Ok(false)
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Lease spec after this replica acquires or renews it
+    fn next_spec(&self, current: &LeaseSpec, now: DateTime<Utc>) -> LeaseSpec {
+        let held = current.holder_identity.as_deref() == Some(self.identity.as_str());
+        let transitions = current.lease_transitions.unwrap_or(0);
+
+        LeaseSpec {
+            holder_identity: Some(self.identity.clone()),
+            lease_duration_seconds: Some(LEASE_DURATION.as_secs() as i32),
+            acquire_time: if held {
+                current.acquire_time.clone()
+            } else {
+                Some(MicroTime(now))
+            },
+            renew_time: Some(MicroTime(now)),
+            lease_transitions: Some(if held { transitions } else { transitions + 1 }),
+        }
+    }
+
+    fn set_status(&self, is_leader: bool, leader: Option<String>) {
+        let mut status = self.status.write().unwrap();
+        status.is_leader = is_leader;
+        status.leader = leader;
+    }
+}
+
+/// Whether `identity` may take or keep a lease at `now`
+///
+/// A lease can be taken when it has no holder, is already held by
+/// `identity`, or has not been renewed within its duration.
+pub fn can_acquire(spec: &LeaseSpec, identity: &str, now: DateTime<Utc>) -> bool {
+    match spec.holder_identity.as_deref() {
+        None | Some("") => true,
+        Some(holder) if holder == identity => true,
+        Some(_) => match (&spec.renew_time, spec.lease_duration_seconds) {
+            (Some(renewed), Some(duration)) => {
+                renewed.0 + ChronoDuration::seconds(i64::from(duration)) < now
+            }
+            _ => true,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn held_by(holder: &str, renewed_secs_ago: i64, now: DateTime<Utc>) -> LeaseSpec {
+        LeaseSpec {
+            holder_identity: Some(holder.to_string()),
+            lease_duration_seconds: Some(15),
+            renew_time: Some(MicroTime(now - ChronoDuration::seconds(renewed_secs_ago))),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_can_acquire() {
+        let now = Utc::now();
+
+        assert!(can_acquire(&LeaseSpec::default(), "a", now));
+        assert!(can_acquire(&held_by("a", 5, now), "a", now));
+        assert!(!can_acquire(&held_by("b", 5, now), "a", now));
+        assert!(can_acquire(&held_by("b", 20, now), "a", now));
+    }
+}
@@ -1,17 +1,30 @@
+pub mod backend;
 pub mod crd;
+pub mod crdgen;
+pub mod events;
+pub mod leader;
+pub mod metrics;
 pub mod reconciler;
+pub mod schedule_reconciler;
+pub mod server;
+pub mod suite_reconciler;
+pub mod watch;
+pub mod webhook;
 
-pub use crd::TestRun;
-pub use reconciler::{build_job, error_policy, reconcile, ReconcileError, ReconcilerContext};
+pub use crd::{TestRun, TestSchedule, TestSuiteRun};
+pub use reconciler::{error_policy, reconcile, ReconcileError, ReconcilerContext};
+pub use schedule_reconciler::{reconcile_schedule, schedule_error_policy};
+pub use suite_reconciler::{reconcile_suite, suite_error_policy};
 
-use futures::StreamExt;
-use kube::{
-    runtime::{controller::Controller, watcher::Config},
-    Client,
-};
+use futures::{future::join_all, FutureExt, StreamExt};
+use kube::{runtime::controller::Controller, Client};
 use std::sync::Arc;
+use watch::WatchScope;
 
-/// Start the TestRun controller
+/// Start the TestRun, TestSuiteRun and TestSchedule controllers
+///
+/// Only the namespaces and labels selected by `WATCH_NAMESPACES` and
+/// `WATCH_LABEL_SELECTOR` are watched, as in the controller binary.
 pub async fn start_controller(backend_url: String) {
     tracing::info!("Starting TestRun controller");
 
@@ -25,22 +38,76 @@ pub async fn start_controller(backend_url: String) {
 
     let context = Arc::new(ReconcilerContext {
         client: client.clone(),
-        backend_url,
+        backend: backend::BackendClient::from_env(backend_url),
+        reporter: events::reporter(),
+        metrics: Arc::new(metrics::Metrics::default()),
     });
 
-    let testruns = kube::Api::<TestRun>::all(client.clone());
-
-    Controller::new(testruns, Config::default())
-        .run(reconcile, error_policy, context)
-        .for_each(|res| async move {
-            match res {
-                Ok((_obj_ref, _action)) => {
-                    tracing::info!("Reconciliation successful");
-                }
-                Err(err) => {
-                    tracing::error!("Reconciliation error: {:?}", err);
-                }
-            }
-        })
-        .await;
+    // One set of controllers per watched namespace, or a single cluster-wide set
+    let scope = WatchScope::from_env();
+    tracing::info!(
+        "Watching TestRuns, TestSuiteRuns and TestSchedules in {}",
+        scope
+    );
+
+    let mut controllers = Vec::new();
+    let apis = scope
+        .apis::<TestRun>(&client)
+        .into_iter()
+        .zip(scope.apis::<TestSuiteRun>(&client))
+        .zip(scope.apis::<TestSchedule>(&client));
+    for ((testruns, suiteruns), schedules) in apis {
+        controllers.push(
+            Controller::new(testruns.clone(), scope.watcher_config())
+                .run(reconcile, error_policy, context.clone())
+                .for_each(|res| async move {
+                    match res {
+                        Ok((_obj_ref, _action)) => {
+                            tracing::info!("Reconciliation successful");
+                        }
+                        Err(err) => {
+                            tracing::error!("Reconciliation error: {:?}", err);
+                        }
+                    }
+                })
+                .boxed(),
+        );
+
+        controllers.push(
+            Controller::new(suiteruns.clone(), scope.watcher_config())
+                .owns(testruns.clone(), scope.watcher_config())
+                .run(reconcile_suite, suite_error_policy, context.clone())
+                .for_each(|res| async move {
+                    match res {
+                        Ok((_obj_ref, _action)) => {
+                            tracing::info!("Suite reconciliation successful");
+                        }
+                        Err(err) => {
+                            tracing::error!("Suite reconciliation error: {:?}", err);
+                        }
+                    }
+                })
+                .boxed(),
+        );
+
+        controllers.push(
+            Controller::new(schedules, scope.watcher_config())
+                .owns(testruns, scope.watcher_config())
+                .owns(suiteruns, scope.watcher_config())
+                .run(reconcile_schedule, schedule_error_policy, context.clone())
+                .for_each(|res| async move {
+                    match res {
+                        Ok((_obj_ref, _action)) => {
+                            tracing::info!("Schedule reconciliation successful");
+                        }
+                        Err(err) => {
+                            tracing::error!("Schedule reconciliation error: {:?}", err);
+                        }
+                    }
+                })
+                .boxed(),
+        );
+    }
+
+    join_all(controllers).await;
 }
@@ -1,13 +1,24 @@
 use anyhow::Result;
-use futures::StreamExt;
+use futures::future::join_all;
+use futures::{FutureExt, StreamExt};
 use kube::runtime::controller::{Action, Controller};
-use kube::runtime::watcher::Config;
 use kube::{Client, ResourceExt};
 use sparktest_controller::{
+    backend::BackendClient,
+    crdgen, events,
+    leader::LeaderElector,
+    metrics::Metrics,
     reconciler::{reconcile, ReconcileError, ReconcilerContext},
-    TestRun,
+    schedule_reconciler::{reconcile_schedule, schedule_error_policy},
+    server::{self, ServerState},
+    suite_reconciler::{reconcile_suite, suite_error_policy},
+    watch::WatchScope,
+    webhook::{self, WebhookState},
+    TestRun, TestSchedule, TestSuiteRun,
 };
+use std::path::PathBuf;
 use std::sync::Arc;
+use tokio::sync::watch;
 use tokio::time::Duration;
 use tracing::{error, info};
 
@@ -21,6 +32,29 @@ async fn main() -> Result<()> {
         )
         .init();
 
+    // `crd print` and `crd install` manage the CRDs instead of running the controller
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    match args
+        .iter()
+        .map(String::as_str)
+        .collect::<Vec<_>>()
+        .as_slice()
+    {
+        [] => {}
+        ["crd", "print"] => {
+            print!("{}", crdgen::to_yaml(None)?);
+            return Ok(());
+        }
+        ["crd", "print", name] => {
+            print!("{}", crdgen::to_yaml(Some(name))?);
+            return Ok(());
+        }
+        ["crd", "install"] => {
+            return crdgen::install(Client::try_default().await?).await;
+        }
+        _ => anyhow::bail!("Usage: sparktest-controller [crd print [NAME] | crd install]"),
+    }
+
     info!("Starting SparkTest Controller");
 
     // Create Kubernetes client
@@ -31,49 +65,222 @@ async fn main() -> Result<()> {
     let backend_url = std::env::var("SPARKTEST_BACKEND_URL")
         .unwrap_or_else(|_| "http://sparktest-backend-service:8080/api".to_string());
     info!("Backend URL: {}", backend_url);
+    let backend = BackendClient::from_env(backend_url);
+
+    // Only the replica holding the lease reconciles
+    let identity = std::env::var("POD_NAME")
+        .unwrap_or_else(|_| format!("{}-{}", events::CONTROLLER_NAME, uuid::Uuid::new_v4()));
+    let lease_namespace =
+        std::env::var("POD_NAMESPACE").unwrap_or_else(|_| "sparktest".to_string());
+    let leader = Arc::new(LeaderElector::new(
+        client.clone(),
+        &lease_namespace,
+        identity,
+    ));
+
+    let health_port = std::env::var("HEALTH_PORT")
+        .unwrap_or_else(|_| "8080".to_string())
+        .parse::<u16>()
+        .expect("HEALTH_PORT must be a valid number");
+    let metrics = Arc::new(Metrics::default());
+    let server_state = ServerState::new(leader.clone(), metrics.clone());
+    tokio::spawn(server::serve(health_port, server_state.clone()));
+
+    // Every replica serves the admission webhook, not just the leader
+    if let Ok(cert_dir) = std::env::var("WEBHOOK_CERT_DIR") {
+        let webhook_port = std::env::var("WEBHOOK_PORT")
+            .unwrap_or_else(|_| "8443".to_string())
+            .parse::<u16>()
+            .expect("WEBHOOK_PORT must be a valid number");
+        let verify_definitions =
+            std::env::var("WEBHOOK_VERIFY_DEFINITIONS").is_ok_and(|v| v == "true");
+        let webhook_state = WebhookState::new(backend.clone(), verify_definitions);
+        tokio::spawn(async move {
+            webhook::serve(webhook_port, &PathBuf::from(cert_dir), webhook_state).await
+        });
+    }
+
+    info!(
+        "Waiting for leadership in {} as {}",
+        lease_namespace,
+        leader.identity()
+    );
+    tokio::select! {
+        _ = leader.acquire() => {}
+        _ = shutdown_signal() => {
+            info!("Shutdown requested before acquiring leadership");
+            return Ok(());
+        }
+    }
+
+    // Controllers drain in-flight reconciles once this is signalled
+    let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
     // Create controller context
     let context = Arc::new(ReconcilerContext {
         client: client.clone(),
-        backend_url,
+        backend,
+        reporter: events::reporter(),
+        metrics: metrics.clone(),
     });
 
-    // Set up the controller
-    Controller::new(kube::Api::<TestRun>::all(client.clone()), Config::default())
-        .run(
-            |testrun, ctx| async move {
-                let name = testrun.name_any();
-                let namespace = testrun.namespace().unwrap_or_else(|| "default".to_string());
+    // One set of controllers per watched namespace, or a single cluster-wide set
+    let scope = WatchScope::from_env();
+    info!(
+        "Watching TestRuns, TestSuiteRuns and TestSchedules in {}",
+        scope
+    );
 
-                info!("Reconciling TestRun {}/{}", namespace, name);
+    let mut controllers = Vec::new();
+    let mut testrun_stores = Vec::new();
+    let apis = scope
+        .apis::<TestRun>(&client)
+        .into_iter()
+        .zip(scope.apis::<TestSuiteRun>(&client))
+        .zip(scope.apis::<TestSchedule>(&client));
+    for ((testruns, suiteruns), schedules) in apis {
+        // The TestRun controller's cache backs readiness and the phase metrics
+        let testrun_controller = Controller::new(testruns.clone(), scope.watcher_config());
+        testrun_stores.push(testrun_controller.store());
 
-                match reconcile(testrun, ctx).await {
-                    Ok(action) => {
-                        info!("Reconciled TestRun {}/{} successfully", namespace, name);
-                        Ok(action)
+        controllers.push(
+            testrun_controller
+                .graceful_shutdown_on(shutdown_requested(shutdown_rx.clone()))
+                .run(reconcile_logged, testrun_error_policy, context.clone())
+                .for_each(|res| async move {
+                    match res {
+                        Ok(o) => info!("Reconciled: {:?}", o),
+                        Err(e) => error!("Reconciliation error: {:?}", e),
                     }
-                    Err(e) => {
-                        error!(
-                            "Failed to reconcile TestRun {}/{}: {:?}",
-                            namespace, name, e
-                        );
-                        Ok(Action::requeue(Duration::from_secs(60)))
+                })
+                .boxed(),
+        );
+
+        // The TestSuiteRun controller is woken up by changes to its child TestRuns
+        controllers.push(
+            Controller::new(suiteruns.clone(), scope.watcher_config())
+                .owns(testruns.clone(), scope.watcher_config())
+                .graceful_shutdown_on(shutdown_requested(shutdown_rx.clone()))
+                .run(reconcile_suite, suite_error_policy, context.clone())
+                .for_each(|res| async move {
+                    match res {
+                        Ok(o) => info!("Reconciled suite run: {:?}", o),
+                        Err(e) => error!("Suite reconciliation error: {:?}", e),
                     }
-                }
-            },
-            |_testrun, error: &ReconcileError, _ctx| {
-                error!("Reconciliation error: {:?}", error);
-                Action::requeue(Duration::from_secs(60))
-            },
-            context,
-        )
-        .for_each(|res| async move {
-            match res {
-                Ok(o) => info!("Reconciled: {:?}", o),
-                Err(e) => error!("Reconciliation error: {:?}", e),
-            }
-        })
-        .await;
+                })
+                .boxed(),
+        );
+
+        // The TestSchedule controller is woken up by changes to the runs it created
+        controllers.push(
+            Controller::new(schedules, scope.watcher_config())
+                .owns(testruns, scope.watcher_config())
+                .owns(suiteruns, scope.watcher_config())
+                .graceful_shutdown_on(shutdown_requested(shutdown_rx.clone()))
+                .run(reconcile_schedule, schedule_error_policy, context.clone())
+                .for_each(|res| async move {
+                    match res {
+                        Ok(o) => info!("Reconciled schedule: {:?}", o),
+                        Err(e) => error!("Schedule reconciliation error: {:?}", e),
+                    }
+                })
+                .boxed(),
+        );
+    }
+
+    metrics.track_testruns(testrun_stores.clone());
+    tokio::spawn(async move {
+        let synced = join_all(testrun_stores.iter().map(|store| store.wait_until_ready())).await;
+        if synced.iter().all(Result::is_ok) {
+            server_state.mark_caches_synced();
+        }
+    });
+
+    let controllers = join_all(controllers);
+    tokio::pin!(controllers);
+
+    let lost_leadership = tokio::select! {
+        _ = &mut controllers => false,
+        _ = shutdown_signal() => false,
+        _ = leader.renew_until_lost() => true,
+    };
+
+    if lost_leadership {
+        // Another replica may already be reconciling, so stop immediately
+        anyhow::bail!("Lost leadership of lease, exiting");
+    }
+
+    // Hand over: finish in-flight reconciles, then free the lease for a standby
+    info!("Shutting down, handing over leadership");
+    let _ = shutdown_tx.send(true);
+    controllers.await;
+    if let Err(e) = leader.release().await {
+        error!("Failed to release lease: {}", e);
+    }
 
     Ok(())
 }
+
+/// Reconcile a TestRun, logging the outcome and requeueing on failure
+async fn reconcile_logged(
+    testrun: Arc<TestRun>,
+    ctx: Arc<ReconcilerContext>,
+) -> Result<Action, ReconcileError> {
+    let name = testrun.name_any();
+    let namespace = testrun.namespace().unwrap_or_else(|| "default".to_string());
+
+    info!("Reconciling TestRun {}/{}", namespace, name);
+
+    match reconcile(testrun, ctx).await {
+        Ok(action) => {
+            info!("Reconciled TestRun {}/{} successfully", namespace, name);
+            Ok(action)
+        }
+        Err(e) => {
+            error!(
+                "Failed to reconcile TestRun {}/{}: {:?}",
+                namespace, name, e
+            );
+            Ok(Action::requeue(Duration::from_secs(60)))
+        }
+    }
+}
+
+fn testrun_error_policy(
+    _testrun: Arc<TestRun>,
+    error: &ReconcileError,
+    _ctx: Arc<ReconcilerContext>,
+) -> Action {
+    error!("Reconciliation error: {:?}", error);
+    Action::requeue(Duration::from_secs(60))
+}
+
+/// Resolve once SIGTERM or Ctrl-C is received
+async fn shutdown_signal() {
+    let ctrl_c = async {
+        tokio::signal::ctrl_c()
+            .await
+            .expect("Failed to install Ctrl-C handler");
+    };
+
+    #[cfg(unix)]
+    let terminate = async {
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+            .expect("Failed to install SIGTERM handler")
+            .recv()
+            .await;
+    };
+
+    #[cfg(not(unix))]
+    let terminate = std::future::pending::<()>();
+
+    tokio::select! {
+        _ = ctrl_c => {}
+        _ = terminate => {}
+    }
+}
+
+/// Resolve once shutdown has been signalled on `rx`
+async fn shutdown_requested(mut rx: watch::Receiver<bool>) {
+    let _ = rx.wait_for(|shutdown| *shutdown).await;
+}
@@ -0,0 +1,290 @@
+use crate::crd::{TestRun, TestRunPhase};
+use crate::reconciler::ReconcileError;
+use kube::runtime::{controller::Action, reflector::Store};
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::future::Future;
+use std::sync::{Mutex, RwLock};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+
+/// Upper bounds in seconds of the reconcile duration histogram buckets
+const DURATION_BUCKETS: [f64; 12] = [
+    0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0,
+];
+
+/// Reconcile metrics of a single controller
+#[derive(Debug, Clone, Default)]
+struct ControllerMetrics {
+    reconciles: u64,
+    errors: BTreeMap<&'static str, u64>,
+    bucket_counts: [u64; DURATION_BUCKETS.len()],
+    duration_sum: f64,
+    last_reconcile: f64,
+}
+
+/// Prometheus metrics collected by the controller
+#[derive(Default)]
+pub struct Metrics {
+    controllers: Mutex<BTreeMap<&'static str, ControllerMetrics>>,
+    testruns: RwLock<Option<Vec<Store<TestRun>>>>,
+}
+
+impl Metrics {
+    /// Run a reconcile, recording its duration and outcome under `controller`
+    pub async fn measure<F>(&self, controller: &'static str, reconcile: F) -> F::Output
+    where
+        F: Future<Output = Result<Action, ReconcileError>>,
+    {
+        let start = Instant::now();
+        let result = reconcile.await;
+        self.record(
+            controller,
+            start.elapsed().as_secs_f64(),
+            result.as_ref().err(),
+        );
+        result
+    }
+
+    fn record(&self, controller: &'static str, seconds: f64, error: Option<&ReconcileError>) {
+        let mut controllers = self.controllers.lock().unwrap();
+        let metrics = controllers.entry(controller).or_default();
+
+        metrics.reconciles += 1;
+        metrics.duration_sum += seconds;
+        for (count, bound) in metrics.bucket_counts.iter_mut().zip(DURATION_BUCKETS) {
+            if seconds <= bound {
+                *count += 1;
+            }
+        }
+        if let Some(error) = error {
+            *metrics.errors.entry(error.kind()).or_default() += 1;
+        }
+        metrics.last_reconcile = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or_default();
+    }
+
+    /// Count TestRuns by phase from the TestRun controllers' caches, one per watched namespace
+    pub fn track_testruns(&self, stores: Vec<Store<TestRun>>) {
+        *self.testruns.write().unwrap() = Some(stores);
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self, is_leader: bool) -> String {
+        let controllers = self.controllers.lock().unwrap().clone();
+        let phases = self.testruns.read().unwrap().as_ref().map(|stores| {
+            let testruns: Vec<_> = stores.iter().flat_map(|store| store.state()).collect();
+            count_phases(&testruns)
+        });
+
+        render(&controllers, phases.as_ref(), is_leader)
+    }
+}
+
+/// Number of TestRuns in each phase, with `Unknown` for runs without status
+fn count_phases(testruns: &[std::sync::Arc<TestRun>]) -> BTreeMap<&'static str, u64> {
+    let mut counts: BTreeMap<&'static str, u64> = [
+        "Pending",
+        "Running",
+        "Succeeded",
+        "Failed",
+        "TimedOut",
+        "Unknown",
+    ]
+    .into_iter()
+    .map(|phase| (phase, 0))
+    .collect();
+
+    for testrun in testruns {
+        let phase = match testrun.status.as_ref().and_then(|s| s.phase.as_ref()) {
+            Some(TestRunPhase::Pending) => "Pending",
+            Some(TestRunPhase::Running) => "Running",
+            Some(TestRunPhase::Succeeded) => "Succeeded",
+            Some(TestRunPhase::Failed) => "Failed",
+            Some(TestRunPhase::TimedOut) => "TimedOut",
+            None => "Unknown",
+        };
+        *counts.entry(phase).or_default() += 1;
+    }
+
+    counts
+}
+
+fn render(
+    controllers: &BTreeMap<&'static str, ControllerMetrics>,
+    phases: Option<&BTreeMap<&'static str, u64>>,
+    is_leader: bool,
+) -> String {
+    let mut out = String::new();
+
+    header(
+        &mut out,
+        "sparktest_controller_is_leader",
+        "gauge",
+        "Whether this replica holds the leader lease",
+    );
+    let _ = writeln!(
+        out,
+        "sparktest_controller_is_leader {}",
+        u8::from(is_leader)
+    );
+
+    header(
+        &mut out,
+        "sparktest_controller_reconciles_total",
+        "counter",
+        "Reconciliations run, by controller",
+    );
+    for (controller, metrics) in controllers {
+        let _ = writeln!(
+            out,
+            "sparktest_controller_reconciles_total{{controller=\"{controller}\"}} {}",
+            metrics.reconciles
+        );
+    }
+
+    header(
+        &mut out,
+        "sparktest_controller_reconcile_errors_total",
+        "counter",
+        "Failed reconciliations, by controller and ReconcileError variant",
+    );
+    for (controller, metrics) in controllers {
+        for (error, count) in &metrics.errors {
+            let _ = writeln!(
+                out,
+                "sparktest_controller_reconcile_errors_total{{controller=\"{controller}\",error=\"{error}\"}} {count}"
+            );
+        }
+    }
+
+    header(
+        &mut out,
+        "sparktest_controller_reconcile_duration_seconds",
+        "histogram",
+        "Duration of reconciliations, by controller",
+    );
+    for (controller, metrics) in controllers {
+        for (bound, count) in DURATION_BUCKETS.iter().zip(metrics.bucket_counts) {
+            let _ = writeln!(
+                out,
+                "sparktest_controller_reconcile_duration_seconds_bucket{{controller=\"{controller}\",le=\"{bound}\"}} {count}"
+            );
+        }
+        let _ = writeln!(
+            out,
+            "sparktest_controller_reconcile_duration_seconds_bucket{{controller=\"{controller}\",le=\"+Inf\"}} {}",
+            metrics.reconciles
+        );
+        let _ = writeln!(
+            out,
+            "sparktest_controller_reconcile_duration_seconds_sum{{controller=\"{controller}\"}} {}",
+            metrics.duration_sum
+        );
+        let _ = writeln!(
+            out,
+            "sparktest_controller_reconcile_duration_seconds_count{{controller=\"{controller}\"}} {}",
+            metrics.reconciles
+        );
+    }
+
+    header(
+        &mut out,
+        "sparktest_controller_last_reconcile_timestamp_seconds",
+        "gauge",
+        "Unix time of the last reconciliation, by controller",
+    );
+    for (controller, metrics) in controllers {
+        let _ = writeln!(
+            out,
+            "sparktest_controller_last_reconcile_timestamp_seconds{{controller=\"{controller}\"}} {}",
+            metrics.last_reconcile
+        );
+    }
+
+    if let Some(phases) = phases {
+        header(
+            &mut out,
+            "sparktest_controller_testruns",
+            "gauge",
+            "TestRuns in the controller cache, by phase",
+        );
+        for (phase, count) in phases {
+            let _ = writeln!(
+                out,
+                "sparktest_controller_testruns{{phase=\"{phase}\"}} {count}"
+            );
+        }
+    }
+
+    out
+}
+
+fn header(out: &mut String, name: &str, kind: &str, help: &str) {
+    let _ = writeln!(out, "# HELP {name} {help}");
+    let _ = writeln!(out, "# TYPE {name} {kind}");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crd::{TestRunSpec, TestRunStatus};
+    use std::sync::Arc;
+
+    #[test]
+    fn test_render_reconcile_metrics() {
+        let metrics = Metrics::default();
+        metrics.record("testrun", 0.02, None);
+        metrics.record(
+            "testrun",
+            3.0,
+            Some(&ReconcileError::InvalidSpec("bad".to_string())),
+        );
+
+        let out = metrics.render(true);
+        assert!(out.contains("sparktest_controller_is_leader 1"));
+        assert!(out.contains("sparktest_controller_reconciles_total{controller=\"testrun\"} 2"));
+        assert!(out.contains(
+            "sparktest_controller_reconcile_errors_total{controller=\"testrun\",error=\"InvalidSpec\"} 1"
+        ));
+        assert!(out.contains(
+            "sparktest_controller_reconcile_duration_seconds_bucket{controller=\"testrun\",le=\"0.025\"} 1"
+        ));
+        assert!(out.contains(
+            "sparktest_controller_reconcile_duration_seconds_bucket{controller=\"testrun\",le=\"5\"} 2"
+        ));
+        assert!(out.contains(
+            "sparktest_controller_reconcile_duration_seconds_count{controller=\"testrun\"} 2"
+        ));
+        // No cache yet, so no phase counts
+        assert!(!out.contains("sparktest_controller_testruns{"));
+    }
+
+    #[test]
+    fn test_count_phases() {
+        let testrun = |phase: Option<TestRunPhase>| {
+            let mut testrun = TestRun::new(
+                "run",
+                serde_json::from_value::<TestRunSpec>(serde_json::json!({"definitionId": "abc"}))
+                    .unwrap(),
+            );
+            testrun.status = phase.map(|phase| TestRunStatus {
+                phase: Some(phase),
+                ..Default::default()
+            });
+            Arc::new(testrun)
+        };
+
+        let counts = count_phases(&[
+            testrun(Some(TestRunPhase::Running)),
+            testrun(Some(TestRunPhase::Running)),
+            testrun(Some(TestRunPhase::Failed)),
+            testrun(None),
+        ]);
+        assert_eq!(counts["Running"], 2);
+        assert_eq!(counts["Failed"], 1);
+        assert_eq!(counts["Unknown"], 1);
+        assert_eq!(counts["Succeeded"], 0);
+    }
+}
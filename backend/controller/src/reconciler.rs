@@ -1,21 +1,31 @@
-use crate::crd::{TestRun, TestRunPhase, TestRunStatus};
-use k8s_openapi::api::batch::v1::{Job, JobSpec};
-use k8s_openapi::api::core::v1::{Container, EnvVar, PodSpec, PodTemplateSpec};
-use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use crate::backend::BackendClient;
+use crate::crd::{TestRun, TestRunAttempt, TestRunPhase, TestRunStatus, TestSource};
+use crate::events;
+use crate::metrics::Metrics;
+use k8s_openapi::api::batch::v1::Job;
 use kube::{
     api::{Api, Patch, PatchParams, PostParams},
     client::Client,
-    runtime::controller::Action,
+    runtime::{
+        controller::Action,
+        events::{EventType, Reporter},
+    },
     ResourceExt,
 };
 use serde_json::json;
-use std::collections::BTreeMap;
+use sparktest_core::{job_name, RunOrigin, RunResources, RunSpec};
 use std::sync::Arc;
 use std::time::Duration;
 use thiserror::Error;
 
 const FINALIZER_NAME: &str = "sparktest.dev/testrun-finalizer";
 
+/// Annotation whose value, when changed, re-runs a TestRun as a new attempt
+pub const RERUN_ANNOTATION: &str = "sparktest.dev/rerun";
+
+/// Number of previous attempts kept in the status history
+const MAX_HISTORY: usize = 10;
+
 #[derive(Error, Debug)]
 pub enum ReconcileError {
     #[error("Kube error: {0}")]
@@ -26,19 +36,50 @@ pub enum ReconcileError {
 
     #[error("Missing field: {0}")]
     MissingField(String),
+
+    #[error("Invalid spec: {0}")]
+    InvalidSpec(String),
+
+    #[error("Backend error: {0}")]
+    BackendError(reqwest::StatusCode),
+}
+
+impl ReconcileError {
+    /// Variant name, used as the `error` label on metrics
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ReconcileError::KubeError(_) => "KubeError",
+            ReconcileError::RequestError(_) => "RequestError",
+            ReconcileError::MissingField(_) => "MissingField",
+            ReconcileError::InvalidSpec(_) => "InvalidSpec",
+            ReconcileError::BackendError(_) => "BackendError",
+        }
+    }
 }
 
 /// Context for the reconciler
 #[derive(Clone)]
 pub struct ReconcilerContext {
     pub client: Client,
-    pub backend_url: String,
+    pub backend: BackendClient,
+    pub reporter: Reporter,
+    pub metrics: Arc<Metrics>,
 }
 
 /// Reconcile a TestRun resource
 pub async fn reconcile(
     testrun: Arc<TestRun>,
     ctx: Arc<ReconcilerContext>,
+) -> Result<Action, ReconcileError> {
+    let metrics = ctx.metrics.clone();
+    metrics
+        .measure("testrun", reconcile_testrun(testrun, ctx))
+        .await
+}
+
+async fn reconcile_testrun(
+    testrun: Arc<TestRun>,
+    ctx: Arc<ReconcilerContext>,
 ) -> Result<Action, ReconcileError> {
     let namespace = testrun.namespace().unwrap_or_else(|| "default".to_string());
     let name = testrun.name_any();
@@ -64,13 +105,17 @@ pub async fn reconcile(
     }
 
     // Get current status or create default
-    let current_phase = testrun.status.as_ref().and_then(|s| s.phase.clone());
+    let status = testrun.status.clone().unwrap_or_default();
+    let current_phase = status.phase.clone();
+
+    // A changed spec or rerun annotation starts a new attempt
+    if current_phase.is_some() && rerun_requested(&testrun) {
+        start_next_attempt(&testrun, &ctx, &testruns).await?;
+        return Ok(Action::requeue(Duration::from_secs(1)));
+    }
 
     // If already finished, don't reconcile
-    if matches!(
-        current_phase,
-        Some(TestRunPhase::Succeeded) | Some(TestRunPhase::Failed) | Some(TestRunPhase::TimedOut)
-    ) {
+    if is_finished(current_phase.as_ref()) {
         tracing::info!(
             "TestRun {}/{} already finished: {:?}",
             namespace,
@@ -82,12 +127,13 @@ pub async fn reconcile(
 
     // Check if Job exists
     let jobs: Api<Job> = Api::namespaced(ctx.client.clone(), &namespace);
-    let job_name = format!("testrun-{name}");
+    let attempt = status.attempt.max(1);
+    let job_name = job_name(&attempt_run_id(&name, attempt));
 
     match jobs.get(&job_name).await {
         Ok(job) => {
             // Job exists, update status based on Job status
-            update_status_from_job(&testruns, &name, &job).await?;
+            update_status_from_job(&testruns, &testrun, &ctx, &job).await?;
         }
         Err(_) => {
             // Job doesn't exist, create it
@@ -97,7 +143,7 @@ pub async fn reconcile(
                     ctx.clone(),
                     &jobs,
                     &testruns,
-                    &job_name,
+                    attempt,
                 )
                 .await?;
             }
@@ -118,13 +164,20 @@ async fn handle_deletion(
 
     tracing::info!("Handling deletion of TestRun {}/{}", namespace, name);
 
-    // Delete the associated Job
+    // Delete the Jobs of the current and previous attempts
     let jobs: Api<Job> = Api::namespaced(ctx.client.clone(), &namespace);
-    let job_name = format!("testrun-{name}");
+    let status = testrun.status.clone().unwrap_or_default();
+    let job_names = status
+        .history
+        .iter()
+        .filter_map(|a| a.job_name.clone())
+        .chain([job_name(&attempt_run_id(&name, status.attempt.max(1)))]);
 
-    if jobs.get(&job_name).await.is_ok() {
-        tracing::info!("Deleting Job {}/{}", namespace, job_name);
-        let _ = jobs.delete(&job_name, &Default::default()).await;
+    for job_name in job_names {
+        if jobs.get(&job_name).await.is_ok() {
+            tracing::info!("Deleting Job {}/{}", namespace, job_name);
+            let _ = jobs.delete(&job_name, &Default::default()).await;
+        }
     }
 
     // Remove finalizer
@@ -169,7 +222,7 @@ async fn create_job_and_register_run(
     ctx: Arc<ReconcilerContext>,
     jobs: &Api<Job>,
     testruns: &Api<TestRun>,
-    job_name: &str,
+    attempt: i32,
 ) -> Result<(), ReconcileError> {
     let namespace = testrun.namespace().unwrap_or_else(|| "default".to_string());
     let name = testrun.name_any();
@@ -180,160 +233,318 @@ async fn create_job_and_register_run(
         name
     );
 
-    // Fetch test definition from backend to get image and commands
-    let def_id = &testrun.spec.definition_id;
-    let backend_url = &ctx.backend_url;
-    let client = reqwest::Client::new();
-
-    let definition_url = format!("{backend_url}/test-definitions/{def_id}");
-    let def_response = client.get(&definition_url).send().await?;
-
-    if !def_response.status().is_success() {
-        let status_code = def_response.status();
-        return Err(ReconcileError::MissingField(format!(
-            "Test definition not found: {status_code}"
-        )));
-    }
-
-    let definition: serde_json::Value = def_response.json().await?;
-
-    let image = definition["image"]
-        .as_str()
-        .ok_or_else(|| ReconcileError::MissingField("image".to_string()))?
-        .to_string();
-
-    let commands: Vec<String> = definition["commands"]
-        .as_array()
-        .ok_or_else(|| ReconcileError::MissingField("commands".to_string()))?
-        .iter()
-        .filter_map(|v| v.as_str().map(String::from))
-        .collect();
+    // Resolve image and commands, either inline or from the backend definition
+    let source = match testrun.spec.validate().and_then(|_| testrun.spec.source()) {
+        Ok(source) => source,
+        Err(message) => {
+            events::publish(
+                &ctx.client,
+                &ctx.reporter,
+                testrun.as_ref(),
+                EventType::Warning,
+                events::REASON_INVALID_SPEC,
+                message.clone(),
+            )
+            .await;
+            return Err(ReconcileError::InvalidSpec(message));
+        }
+    };
 
-    if commands.is_empty() {
-        return Err(ReconcileError::MissingField("commands (empty)".to_string()));
-    }
+    let (image, commands) = match &source {
+        TestSource::Definition(def_id) => fetch_definition(&ctx, &testrun, def_id).await?,
+        TestSource::Inline { image, commands } => (image.clone(), commands.clone()),
+    };
 
-    // Build Job
-    let job = build_job(
-        job_name,
-        &image,
-        &commands,
-        &testrun.spec.env,
-        testrun.spec.timeout_seconds,
-        testrun.spec.ttl_seconds_after_finished,
-    );
+    let definition_id = match &source {
+        TestSource::Definition(def_id) => Some(def_id.clone()),
+        TestSource::Inline { .. } => None,
+    };
+    let spec = RunSpec {
+        env: testrun.spec.env.clone(),
+        definition_id,
+        timeout_seconds: testrun.spec.timeout_seconds,
+        ttl_seconds_after_finished: testrun.spec.ttl_seconds_after_finished,
+        resources: testrun.spec.resources.as_ref().map(|r| RunResources {
+            requests: r.requests.clone(),
+            limits: r.limits.clone(),
+        }),
+        ..RunSpec::new(
+            attempt_run_id(&name, attempt),
+            image.clone(),
+            commands.clone(),
+            RunOrigin::Crd,
+        )
+    };
+    let job_name = &spec.job_name();
+    let job = spec.build_job();
 
     // Create Job
     jobs.create(&PostParams::default(), &job).await?;
 
     tracing::info!("Created Job {}/{}", namespace, job_name);
+    events::publish(
+        &ctx.client,
+        &ctx.reporter,
+        testrun.as_ref(),
+        EventType::Normal,
+        events::REASON_JOB_CREATED,
+        format!("Created Job {job_name}"),
+    )
+    .await;
 
     // Register run in backend
+    let run_name = if attempt > 1 {
+        format!("TestRun: {name} (attempt {attempt})")
+    } else {
+        format!("TestRun: {name}")
+    };
     let run_payload = json!({
-        "name": format!("TestRun: {}", name),
+        "name": run_name,
         "image": image,
         "commands": commands,
         "origin": "crd",
         "k8sRef": {
             "namespace": namespace,
-            "name": name
+            "name": name,
+            "jobName": job_name
         }
     });
 
-    let runs_url = format!("{backend_url}/test-runs");
-    let run_response = client.post(&runs_url).json(&run_payload).send().await?;
+    // Registration is best effort: the Job already exists, and inline runs
+    // must keep working when the backend is unreachable
+    let registration_error = match ctx
+        .backend
+        .post("/test-runs")
+        .json(&run_payload)
+        .send()
+        .await
+    {
+        Ok(response) if response.status().is_success() => None,
+        Ok(response) => Some(format!(
+            "Backend rejected run registration: {}",
+            response.status()
+        )),
+        Err(e) => Some(format!("Failed to reach backend to register run: {e}")),
+    };
 
-    if !run_response.status().is_success() {
-        tracing::warn!(
-            "Failed to register run in backend: {}",
-            run_response.status()
-        );
-    } else {
-        tracing::info!(
-            "Registered run in backend for TestRun {}/{}",
-            namespace,
-            name
-        );
+    match registration_error {
+        Some(message) => {
+            tracing::warn!(
+                "Failed to register run in backend for TestRun {}/{}: {}",
+                namespace,
+                name,
+                message
+            );
+            events::publish(
+                &ctx.client,
+                &ctx.reporter,
+                testrun.as_ref(),
+                EventType::Warning,
+                events::REASON_BACKEND_REGISTRATION_FAILED,
+                message,
+            )
+            .await;
+        }
+        None => {
+            tracing::info!(
+                "Registered run in backend for TestRun {}/{}",
+                namespace,
+                name
+            );
+        }
     }
 
+    // Record which attempt, spec and rerun request the Job runs
+    let patch = json!({
+        "status": {
+            "attempt": attempt,
+            "jobName": job_name,
+            "observedGeneration": testrun.metadata.generation,
+            "observedRerun": testrun.annotations().get(RERUN_ANNOTATION),
+        }
+    });
+    testruns
+        .patch_status(&name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
     // Update status to Pending
     update_status(testruns, &name, TestRunPhase::Pending, None, None).await?;
 
     Ok(())
 }
 
-/// Build a Kubernetes Job from TestRun spec
-pub fn build_job(
-    job_name: &str,
-    image: &str,
-    commands: &[String],
-    env_vars: &BTreeMap<String, String>,
-    timeout_seconds: Option<i32>,
-    ttl_seconds_after_finished: Option<i32>,
-) -> Job {
-    // Build command - wrap in shell if multiple commands
-    let k8s_command: Vec<String> = if commands.len() == 1 {
-        vec!["sh".to_string(), "-c".to_string(), commands[0].clone()]
-    } else {
-        vec!["sh".to_string(), "-c".to_string(), commands.join(" && ")]
+/// Whether the spec or the rerun annotation changed since the current attempt started
+///
+/// TestRuns whose status predates attempts have no observed generation, so
+/// only a rerun annotation re-runs them.
+pub fn rerun_requested(testrun: &TestRun) -> bool {
+    let Some(status) = &testrun.status else {
+        return false;
     };
 
-    // Build environment variables
-    let env: Vec<EnvVar> = env_vars
+    let spec_changed = status
+        .observed_generation
+        .is_some_and(|generation| Some(generation) != testrun.metadata.generation);
+    let rerun = testrun.annotations().get(RERUN_ANNOTATION);
+
+    spec_changed || rerun != status.observed_rerun.as_ref()
+}
+
+/// Run id of an attempt; the first attempt keeps the TestRun name
+pub fn attempt_run_id(name: &str, attempt: i32) -> String {
+    if attempt > 1 {
+        format!("{name}-{attempt}")
+    } else {
+        name.to_string()
+    }
+}
+
+fn is_finished(phase: Option<&TestRunPhase>) -> bool {
+    matches!(
+        phase,
+        Some(TestRunPhase::Succeeded) | Some(TestRunPhase::Failed) | Some(TestRunPhase::TimedOut)
+    )
+}
+
+/// Move the current attempt into the history and reset the status for the next one
+async fn start_next_attempt(
+    testrun: &TestRun,
+    ctx: &ReconcilerContext,
+    testruns: &Api<TestRun>,
+) -> Result<(), ReconcileError> {
+    let namespace = testrun.namespace().unwrap_or_else(|| "default".to_string());
+    let name = testrun.name_any();
+    let status = testrun.status.clone().unwrap_or_default();
+    let attempt = status.attempt.max(1);
+    let previous_job = status
+        .job_name
+        .clone()
+        .unwrap_or_else(|| job_name(&attempt_run_id(&name, attempt)));
+
+    // The unfinished attempt is superseded, so stop its Job
+    if !is_finished(status.phase.as_ref()) {
+        let jobs: Api<Job> = Api::namespaced(ctx.client.clone(), &namespace);
+        tracing::info!("Deleting superseded Job {}/{}", namespace, previous_job);
+        let _ = jobs.delete(&previous_job, &Default::default()).await;
+    }
+
+    let history = next_history(
+        &status.history,
+        TestRunAttempt {
+            attempt,
+            job_name: Some(previous_job),
+            phase: status.phase,
+            started_at: status.started_at,
+            finished_at: status.finished_at,
+        },
+    );
+
+    let patch = json!({
+        "status": {
+            "phase": null,
+            "startedAt": null,
+            "finishedAt": null,
+            "jobName": null,
+            "attempt": attempt + 1,
+            "history": history,
+        }
+    });
+    testruns
+        .patch_status(&name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
+    tracing::info!(
+        "Re-running TestRun {}/{} as attempt {}",
+        namespace,
+        name,
+        attempt + 1
+    );
+    events::publish(
+        &ctx.client,
+        &ctx.reporter,
+        testrun,
+        EventType::Normal,
+        events::REASON_RERUN,
+        format!("Starting attempt {}", attempt + 1),
+    )
+    .await;
+
+    Ok(())
+}
+
+/// History with `previous` appended, keeping the most recent attempts
+fn next_history(history: &[TestRunAttempt], previous: TestRunAttempt) -> Vec<TestRunAttempt> {
+    let mut history = history.to_vec();
+    history.push(previous);
+    let excess = history.len().saturating_sub(MAX_HISTORY);
+    history.drain(..excess);
+    history
+}
+
+/// Fetch image and commands for a test definition from the backend
+async fn fetch_definition(
+    ctx: &ReconcilerContext,
+    testrun: &TestRun,
+    def_id: &str,
+) -> Result<(String, Vec<String>), ReconcileError> {
+    let def_response = ctx
+        .backend
+        .get(&format!("/test-definitions/{def_id}"))
+        .send()
+        .await?;
+
+    if !def_response.status().is_success() {
+        let status_code = def_response.status();
+        let reason =
+            events::lookup_failure_reason(status_code, events::REASON_DEFINITION_NOT_FOUND);
+        events::publish(
+            &ctx.client,
+            &ctx.reporter,
+            testrun,
+            EventType::Warning,
+            reason,
+            format!("Test definition {def_id} could not be fetched: {status_code}"),
+        )
+        .await;
+        // Anything but a 404 may clear up, so the error policy requeues it
+        return Err(match status_code {
+            reqwest::StatusCode::NOT_FOUND => {
+                ReconcileError::MissingField(format!("Test definition not found: {status_code}"))
+            }
+            _ => ReconcileError::BackendError(status_code),
+        });
+    }
+
+    let definition: serde_json::Value = def_response.json().await?;
+
+    let image = definition["image"]
+        .as_str()
+        .ok_or_else(|| ReconcileError::MissingField("image".to_string()))?
+        .to_string();
+
+    let commands: Vec<String> = definition["commands"]
+        .as_array()
+        .ok_or_else(|| ReconcileError::MissingField("commands".to_string()))?
         .iter()
-        .map(|(k, v)| EnvVar {
-            name: k.clone(),
-            value: Some(v.clone()),
-            ..Default::default()
-        })
+        .filter_map(|v| v.as_str().map(String::from))
         .collect();
 
-    Job {
-        metadata: ObjectMeta {
-            name: Some(job_name.to_string()),
-            labels: Some(BTreeMap::from([
-                ("app".to_string(), "sparktest".to_string()),
-                ("component".to_string(), "test-runner".to_string()),
-                ("managed-by".to_string(), "sparktest-controller".to_string()),
-            ])),
-            ..Default::default()
-        },
-        spec: Some(JobSpec {
-            template: PodTemplateSpec {
-                metadata: Some(ObjectMeta {
-                    labels: Some(BTreeMap::from([
-                        ("job-name".to_string(), job_name.to_string()),
-                        ("app".to_string(), "sparktest".to_string()),
-                    ])),
-                    ..Default::default()
-                }),
-                spec: Some(PodSpec {
-                    containers: vec![Container {
-                        name: job_name.to_string(),
-                        image: Some(image.to_string()),
-                        command: Some(k8s_command),
-                        env: if env.is_empty() { None } else { Some(env) },
-                        ..Default::default()
-                    }],
-                    restart_policy: Some("Never".to_string()),
-                    ..Default::default()
-                }),
-            },
-            backoff_limit: Some(0), // Don't retry failed jobs
-            active_deadline_seconds: timeout_seconds.map(|s| s as i64),
-            ttl_seconds_after_finished,
-            ..Default::default()
-        }),
-        ..Default::default()
+    if commands.is_empty() {
+        return Err(ReconcileError::MissingField("commands (empty)".to_string()));
     }
+
+    Ok((image, commands))
 }
 
 /// Update status from Job
 async fn update_status_from_job(
     testruns: &Api<TestRun>,
-    name: &str,
+    testrun: &TestRun,
+    ctx: &ReconcilerContext,
     job: &Job,
 ) -> Result<(), ReconcileError> {
+    let name = testrun.name_any();
+    let previous_phase = testrun.status.as_ref().and_then(|s| s.phase.clone());
     let job_status = job.status.as_ref();
 
     let phase = if let Some(status) = job_status {
@@ -374,7 +585,21 @@ async fn update_status_from_job(
         .map(|t| t.0.to_rfc3339());
 
     if let Some(p) = phase {
-        update_status(testruns, name, p, started_at, finished_at).await?;
+        update_status(testruns, &name, p.clone(), started_at, finished_at).await?;
+
+        if previous_phase.as_ref() != Some(&p) {
+            if let Some((type_, reason)) = events::terminal_phase_event(&p) {
+                events::publish(
+                    &ctx.client,
+                    &ctx.reporter,
+                    testrun,
+                    type_,
+                    reason,
+                    format!("Job {} finished with phase {p:?}", job.name_any()),
+                )
+                .await;
+            }
+        }
     }
 
     Ok(())
@@ -426,128 +651,80 @@ pub fn error_policy(
 #[cfg(test)]
 mod tests {
     use super::*;
-
-    #[test]
-    fn test_build_job_single_command() {
-        let job = build_job(
-            "test-job",
-            "test:latest",
-            &["echo hello".to_string()],
-            &BTreeMap::new(),
-            None,
-            None,
-        );
-
-        assert_eq!(job.metadata.name, Some("test-job".to_string()));
-        let container = &job
-            .spec
-            .as_ref()
-            .unwrap()
-            .template
-            .spec
-            .as_ref()
-            .unwrap()
-            .containers[0];
-        assert_eq!(container.image, Some("test:latest".to_string()));
-        assert_eq!(
-            container.command,
-            Some(vec![
-                "sh".to_string(),
-                "-c".to_string(),
-                "echo hello".to_string()
-            ])
+    use crate::crd::TestRunSpec;
+    use std::collections::BTreeMap;
+
+    fn testrun(generation: i64, rerun: Option<&str>, status: Option<TestRunStatus>) -> TestRun {
+        let mut testrun = TestRun::new(
+            "run",
+            serde_json::from_value::<TestRunSpec>(serde_json::json!({"definitionId": "abc"}))
+                .unwrap(),
         );
+        testrun.metadata.generation = Some(generation);
+        testrun.metadata.annotations =
+            rerun.map(|value| BTreeMap::from([(RERUN_ANNOTATION.to_string(), value.to_string())]));
+        testrun.status = status;
+        testrun
     }
 
-    #[test]
-    fn test_build_job_multiple_commands() {
-        let job = build_job(
-            "test-job",
-            "test:latest",
-            &["echo hello".to_string(), "echo world".to_string()],
-            &BTreeMap::new(),
-            None,
-            None,
-        );
-
-        let container = &job
-            .spec
-            .as_ref()
-            .unwrap()
-            .template
-            .spec
-            .as_ref()
-            .unwrap()
-            .containers[0];
-        assert_eq!(
-            container.command,
-            Some(vec![
-                "sh".to_string(),
-                "-c".to_string(),
-                "echo hello && echo world".to_string()
-            ])
-        );
+    fn observed(generation: Option<i64>, rerun: Option<&str>) -> Option<TestRunStatus> {
+        Some(TestRunStatus {
+            phase: Some(TestRunPhase::Succeeded),
+            attempt: 1,
+            observed_generation: generation,
+            observed_rerun: rerun.map(String::from),
+            ..Default::default()
+        })
     }
 
     #[test]
-    fn test_build_job_with_env() {
-        let mut env_vars = BTreeMap::new();
-        env_vars.insert("KEY1".to_string(), "value1".to_string());
-        env_vars.insert("KEY2".to_string(), "value2".to_string());
-
-        let job = build_job(
-            "test-job",
-            "test:latest",
-            &["echo $KEY1".to_string()],
-            &env_vars,
-            None,
-            None,
-        );
+    fn test_rerun_requested() {
+        assert!(!rerun_requested(&testrun(1, None, None)));
+        assert!(!rerun_requested(&testrun(1, None, observed(Some(1), None))));
+        assert!(!rerun_requested(&testrun(
+            1,
+            Some("a"),
+            observed(Some(1), Some("a"))
+        )));
 
-        let container = &job
-            .spec
-            .as_ref()
-            .unwrap()
-            .template
-            .spec
-            .as_ref()
-            .unwrap()
-            .containers[0];
-        let env = container.env.as_ref().unwrap();
-        assert_eq!(env.len(), 2);
-        assert!(env
-            .iter()
-            .any(|e| e.name == "KEY1" && e.value == Some("value1".to_string())));
+        // Spec edits bump the generation
+        assert!(rerun_requested(&testrun(2, None, observed(Some(1), None))));
+        // Setting or changing the annotation
+        assert!(rerun_requested(&testrun(
+            1,
+            Some("a"),
+            observed(Some(1), None)
+        )));
+        assert!(rerun_requested(&testrun(
+            1,
+            Some("b"),
+            observed(Some(1), Some("a"))
+        )));
+        // Statuses from before attempts were tracked only rerun on the annotation
+        assert!(!rerun_requested(&testrun(3, None, observed(None, None))));
     }
 
     #[test]
-    fn test_build_job_with_timeout() {
-        let job = build_job(
-            "test-job",
-            "test:latest",
-            &["sleep 100".to_string()],
-            &BTreeMap::new(),
-            Some(60),
-            None,
-        );
-
-        assert_eq!(job.spec.as_ref().unwrap().active_deadline_seconds, Some(60));
+    fn test_attempt_run_id() {
+        assert_eq!(attempt_run_id("run", 0), "run");
+        assert_eq!(attempt_run_id("run", 1), "run");
+        assert_eq!(attempt_run_id("run", 3), "run-3");
     }
 
     #[test]
-    fn test_build_job_with_ttl() {
-        let job = build_job(
-            "test-job",
-            "test:latest",
-            &["echo done".to_string()],
-            &BTreeMap::new(),
-            None,
-            Some(3600),
-        );
-
-        assert_eq!(
-            job.spec.as_ref().unwrap().ttl_seconds_after_finished,
-            Some(3600)
-        );
+    fn test_next_history_keeps_recent_attempts() {
+        let attempt = |n| TestRunAttempt {
+            attempt: n,
+            job_name: None,
+            phase: Some(TestRunPhase::Failed),
+            started_at: None,
+            finished_at: None,
+        };
+
+        let history: Vec<_> = (1..=MAX_HISTORY as i32).map(attempt).collect();
+        let next = next_history(&history, attempt(MAX_HISTORY as i32 + 1));
+        assert_eq!(next.len(), MAX_HISTORY);
+        assert_eq!(next.first().unwrap().attempt, 2);
+        assert_eq!(next.last().unwrap().attempt, MAX_HISTORY as i32 + 1);
     }
 }
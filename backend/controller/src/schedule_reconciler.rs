@@ -0,0 +1,428 @@
+use crate::crd::{
+    TestRun, TestRunPhase, TestRunSpec, TestSchedule, TestScheduleStatus, TestSuiteRun,
+    TestSuiteRunSpec,
+};
+use crate::events;
+use crate::reconciler::{ReconcileError, ReconcilerContext};
+use crate::watch::inherited_labels;
+use chrono::{DateTime, Utc};
+use kube::{
+    api::{Api, DeleteParams, ListParams, ObjectMeta, Patch, PatchParams, PostParams},
+    runtime::{controller::Action, events::EventType},
+    Resource, ResourceExt,
+};
+use serde_json::json;
+use sparktest_core::{ConcurrencyPolicy, CronSchedule};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Label set on runs pointing at the TestSchedule that created them
+pub const SCHEDULE_LABEL: &str = "sparktest.dev/schedule";
+
+/// Longest time between reconciles of an idle schedule
+const MAX_REQUEUE: Duration = Duration::from_secs(300);
+
+/// A TestRun or TestSuiteRun created by a schedule
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledRun {
+    pub name: String,
+    pub created: Option<DateTime<Utc>>,
+    pub phase: Option<TestRunPhase>,
+}
+
+impl ScheduledRun {
+    fn is_finished(&self) -> bool {
+        matches!(
+            self.phase,
+            Some(TestRunPhase::Succeeded)
+                | Some(TestRunPhase::Failed)
+                | Some(TestRunPhase::TimedOut)
+        )
+    }
+}
+
+/// Reconcile a TestSchedule resource
+pub async fn reconcile_schedule(
+    schedule: Arc<TestSchedule>,
+    ctx: Arc<ReconcilerContext>,
+) -> Result<Action, ReconcileError> {
+    let metrics = ctx.metrics.clone();
+    metrics
+        .measure("testschedule", reconcile_testschedule(schedule, ctx))
+        .await
+}
+
+async fn reconcile_testschedule(
+    schedule: Arc<TestSchedule>,
+    ctx: Arc<ReconcilerContext>,
+) -> Result<Action, ReconcileError> {
+    let namespace = schedule
+        .namespace()
+        .unwrap_or_else(|| "default".to_string());
+    let name = schedule.name_any();
+
+    tracing::info!("Reconciling TestSchedule {}/{}", namespace, name);
+
+    // Scheduled runs are garbage collected through their owner references
+    if schedule.metadata.deletion_timestamp.is_some() {
+        return Ok(Action::await_change());
+    }
+
+    let cron = match schedule.spec.validate().and_then(|_| {
+        CronSchedule::parse(&schedule.spec.schedule, schedule.spec.time_zone.as_deref())
+            .map_err(|e| e.to_string())
+    }) {
+        Ok(cron) => cron,
+        Err(message) => {
+            events::publish(
+                &ctx.client,
+                &ctx.reporter,
+                schedule.as_ref(),
+                EventType::Warning,
+                events::REASON_INVALID_SPEC,
+                message.clone(),
+            )
+            .await;
+            return Err(ReconcileError::InvalidSpec(message));
+        }
+    };
+
+    let schedules: Api<TestSchedule> = Api::namespaced(ctx.client.clone(), &namespace);
+    let runs = list_runs(&ctx, &schedule, &namespace).await?;
+
+    // Drop finished runs beyond the history limits
+    for run_name in runs_to_prune(
+        &runs,
+        schedule.spec.successful_runs_history_limit,
+        schedule.spec.failed_runs_history_limit,
+    ) {
+        delete_run(&ctx, &schedule, &namespace, &run_name).await?;
+    }
+
+    let mut active: Vec<String> = runs
+        .iter()
+        .filter(|run| !run.is_finished())
+        .map(|run| run.name.clone())
+        .collect();
+
+    let status = schedule.status.clone().unwrap_or_default();
+    let now = Utc::now();
+    let since = status
+        .last_schedule_time
+        .as_deref()
+        .and_then(|t| DateTime::parse_from_rfc3339(t).ok())
+        .map(|t| t.with_timezone(&Utc))
+        .or_else(|| schedule.metadata.creation_timestamp.as_ref().map(|t| t.0))
+        .unwrap_or(now);
+    let mut last_schedule_time = status.last_schedule_time.clone();
+
+    if let Some(due) = cron
+        .latest_due(since, now)
+        .filter(|_| !schedule.spec.suspend)
+    {
+        let start = match schedule.spec.concurrency_policy {
+            ConcurrencyPolicy::Allow => true,
+            ConcurrencyPolicy::Forbid => active.is_empty(),
+            ConcurrencyPolicy::Replace => {
+                for run_name in active.drain(..) {
+                    delete_run(&ctx, &schedule, &namespace, &run_name).await?;
+                    events::publish(
+                        &ctx.client,
+                        &ctx.reporter,
+                        schedule.as_ref(),
+                        EventType::Normal,
+                        events::REASON_RUN_REPLACED,
+                        format!("Deleted active run {run_name}"),
+                    )
+                    .await;
+                }
+                true
+            }
+        };
+
+        if start {
+            let run_name = create_run(&ctx, &schedule, &namespace, due).await?;
+            events::publish(
+                &ctx.client,
+                &ctx.reporter,
+                schedule.as_ref(),
+                EventType::Normal,
+                events::REASON_RUN_SCHEDULED,
+                format!("Created {run_name} for {}", due.to_rfc3339()),
+            )
+            .await;
+            active.push(run_name);
+        } else {
+            events::publish(
+                &ctx.client,
+                &ctx.reporter,
+                schedule.as_ref(),
+                EventType::Normal,
+                events::REASON_RUN_SKIPPED,
+                format!(
+                    "Skipped run for {}: {} still active",
+                    due.to_rfc3339(),
+                    active.join(", ")
+                ),
+            )
+            .await;
+        }
+
+        last_schedule_time = Some(due.to_rfc3339());
+    }
+
+    let next = cron.next_after(now);
+    let new_status = TestScheduleStatus {
+        last_schedule_time,
+        next_schedule_time: next.map(|t| t.to_rfc3339()),
+        active,
+    };
+    let patch = json!({
+        "status": new_status
+    });
+    schedules
+        .patch_status(&name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
+    // Wake up for the next firing; run changes trigger a reconcile on their own
+    let wait = next
+        .and_then(|t| (t - now).to_std().ok())
+        .unwrap_or(MAX_REQUEUE)
+        .clamp(Duration::from_secs(1), MAX_REQUEUE);
+    Ok(Action::requeue(wait))
+}
+
+/// List the runs created by a schedule
+async fn list_runs(
+    ctx: &ReconcilerContext,
+    schedule: &TestSchedule,
+    namespace: &str,
+) -> Result<Vec<ScheduledRun>, ReconcileError> {
+    let params = ListParams::default().labels(&format!("{SCHEDULE_LABEL}={}", schedule.name_any()));
+
+    let runs = if schedule.spec.suite_id.is_some() {
+        let suiteruns: Api<TestSuiteRun> = Api::namespaced(ctx.client.clone(), namespace);
+        suiteruns
+            .list(&params)
+            .await?
+            .items
+            .into_iter()
+            .map(|run| ScheduledRun {
+                name: run.name_any(),
+                created: run.metadata.creation_timestamp.map(|t| t.0),
+                phase: run.status.and_then(|s| s.phase),
+            })
+            .collect()
+    } else {
+        let testruns: Api<TestRun> = Api::namespaced(ctx.client.clone(), namespace);
+        testruns
+            .list(&params)
+            .await?
+            .items
+            .into_iter()
+            .map(|run| ScheduledRun {
+                name: run.name_any(),
+                created: run.metadata.creation_timestamp.map(|t| t.0),
+                phase: run.status.and_then(|s| s.phase),
+            })
+            .collect()
+    };
+
+    Ok(runs)
+}
+
+/// Create the run for one firing of a schedule, returning its name
+async fn create_run(
+    ctx: &ReconcilerContext,
+    schedule: &TestSchedule,
+    namespace: &str,
+    due: DateTime<Utc>,
+) -> Result<String, ReconcileError> {
+    let metadata = scheduled_run_metadata(schedule, due);
+    let run_name = metadata.name.clone().unwrap_or_default();
+    let spec = &schedule.spec;
+
+    let result = if let Some(suite_id) = &spec.suite_id {
+        let suiterun = TestSuiteRun {
+            metadata,
+            spec: TestSuiteRunSpec {
+                suite_id: Some(suite_id.clone()),
+                definition_ids: Vec::new(),
+                execution_mode: None,
+                env: spec.env.clone(),
+                timeout_seconds: spec.timeout_seconds,
+                ttl_seconds_after_finished: spec.ttl_seconds_after_finished,
+            },
+            status: None,
+        };
+        let suiteruns: Api<TestSuiteRun> = Api::namespaced(ctx.client.clone(), namespace);
+        suiteruns
+            .create(&PostParams::default(), &suiterun)
+            .await
+            .map(|_| ())
+    } else {
+        let testrun = TestRun {
+            metadata,
+            spec: TestRunSpec {
+                definition_id: spec.definition_id.clone(),
+                image: None,
+                commands: Vec::new(),
+                env: spec.env.clone(),
+                resources: None,
+                timeout_seconds: spec.timeout_seconds,
+                ttl_seconds_after_finished: spec.ttl_seconds_after_finished,
+                run_id: None,
+            },
+            status: None,
+        };
+        let testruns: Api<TestRun> = Api::namespaced(ctx.client.clone(), namespace);
+        testruns
+            .create(&PostParams::default(), &testrun)
+            .await
+            .map(|_| ())
+    };
+
+    match result {
+        // Already created by an earlier reconcile of the same firing
+        Ok(()) | Err(kube::Error::Api(kube::core::ErrorResponse { code: 409, .. })) => Ok(run_name),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Delete a run created by a schedule
+async fn delete_run(
+    ctx: &ReconcilerContext,
+    schedule: &TestSchedule,
+    namespace: &str,
+    run_name: &str,
+) -> Result<(), ReconcileError> {
+    let params = DeleteParams::background();
+    let result = if schedule.spec.suite_id.is_some() {
+        let suiteruns: Api<TestSuiteRun> = Api::namespaced(ctx.client.clone(), namespace);
+        suiteruns.delete(run_name, &params).await.map(|_| ())
+    } else {
+        let testruns: Api<TestRun> = Api::namespaced(ctx.client.clone(), namespace);
+        testruns.delete(run_name, &params).await.map(|_| ())
+    };
+
+    match result {
+        Ok(()) | Err(kube::Error::Api(kube::core::ErrorResponse { code: 404, .. })) => Ok(()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+/// Metadata of the run for one firing of a schedule
+///
+/// The name is derived from the scheduled minute, so a firing creates at
+/// most one run even when it is reconciled more than once.
+pub fn scheduled_run_metadata(schedule: &TestSchedule, due: DateTime<Utc>) -> ObjectMeta {
+    let schedule_name = schedule.name_any();
+    let mut labels = inherited_labels(schedule.labels());
+    labels.insert(SCHEDULE_LABEL.to_string(), schedule_name.clone());
+
+    ObjectMeta {
+        name: Some(format!("{schedule_name}-{}", due.timestamp() / 60)),
+        namespace: schedule.namespace(),
+        labels: Some(labels),
+        owner_references: schedule.controller_owner_ref(&()).map(|r| vec![r]),
+        ..Default::default()
+    }
+}
+
+/// Names of finished runs beyond the history limits, oldest first
+pub fn runs_to_prune(
+    runs: &[ScheduledRun],
+    successful_limit: i32,
+    failed_limit: i32,
+) -> Vec<String> {
+    let mut finished: Vec<&ScheduledRun> = runs.iter().filter(|run| run.is_finished()).collect();
+    finished.sort_by_key(|run| std::cmp::Reverse(run.created));
+
+    let mut kept_succeeded = 0;
+    let mut kept_failed = 0;
+    let mut pruned = Vec::new();
+
+    for run in finished {
+        let (kept, limit) = if run.phase == Some(TestRunPhase::Succeeded) {
+            (&mut kept_succeeded, successful_limit)
+        } else {
+            (&mut kept_failed, failed_limit)
+        };
+
+        if *kept < limit {
+            *kept += 1;
+        } else {
+            pruned.push(run.name.clone());
+        }
+    }
+
+    pruned.reverse();
+    pruned
+}
+
+/// Handle errors during schedule reconciliation
+pub fn schedule_error_policy(
+    _schedule: Arc<TestSchedule>,
+    error: &ReconcileError,
+    _ctx: Arc<ReconcilerContext>,
+) -> Action {
+    tracing::error!("Schedule reconciliation error: {:?}", error);
+    Action::requeue(Duration::from_secs(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crd::TestScheduleSpec;
+    use chrono::TimeZone;
+
+    fn run(name: &str, minute: u32, phase: Option<TestRunPhase>) -> ScheduledRun {
+        ScheduledRun {
+            name: name.to_string(),
+            created: Some(Utc.with_ymd_and_hms(2025, 1, 1, 0, minute, 0).unwrap()),
+            phase,
+        }
+    }
+
+    #[test]
+    fn test_runs_to_prune_keeps_newest_per_outcome() {
+        let runs = vec![
+            run("a", 1, Some(TestRunPhase::Succeeded)),
+            run("b", 2, Some(TestRunPhase::Failed)),
+            run("c", 3, Some(TestRunPhase::Succeeded)),
+            run("d", 4, Some(TestRunPhase::TimedOut)),
+            run("e", 5, Some(TestRunPhase::Succeeded)),
+            run("f", 6, Some(TestRunPhase::Running)),
+            run("g", 7, None),
+        ];
+
+        assert_eq!(runs_to_prune(&runs, 2, 1), vec!["a", "b"]);
+        assert_eq!(runs_to_prune(&runs, 0, 0), vec!["a", "b", "c", "d", "e"]);
+        assert!(runs_to_prune(&runs, 3, 2).is_empty());
+    }
+
+    #[test]
+    fn test_scheduled_run_metadata() {
+        let mut schedule = TestSchedule::new(
+            "nightly",
+            serde_json::from_value::<TestScheduleSpec>(json!({
+                "schedule": "0 2 * * *",
+                "definitionId": "abc"
+            }))
+            .unwrap(),
+        );
+        schedule.metadata.namespace = Some("ci".to_string());
+        schedule.metadata.uid = Some("uid-1".to_string());
+
+        let due = Utc.with_ymd_and_hms(2025, 1, 1, 2, 0, 0).unwrap();
+        let metadata = scheduled_run_metadata(&schedule, due);
+
+        assert_eq!(
+            metadata.name.as_deref(),
+            Some(format!("nightly-{}", due.timestamp() / 60).as_str())
+        );
+        assert_eq!(metadata.namespace.as_deref(), Some("ci"));
+        assert_eq!(metadata.labels.unwrap()[SCHEDULE_LABEL], "nightly");
+        assert_eq!(metadata.owner_references.unwrap()[0].name, "nightly");
+    }
+}
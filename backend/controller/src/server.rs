@@ -0,0 +1,107 @@
+use crate::leader::LeaderElector;
+use crate::metrics::Metrics;
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json},
+    routing::get,
+    Router,
+};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use tokio::net::TcpListener;
+
+/// Shared state of the health, readiness and metrics endpoints
+#[derive(Clone)]
+pub struct ServerState {
+    pub leader: Arc<LeaderElector>,
+    pub metrics: Arc<Metrics>,
+    caches_synced: Arc<AtomicBool>,
+}
+
+impl ServerState {
+    pub fn new(leader: Arc<LeaderElector>, metrics: Arc<Metrics>) -> Self {
+        Self {
+            leader,
+            metrics,
+            caches_synced: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Record that the leader's controllers have synced their caches
+    pub fn mark_caches_synced(&self) {
+        self.caches_synced.store(true, Ordering::Relaxed);
+    }
+}
+
+/// Report liveness along with this replica's leader election state
+///
+/// Standby replicas are healthy too: they are waiting for the lease.
+pub async fn healthz(State(state): State<ServerState>) -> Json<serde_json::Value> {
+    let status = state.leader.status();
+
+    Json(serde_json::json!({
+        "status": "healthy",
+        "identity": status.identity,
+        "isLeader": status.is_leader,
+        "leader": status.leader,
+        "timestamp": chrono::Utc::now().to_rfc3339()
+    }))
+}
+
+/// Report whether this replica is ready
+///
+/// The leader is ready once its controllers have synced their caches;
+/// standbys are always ready.
+pub async fn readyz(State(state): State<ServerState>) -> (StatusCode, Json<serde_json::Value>) {
+    let is_leader = state.leader.status().is_leader;
+    let ready = !is_leader || state.caches_synced.load(Ordering::Relaxed);
+
+    let code = if ready {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+
+    (
+        code,
+        Json(serde_json::json!({
+            "ready": ready,
+            "isLeader": is_leader
+        })),
+    )
+}
+
+pub async fn metrics(State(state): State<ServerState>) -> impl IntoResponse {
+    let body = state.metrics.render(state.leader.status().is_leader);
+
+    ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], body)
+}
+
+pub fn router(state: ServerState) -> Router {
+    Router::new()
+        .route("/health", get(healthz))
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/metrics", get(metrics))
+        .with_state(state)
+}
+
+/// Serve the HTTP endpoints until the process exits
+pub async fn serve(port: u16, state: ServerState) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(e) => {
+            tracing::error!("Failed to bind HTTP endpoint on {}: {}", addr, e);
+            return;
+        }
+    };
+
+    tracing::info!("Health and metrics endpoints listening on {}", addr);
+    if let Err(e) = axum::serve(listener, router(state)).await {
+        tracing::error!("HTTP endpoint failed: {}", e);
+    }
+}
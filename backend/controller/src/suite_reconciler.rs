@@ -0,0 +1,451 @@
+use crate::crd::{
+    ExecutionMode, TestRun, TestRunPhase, TestRunSpec, TestSuiteRun, TestSuiteRunStatus,
+};
+use crate::events;
+use crate::reconciler::{ReconcileError, ReconcilerContext};
+use crate::watch::inherited_labels;
+use kube::{
+    api::{Api, ListParams, Patch, PatchParams, PostParams},
+    runtime::{controller::Action, events::EventType},
+    Resource, ResourceExt,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Label set on child TestRuns pointing at their owning TestSuiteRun
+pub const SUITE_RUN_LABEL: &str = "sparktest.dev/suite-run";
+
+/// Observed state of a single child TestRun of a suite run
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ChildState {
+    NotCreated,
+    Pending,
+    Running,
+    Succeeded,
+    Failed,
+}
+
+impl ChildState {
+    fn from_phase(phase: Option<&TestRunPhase>) -> Self {
+        match phase {
+            None | Some(TestRunPhase::Pending) => ChildState::Pending,
+            Some(TestRunPhase::Running) => ChildState::Running,
+            Some(TestRunPhase::Succeeded) => ChildState::Succeeded,
+            Some(TestRunPhase::Failed) | Some(TestRunPhase::TimedOut) => ChildState::Failed,
+        }
+    }
+
+    fn is_finished(self) -> bool {
+        matches!(self, ChildState::Succeeded | ChildState::Failed)
+    }
+}
+
+/// Reconcile a TestSuiteRun resource
+pub async fn reconcile_suite(
+    suiterun: Arc<TestSuiteRun>,
+    ctx: Arc<ReconcilerContext>,
+) -> Result<Action, ReconcileError> {
+    let metrics = ctx.metrics.clone();
+    metrics
+        .measure("testsuiterun", reconcile_suite_run(suiterun, ctx))
+        .await
+}
+
+async fn reconcile_suite_run(
+    suiterun: Arc<TestSuiteRun>,
+    ctx: Arc<ReconcilerContext>,
+) -> Result<Action, ReconcileError> {
+    let namespace = suiterun
+        .namespace()
+        .unwrap_or_else(|| "default".to_string());
+    let name = suiterun.name_any();
+
+    tracing::info!("Reconciling TestSuiteRun {}/{}", namespace, name);
+
+    // Child TestRuns are garbage collected through their owner references
+    if suiterun.metadata.deletion_timestamp.is_some() {
+        return Ok(Action::await_change());
+    }
+
+    let suiteruns: Api<TestSuiteRun> = Api::namespaced(ctx.client.clone(), &namespace);
+    let testruns: Api<TestRun> = Api::namespaced(ctx.client.clone(), &namespace);
+    let status = suiterun.status.clone().unwrap_or_default();
+
+    // If already finished, don't reconcile
+    if matches!(
+        status.phase,
+        Some(TestRunPhase::Succeeded) | Some(TestRunPhase::Failed) | Some(TestRunPhase::TimedOut)
+    ) {
+        return Ok(Action::await_change());
+    }
+
+    // Resolve the definitions once and record them, so the suite run is not
+    // affected by later edits to the backend suite
+    if status.phase.is_none() {
+        let (definition_ids, execution_mode) = resolve_suite(&suiterun, &ctx).await?;
+        let total = definition_ids.len() as i32;
+        let status = TestSuiteRunStatus {
+            phase: Some(TestRunPhase::Pending),
+            execution_mode: Some(execution_mode),
+            definition_ids,
+            total,
+            pending: total,
+            started_at: Some(chrono::Utc::now().to_rfc3339()),
+            ..Default::default()
+        };
+        patch_status(&suiteruns, &name, &status).await?;
+        return Ok(Action::requeue(Duration::from_secs(1)));
+    }
+
+    // Observe the child TestRuns
+    let selector = format!("{SUITE_RUN_LABEL}={name}");
+    let children = testruns
+        .list(&ListParams::default().labels(&selector))
+        .await?;
+    let phases: BTreeMap<String, Option<TestRunPhase>> = children
+        .items
+        .into_iter()
+        .map(|child| (child.name_any(), child.status.and_then(|s| s.phase)))
+        .collect();
+
+    let mut states: Vec<ChildState> = (0..status.definition_ids.len())
+        .map(|index| match phases.get(&child_name(&name, index)) {
+            Some(phase) => ChildState::from_phase(phase.as_ref()),
+            None => ChildState::NotCreated,
+        })
+        .collect();
+
+    // Start the children the execution mode allows
+    let execution_mode = status.execution_mode.unwrap_or_default();
+    for index in children_to_start(execution_mode, &states) {
+        let child = build_child_testrun(&suiterun, index, &status.definition_ids[index]);
+        match testruns.create(&PostParams::default(), &child).await {
+            Ok(_) => {
+                events::publish(
+                    &ctx.client,
+                    &ctx.reporter,
+                    suiterun.as_ref(),
+                    EventType::Normal,
+                    events::REASON_TEST_RUN_CREATED,
+                    format!("Created TestRun {}", child.name_any()),
+                )
+                .await;
+            }
+            Err(kube::Error::Api(e)) if e.code == 409 => {}
+            Err(e) => return Err(e.into()),
+        }
+        states[index] = ChildState::Pending;
+    }
+
+    // Aggregate the children's phases into the suite run status
+    let mut new_status = summarize(&states);
+    new_status.execution_mode = status.execution_mode;
+    new_status.definition_ids = status.definition_ids.clone();
+    new_status.started_at = status.started_at.clone();
+
+    let finished = matches!(
+        new_status.phase,
+        Some(TestRunPhase::Succeeded) | Some(TestRunPhase::Failed)
+    );
+    if finished {
+        new_status.finished_at = Some(chrono::Utc::now().to_rfc3339());
+    }
+    patch_status(&suiteruns, &name, &new_status).await?;
+
+    if let Some(phase) = new_status.phase.as_ref().filter(|_| finished) {
+        if let Some((type_, reason)) = events::terminal_phase_event(phase) {
+            events::publish(
+                &ctx.client,
+                &ctx.reporter,
+                suiterun.as_ref(),
+                type_,
+                reason,
+                format!(
+                    "{} of {} TestRuns succeeded",
+                    new_status.succeeded, new_status.total
+                ),
+            )
+            .await;
+        }
+    }
+
+    Ok(Action::requeue(Duration::from_secs(30))) // Recheck every 30 seconds
+}
+
+/// Resolve the definitions and execution mode of a suite run
+async fn resolve_suite(
+    suiterun: &TestSuiteRun,
+    ctx: &ReconcilerContext,
+) -> Result<(Vec<String>, ExecutionMode), ReconcileError> {
+    if let Err(message) = suiterun.spec.validate() {
+        events::publish(
+            &ctx.client,
+            &ctx.reporter,
+            suiterun,
+            EventType::Warning,
+            events::REASON_INVALID_SPEC,
+            message.clone(),
+        )
+        .await;
+        return Err(ReconcileError::InvalidSpec(message));
+    }
+
+    let Some(suite_id) = &suiterun.spec.suite_id else {
+        return Ok((
+            suiterun.spec.definition_ids.clone(),
+            suiterun.spec.execution_mode.unwrap_or_default(),
+        ));
+    };
+
+    // Fetch the suite from the backend to get its definitions
+    let suite_response = ctx
+        .backend
+        .get(&format!("/test-suites/{suite_id}"))
+        .send()
+        .await?;
+
+    if !suite_response.status().is_success() {
+        let status_code = suite_response.status();
+        let reason = events::lookup_failure_reason(status_code, events::REASON_SUITE_NOT_FOUND);
+        events::publish(
+            &ctx.client,
+            &ctx.reporter,
+            suiterun,
+            EventType::Warning,
+            reason,
+            format!("Test suite {suite_id} could not be fetched: {status_code}"),
+        )
+        .await;
+        return Err(match status_code {
+            reqwest::StatusCode::NOT_FOUND => {
+                ReconcileError::MissingField(format!("Test suite not found: {status_code}"))
+            }
+            _ => ReconcileError::BackendError(status_code),
+        });
+    }
+
+    let suite: serde_json::Value = suite_response.json().await?;
+
+    let definition_ids: Vec<String> = suite["testDefinitionIds"]
+        .as_array()
+        .ok_or_else(|| ReconcileError::MissingField("testDefinitionIds".to_string()))?
+        .iter()
+        .filter_map(|v| v.as_str().map(String::from))
+        .collect();
+
+    if definition_ids.is_empty() {
+        return Err(ReconcileError::MissingField(
+            "testDefinitionIds (empty)".to_string(),
+        ));
+    }
+
+    let execution_mode = suiterun.spec.execution_mode.unwrap_or_else(|| {
+        serde_json::from_value(suite["executionMode"].clone()).unwrap_or_default()
+    });
+
+    Ok((definition_ids, execution_mode))
+}
+
+/// Name of the child TestRun at `index` of a suite run
+pub fn child_name(suite_run_name: &str, index: usize) -> String {
+    format!("{suite_run_name}-{index}")
+}
+
+/// Indices of the children to create next
+///
+/// Parallel suites start every child at once; sequential suites start the
+/// next child only once all earlier children have finished.
+pub fn children_to_start(mode: ExecutionMode, states: &[ChildState]) -> Vec<usize> {
+    let not_created = states
+        .iter()
+        .enumerate()
+        .filter(|(_, state)| **state == ChildState::NotCreated)
+        .map(|(index, _)| index);
+
+    match mode {
+        ExecutionMode::Parallel => not_created.collect(),
+        ExecutionMode::Sequential => states
+            .iter()
+            .position(|state| !state.is_finished())
+            .filter(|&index| states[index] == ChildState::NotCreated)
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Aggregate child states into counts and a suite phase
+pub fn summarize(states: &[ChildState]) -> TestSuiteRunStatus {
+    let count =
+        |wanted: &[ChildState]| states.iter().filter(|state| wanted.contains(state)).count() as i32;
+
+    let pending = count(&[ChildState::NotCreated, ChildState::Pending]);
+    let running = count(&[ChildState::Running]);
+    let succeeded = count(&[ChildState::Succeeded]);
+    let failed = count(&[ChildState::Failed]);
+    let total = states.len() as i32;
+
+    let phase = if succeeded + failed == total {
+        if failed > 0 {
+            TestRunPhase::Failed
+        } else {
+            TestRunPhase::Succeeded
+        }
+    } else if running > 0 || succeeded + failed > 0 {
+        TestRunPhase::Running
+    } else {
+        TestRunPhase::Pending
+    };
+
+    TestSuiteRunStatus {
+        phase: Some(phase),
+        total,
+        pending,
+        running,
+        succeeded,
+        failed,
+        ..Default::default()
+    }
+}
+
+/// Build the child TestRun for one definition of a suite run
+pub fn build_child_testrun(suiterun: &TestSuiteRun, index: usize, definition_id: &str) -> TestRun {
+    let suite_run_name = suiterun.name_any();
+
+    let mut testrun = TestRun::new(
+        &child_name(&suite_run_name, index),
+        TestRunSpec {
+            definition_id: Some(definition_id.to_string()),
+            image: None,
+            commands: Vec::new(),
+            env: suiterun.spec.env.clone(),
+            resources: None,
+            timeout_seconds: suiterun.spec.timeout_seconds,
+            ttl_seconds_after_finished: suiterun.spec.ttl_seconds_after_finished,
+            run_id: None,
+        },
+    );
+    testrun.metadata.namespace = suiterun.namespace();
+    let mut labels = inherited_labels(suiterun.labels());
+    labels.insert(SUITE_RUN_LABEL.to_string(), suite_run_name);
+    testrun.metadata.labels = Some(labels);
+    testrun.metadata.owner_references = suiterun.controller_owner_ref(&()).map(|r| vec![r]);
+    testrun
+}
+
+/// Update TestSuiteRun status
+async fn patch_status(
+    suiteruns: &Api<TestSuiteRun>,
+    name: &str,
+    status: &TestSuiteRunStatus,
+) -> Result<(), ReconcileError> {
+    let patch = json!({
+        "status": status
+    });
+
+    suiteruns
+        .patch_status(name, &PatchParams::default(), &Patch::Merge(patch))
+        .await?;
+
+    Ok(())
+}
+
+/// Handle errors during suite reconciliation
+pub fn suite_error_policy(
+    _suiterun: Arc<TestSuiteRun>,
+    error: &ReconcileError,
+    _ctx: Arc<ReconcilerContext>,
+) -> Action {
+    tracing::error!("Suite reconciliation error: {:?}", error);
+    Action::requeue(Duration::from_secs(60))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::crd::TestSuiteRunSpec;
+    use ChildState::*;
+
+    #[test]
+    fn test_parallel_starts_all_missing_children() {
+        assert_eq!(
+            children_to_start(ExecutionMode::Parallel, &[NotCreated, Running, NotCreated]),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn test_sequential_waits_for_previous_children() {
+        assert_eq!(
+            children_to_start(ExecutionMode::Sequential, &[NotCreated, NotCreated]),
+            vec![0]
+        );
+        assert!(children_to_start(ExecutionMode::Sequential, &[Running, NotCreated]).is_empty());
+        assert_eq!(
+            children_to_start(ExecutionMode::Sequential, &[Succeeded, Failed, NotCreated]),
+            vec![2]
+        );
+        assert!(children_to_start(ExecutionMode::Sequential, &[Succeeded, Succeeded]).is_empty());
+    }
+
+    #[test]
+    fn test_summarize_phases() {
+        assert_eq!(
+            summarize(&[NotCreated, Pending]).phase,
+            Some(TestRunPhase::Pending)
+        );
+        assert_eq!(
+            summarize(&[Succeeded, NotCreated]).phase,
+            Some(TestRunPhase::Running)
+        );
+        assert_eq!(
+            summarize(&[Succeeded, Succeeded]).phase,
+            Some(TestRunPhase::Succeeded)
+        );
+
+        let status = summarize(&[Succeeded, Failed, Running, Pending]);
+        assert_eq!(status.phase, Some(TestRunPhase::Running));
+        assert_eq!(status.total, 4);
+        assert_eq!(status.succeeded, 1);
+        assert_eq!(status.failed, 1);
+        assert_eq!(status.running, 1);
+        assert_eq!(status.pending, 1);
+
+        assert_eq!(
+            summarize(&[Succeeded, Failed]).phase,
+            Some(TestRunPhase::Failed)
+        );
+    }
+
+    #[test]
+    fn test_build_child_testrun() {
+        let mut suiterun = TestSuiteRun::new(
+            "nightly",
+            TestSuiteRunSpec {
+                suite_id: None,
+                definition_ids: vec!["def-a".to_string()],
+                execution_mode: None,
+                env: BTreeMap::from([("CI".to_string(), "true".to_string())]),
+                timeout_seconds: Some(600),
+                ttl_seconds_after_finished: None,
+            },
+        );
+        suiterun.metadata.namespace = Some("sparktest".to_string());
+        suiterun.metadata.uid = Some("1234".to_string());
+
+        let child = build_child_testrun(&suiterun, 0, "def-a");
+
+        assert_eq!(child.name_any(), "nightly-0");
+        assert_eq!(child.namespace().as_deref(), Some("sparktest"));
+        assert_eq!(child.spec.definition_id.as_deref(), Some("def-a"));
+        assert_eq!(child.spec.env["CI"], "true");
+        assert_eq!(child.spec.timeout_seconds, Some(600));
+        assert_eq!(child.labels()[SUITE_RUN_LABEL], "nightly");
+
+        let owners = child.owner_references();
+        assert_eq!(owners.len(), 1);
+        assert_eq!(owners[0].kind, "TestSuiteRun");
+        assert_eq!(owners[0].controller, Some(true));
+    }
+}
@@ -0,0 +1,132 @@
+use kube::{api::Api, runtime::watcher::Config, Client, Resource};
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Prefix of the labels the controller manages itself
+const MANAGED_LABEL_PREFIX: &str = "sparktest.dev/";
+
+/// Which SparkTest resources the controller watches
+///
+/// By default every namespace is watched, which needs cluster-wide RBAC.
+/// Restricting the scope to namespaces only needs a Role in each of them.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct WatchScope {
+    /// Namespaces to watch; empty means all namespaces
+    pub namespaces: Vec<String>,
+    /// Only reconcile resources matching this label selector
+    pub label_selector: Option<String>,
+}
+
+impl WatchScope {
+    /// Read the scope from `WATCH_NAMESPACES` (comma-separated) and `WATCH_LABEL_SELECTOR`
+    pub fn from_env() -> Self {
+        Self::parse(
+            std::env::var("WATCH_NAMESPACES").ok().as_deref(),
+            std::env::var("WATCH_LABEL_SELECTOR").ok().as_deref(),
+        )
+    }
+
+    pub fn parse(namespaces: Option<&str>, label_selector: Option<&str>) -> Self {
+        let namespaces = namespaces
+            .unwrap_or_default()
+            .split(',')
+            .map(str::trim)
+            .filter(|ns| !ns.is_empty())
+            .map(String::from)
+            .collect();
+        let label_selector = label_selector
+            .map(str::trim)
+            .filter(|selector| !selector.is_empty())
+            .map(String::from);
+
+        Self {
+            namespaces,
+            label_selector,
+        }
+    }
+
+    /// One Api per watched namespace, or a single cluster-wide Api
+    pub fn apis<K>(&self, client: &Client) -> Vec<Api<K>>
+    where
+        K: Resource<Scope = k8s_openapi::NamespaceResourceScope>,
+        K::DynamicType: Default,
+    {
+        if self.namespaces.is_empty() {
+            vec![Api::all(client.clone())]
+        } else {
+            self.namespaces
+                .iter()
+                .map(|ns| Api::namespaced(client.clone(), ns))
+                .collect()
+        }
+    }
+
+    /// Watcher configuration applying the label selector
+    pub fn watcher_config(&self) -> Config {
+        match &self.label_selector {
+            Some(selector) => Config::default().labels(selector),
+            None => Config::default(),
+        }
+    }
+}
+
+impl fmt::Display for WatchScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.namespaces.is_empty() {
+            write!(f, "all namespaces")?;
+        } else {
+            write!(f, "namespaces {}", self.namespaces.join(", "))?;
+        }
+        if let Some(selector) = &self.label_selector {
+            write!(f, " matching {selector}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Labels a child resource copies from its parent
+///
+/// Children keep matching the label selector their parent was picked up by;
+/// the controller's own `sparktest.dev/` labels are not copied.
+pub fn inherited_labels(parent: &BTreeMap<String, String>) -> BTreeMap<String, String> {
+    parent
+        .iter()
+        .filter(|(key, _)| !key.starts_with(MANAGED_LABEL_PREFIX))
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_scope() {
+        assert_eq!(WatchScope::parse(None, None), WatchScope::default());
+        assert_eq!(
+            WatchScope::parse(Some(""), Some(" ")),
+            WatchScope::default()
+        );
+
+        let scope = WatchScope::parse(Some("team-a, team-b,"), Some("tenant=a"));
+        assert_eq!(scope.namespaces, vec!["team-a", "team-b"]);
+        assert_eq!(scope.label_selector.as_deref(), Some("tenant=a"));
+        assert_eq!(
+            scope.to_string(),
+            "namespaces team-a, team-b matching tenant=a"
+        );
+    }
+
+    #[test]
+    fn test_inherited_labels() {
+        let parent = BTreeMap::from([
+            ("tenant".to_string(), "a".to_string()),
+            ("sparktest.dev/schedule".to_string(), "nightly".to_string()),
+        ]);
+
+        assert_eq!(
+            inherited_labels(&parent),
+            BTreeMap::from([("tenant".to_string(), "a".to_string())])
+        );
+    }
+}
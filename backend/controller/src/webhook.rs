@@ -0,0 +1,207 @@
+use crate::backend::BackendClient;
+use crate::crd::TestRunSpec;
+use axum::{extract::State, response::Json, routing::post, Router};
+use axum_server::tls_rustls::RustlsConfig;
+use kube::core::{
+    admission::{AdmissionRequest, AdmissionResponse, AdmissionReview, Operation},
+    DynamicObject,
+};
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// Path the ValidatingWebhookConfiguration sends TestRun reviews to
+pub const VALIDATE_TESTRUN_PATH: &str = "/validate-testrun";
+
+/// Shared state of the admission webhook
+#[derive(Clone)]
+pub struct WebhookState {
+    pub backend: BackendClient,
+    /// Reject TestRuns whose definition does not exist in the backend
+    pub verify_definitions: bool,
+}
+
+impl WebhookState {
+    pub fn new(backend: BackendClient, verify_definitions: bool) -> Self {
+        Self {
+            backend,
+            verify_definitions,
+        }
+    }
+}
+
+/// Admit or reject a TestRun create or update
+pub async fn validate_testrun(
+    State(state): State<WebhookState>,
+    Json(review): Json<AdmissionReview<DynamicObject>>,
+) -> Json<AdmissionReview<DynamicObject>> {
+    let request: AdmissionRequest<DynamicObject> = match review.try_into() {
+        Ok(request) => request,
+        Err(e) => {
+            tracing::error!("Invalid admission review: {}", e);
+            return Json(AdmissionResponse::invalid(e.to_string()).into_review());
+        }
+    };
+
+    Json(review_testrun(&state, &request).await.into_review())
+}
+
+async fn review_testrun(
+    state: &WebhookState,
+    request: &AdmissionRequest<DynamicObject>,
+) -> AdmissionResponse {
+    let response = AdmissionResponse::from(request);
+
+    let Some(object) = &request.object else {
+        return response;
+    };
+    let spec = &object.data["spec"];
+
+    // Metadata-only updates, such as the controller's finalizer patches, must
+    // keep working for TestRuns created before the webhook was installed
+    if request.operation == Operation::Update
+        && request
+            .old_object
+            .as_ref()
+            .is_some_and(|old| &old.data["spec"] == spec)
+    {
+        return response;
+    }
+
+    let spec: TestRunSpec = match serde_json::from_value(spec.clone()) {
+        Ok(spec) => spec,
+        Err(e) => return response.deny(format!("Invalid TestRun spec: {e}")),
+    };
+    if let Err(message) = spec.validate() {
+        return response.deny(message);
+    }
+
+    match &spec.definition_id {
+        Some(def_id) if state.verify_definitions => check_definition(state, def_id, response).await,
+        _ => response,
+    }
+}
+
+/// Reject the TestRun if the backend does not know its definition
+///
+/// An unreachable backend only adds a warning, so it cannot block applies.
+async fn check_definition(
+    state: &WebhookState,
+    def_id: &str,
+    mut response: AdmissionResponse,
+) -> AdmissionResponse {
+    let definition_url = format!("/test-definitions/{def_id}");
+
+    let warning = match state.backend.get(&definition_url).send().await {
+        Ok(res) if res.status().is_success() => return response,
+        Ok(res) if res.status() == reqwest::StatusCode::NOT_FOUND => {
+            return response.deny(format!("Test definition {def_id} does not exist"));
+        }
+        Ok(res) => format!(
+            "Could not verify test definition {def_id}: backend returned {}",
+            res.status()
+        ),
+        Err(e) => format!("Could not verify test definition {def_id}: {e}"),
+    };
+
+    tracing::warn!("{}", warning);
+    response.warnings = Some(vec![warning]);
+    response
+}
+
+pub fn router(state: WebhookState) -> Router {
+    Router::new()
+        .route(VALIDATE_TESTRUN_PATH, post(validate_testrun))
+        .with_state(state)
+}
+
+/// Serve the webhook over HTTPS with the certificate in `cert_dir`
+///
+/// `cert_dir` holds `tls.crt` and `tls.key`, as mounted from a TLS Secret.
+pub async fn serve(port: u16, cert_dir: &Path, state: WebhookState) {
+    let addr = SocketAddr::from(([0, 0, 0, 0], port));
+
+    let tls = match RustlsConfig::from_pem_file(cert_dir.join("tls.crt"), cert_dir.join("tls.key"))
+        .await
+    {
+        Ok(tls) => tls,
+        Err(e) => {
+            tracing::error!(
+                "Failed to load webhook certificate from {}: {}",
+                cert_dir.display(),
+                e
+            );
+            return;
+        }
+    };
+
+    tracing::info!("Admission webhook listening on {}", addr);
+    if let Err(e) = axum_server::bind_rustls(addr, tls)
+        .serve(router(state).into_make_service())
+        .await
+    {
+        tracing::error!("Admission webhook failed: {}", e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn review(
+        operation: &str,
+        spec: serde_json::Value,
+        old_spec: Option<serde_json::Value>,
+    ) -> AdmissionRequest<DynamicObject> {
+        let object = |spec: serde_json::Value| {
+            serde_json::json!({
+                "apiVersion": "sparktest.dev/v1alpha1",
+                "kind": "TestRun",
+                "metadata": {"name": "run", "namespace": "default"},
+                "spec": spec
+            })
+        };
+
+        let review: AdmissionReview<DynamicObject> = serde_json::from_value(serde_json::json!({
+            "apiVersion": "admission.k8s.io/v1",
+            "kind": "AdmissionReview",
+            "request": {
+                "uid": "705ab4f5-6393-11e8-b7cc-42010a800002",
+                "kind": {"group": "sparktest.dev", "version": "v1alpha1", "kind": "TestRun"},
+                "resource": {"group": "sparktest.dev", "version": "v1alpha1", "resource": "testruns"},
+                "name": "run",
+                "namespace": "default",
+                "operation": operation,
+                "userInfo": {"username": "admin"},
+                "object": object(spec),
+                "oldObject": old_spec.map(object),
+                "dryRun": false
+            }
+        }))
+        .unwrap();
+        review.try_into().unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_review_testrun() {
+        let state = WebhookState::new(
+            BackendClient::new("http://backend.invalid/api".to_string(), None),
+            false,
+        );
+
+        let valid = review(
+            "CREATE",
+            serde_json::json!({"image": "node:18", "commands": ["npm test"]}),
+            None,
+        );
+        assert!(review_testrun(&state, &valid).await.allowed);
+
+        let invalid = serde_json::json!({"definitionId": "abc", "timeoutSeconds": -1});
+        let denied = review_testrun(&state, &review("CREATE", invalid.clone(), None)).await;
+        assert!(!denied.allowed);
+        assert!(denied.result.message.contains("not a UUID"));
+
+        // Unchanged specs are admitted so finalizers can still be removed
+        let metadata_update = review("UPDATE", invalid.clone(), Some(invalid));
+        assert!(review_testrun(&state, &metadata_update).await.allowed);
+    }
+}
@@ -0,0 +1,346 @@
+use crate::models::RunOrigin;
+use k8s_openapi::api::batch::v1::{Job, JobSpec};
+use k8s_openapi::api::core::v1::{
+    Container, EnvVar, PodSpec, PodTemplateSpec, ResourceRequirements,
+};
+use k8s_openapi::apimachinery::pkg::api::resource::Quantity;
+use k8s_openapi::apimachinery::pkg::apis::meta::v1::ObjectMeta;
+use std::collections::BTreeMap;
+
+/// Prefix of the name of every Job running a test
+pub const JOB_NAME_PREFIX: &str = "test-run-";
+
+/// Label holding the id of the run a Job belongs to
+pub const RUN_ID_LABEL: &str = "sparktest.dev/run-id";
+
+/// Label holding the id of the test definition a Job runs, if any
+pub const DEFINITION_ID_LABEL: &str = "sparktest.dev/definition-id";
+
+/// Label holding whether a Job was started through the API or a TestRun
+pub const ORIGIN_LABEL: &str = "sparktest.dev/origin";
+
+/// Name of the Job running `run_id`
+pub fn job_name(run_id: &str) -> String {
+    format!("{JOB_NAME_PREFIX}{run_id}")
+}
+
+/// Compute resource requests and limits, as Kubernetes quantity strings
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RunResources {
+    pub requests: BTreeMap<String, String>,
+    pub limits: BTreeMap<String, String>,
+}
+
+/// Everything needed to run a test as a Kubernetes Job
+///
+/// The run id is the backend run UUID for API runs and the TestRun name for
+/// CRD runs; the Job name and labels are derived from it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RunSpec {
+    pub run_id: String,
+    pub image: String,
+    pub commands: Vec<String>,
+    pub env: BTreeMap<String, String>,
+    pub definition_id: Option<String>,
+    pub origin: RunOrigin,
+    pub timeout_seconds: Option<i32>,
+    pub ttl_seconds_after_finished: Option<i32>,
+    pub resources: Option<RunResources>,
+}
+
+impl RunSpec {
+    pub fn new(
+        run_id: impl Into<String>,
+        image: impl Into<String>,
+        commands: Vec<String>,
+        origin: RunOrigin,
+    ) -> Self {
+        Self {
+            run_id: run_id.into(),
+            image: image.into(),
+            commands,
+            env: BTreeMap::new(),
+            definition_id: None,
+            origin,
+            timeout_seconds: None,
+            ttl_seconds_after_finished: None,
+            resources: None,
+        }
+    }
+
+    pub fn job_name(&self) -> String {
+        job_name(&self.run_id)
+    }
+
+    /// Container command running all commands in sequence with `sh -c`
+    pub fn shell_command(&self) -> Vec<String> {
+        vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            self.commands.join(" && "),
+        ]
+    }
+
+    /// Labels set on both the Job and its pods
+    pub fn labels(&self) -> BTreeMap<String, String> {
+        let managed_by = match self.origin {
+            RunOrigin::Api => "sparktest-api",
+            RunOrigin::Crd => "sparktest-controller",
+        };
+
+        let mut labels = BTreeMap::from([
+            ("app".to_string(), "sparktest".to_string()),
+            ("component".to_string(), "test-runner".to_string()),
+            ("managed-by".to_string(), managed_by.to_string()),
+            (RUN_ID_LABEL.to_string(), self.run_id.clone()),
+            (ORIGIN_LABEL.to_string(), self.origin.as_str().to_string()),
+        ]);
+        if let Some(definition_id) = &self.definition_id {
+            labels.insert(DEFINITION_ID_LABEL.to_string(), definition_id.clone());
+        }
+        labels
+    }
+
+    /// Build the Kubernetes Job running this test
+    pub fn build_job(&self) -> Job {
+        let job_name = self.job_name();
+        let labels = self.labels();
+
+        let mut pod_labels = labels.clone();
+        pod_labels.insert("job-name".to_string(), job_name.clone());
+
+        let env: Vec<EnvVar> = self
+            .env
+            .iter()
+            .map(|(k, v)| EnvVar {
+                name: k.clone(),
+                value: Some(v.clone()),
+                ..Default::default()
+            })
+            .collect();
+
+        Job {
+            metadata: ObjectMeta {
+                name: Some(job_name.clone()),
+                labels: Some(labels),
+                ..Default::default()
+            },
+            spec: Some(JobSpec {
+                template: PodTemplateSpec {
+                    metadata: Some(ObjectMeta {
+                        labels: Some(pod_labels),
+                        ..Default::default()
+                    }),
+                    spec: Some(PodSpec {
+                        containers: vec![Container {
+                            name: job_name,
+                            image: Some(self.image.clone()),
+                            command: Some(self.shell_command()),
+                            env: if env.is_empty() { None } else { Some(env) },
+                            resources: self.resources.as_ref().map(build_resource_requirements),
+                            ..Default::default()
+                        }],
+                        restart_policy: Some("Never".to_string()),
+                        ..Default::default()
+                    }),
+                },
+                backoff_limit: Some(0), // Don't retry failed jobs
+                active_deadline_seconds: self.timeout_seconds.map(i64::from),
+                ttl_seconds_after_finished: self.ttl_seconds_after_finished,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+}
+
+/// Convert run resources into Kubernetes resource requirements
+fn build_resource_requirements(resources: &RunResources) -> ResourceRequirements {
+    let to_quantities = |map: &BTreeMap<String, String>| {
+        if map.is_empty() {
+            None
+        } else {
+            Some(
+                map.iter()
+                    .map(|(k, v)| (k.clone(), Quantity(v.clone())))
+                    .collect(),
+            )
+        }
+    };
+
+    ResourceRequirements {
+        requests: to_quantities(&resources.requests),
+        limits: to_quantities(&resources.limits),
+        ..Default::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spec(commands: &[&str]) -> RunSpec {
+        RunSpec::new(
+            "run-1",
+            "test:latest",
+            commands.iter().map(|c| c.to_string()).collect(),
+            RunOrigin::Crd,
+        )
+    }
+
+    fn container(job: &Job) -> &Container {
+        &job.spec
+            .as_ref()
+            .unwrap()
+            .template
+            .spec
+            .as_ref()
+            .unwrap()
+            .containers[0]
+    }
+
+    #[test]
+    fn test_job_name() {
+        let run_id = uuid::Uuid::new_v4().to_string();
+        let job_name = job_name(&run_id);
+
+        assert!(job_name.starts_with("test-run-"));
+        assert_eq!(job_name.len(), 45); // "test-run-" (9) + UUID (36)
+    }
+
+    #[test]
+    fn test_build_job_single_command() {
+        let job = spec(&["echo hello"]).build_job();
+
+        assert_eq!(job.metadata.name, Some("test-run-run-1".to_string()));
+        let container = container(&job);
+        assert_eq!(container.image, Some("test:latest".to_string()));
+        assert_eq!(
+            container.command,
+            Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "echo hello".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_build_job_multiple_commands() {
+        let job = spec(&["echo hello", "echo world"]).build_job();
+
+        assert_eq!(
+            container(&job).command,
+            Some(vec![
+                "sh".to_string(),
+                "-c".to_string(),
+                "echo hello && echo world".to_string()
+            ])
+        );
+    }
+
+    #[test]
+    fn test_build_job_labels() {
+        let api_run = RunSpec {
+            definition_id: Some("def-1".to_string()),
+            ..RunSpec::new("run-2", "test:latest", vec![], RunOrigin::Api)
+        };
+        let job = api_run.build_job();
+
+        let labels = job.metadata.labels.as_ref().unwrap();
+        assert_eq!(labels["app"], "sparktest");
+        assert_eq!(labels["managed-by"], "sparktest-api");
+        assert_eq!(labels[RUN_ID_LABEL], "run-2");
+        assert_eq!(labels[DEFINITION_ID_LABEL], "def-1");
+        assert_eq!(labels[ORIGIN_LABEL], "api");
+
+        let pod_labels = job
+            .spec
+            .as_ref()
+            .unwrap()
+            .template
+            .metadata
+            .as_ref()
+            .unwrap()
+            .labels
+            .as_ref()
+            .unwrap();
+        assert_eq!(pod_labels["job-name"], "test-run-run-2");
+        assert_eq!(pod_labels[RUN_ID_LABEL], "run-2");
+
+        let crd_labels = spec(&["echo"]).labels();
+        assert_eq!(crd_labels["managed-by"], "sparktest-controller");
+        assert_eq!(crd_labels[ORIGIN_LABEL], "crd");
+        assert!(!crd_labels.contains_key(DEFINITION_ID_LABEL));
+    }
+
+    #[test]
+    fn test_build_job_with_env() {
+        let mut run = spec(&["echo $KEY1"]);
+        run.env.insert("KEY1".to_string(), "value1".to_string());
+        run.env.insert("KEY2".to_string(), "value2".to_string());
+        let job = run.build_job();
+
+        let env = container(&job).env.as_ref().unwrap();
+        assert_eq!(env.len(), 2);
+        assert!(env
+            .iter()
+            .any(|e| e.name == "KEY1" && e.value == Some("value1".to_string())));
+    }
+
+    #[test]
+    fn test_build_job_with_timeout() {
+        let run = RunSpec {
+            timeout_seconds: Some(60),
+            ..spec(&["sleep 100"])
+        };
+
+        assert_eq!(
+            run.build_job()
+                .spec
+                .as_ref()
+                .unwrap()
+                .active_deadline_seconds,
+            Some(60)
+        );
+    }
+
+    #[test]
+    fn test_build_job_with_ttl() {
+        let run = RunSpec {
+            ttl_seconds_after_finished: Some(3600),
+            ..spec(&["echo done"])
+        };
+
+        assert_eq!(
+            run.build_job()
+                .spec
+                .as_ref()
+                .unwrap()
+                .ttl_seconds_after_finished,
+            Some(3600)
+        );
+    }
+
+    #[test]
+    fn test_build_job_with_resources() {
+        let run = RunSpec {
+            resources: Some(RunResources {
+                requests: BTreeMap::from([("cpu".to_string(), "100m".to_string())]),
+                limits: BTreeMap::from([("memory".to_string(), "512Mi".to_string())]),
+            }),
+            ..spec(&["echo done"])
+        };
+        let job = run.build_job();
+
+        let requirements = container(&job).resources.as_ref().unwrap();
+        assert_eq!(
+            requirements.requests.as_ref().unwrap()["cpu"],
+            Quantity("100m".to_string())
+        );
+        assert_eq!(
+            requirements.limits.as_ref().unwrap()["memory"],
+            Quantity("512Mi".to_string())
+        );
+    }
+}
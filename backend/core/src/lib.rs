@@ -1,8 +1,12 @@
 pub mod db;
+pub mod job;
 pub mod models;
+pub mod schedule;
 
 pub use db::*;
+pub use job::*;
 pub use models::*;
+pub use schedule::*;
 
 #[cfg(test)]
 mod tests {
@@ -21,20 +25,14 @@ mod tests {
             created_at: Utc::now(),
             definition_id: None,
             executor_id: None,
-            suite_id: None,
-            variables: None,
-            artifacts: None,
+            schedule_id: None,
             duration: None,
-            retries: None,
             logs: None,
             k8s_job_name: None,
-            pod_scheduled: None,
-            container_created: None,
-            container_started: None,
-            completed: None,
-            failed: None,
             origin: RunOrigin::Api,
             k8s_ref: None,
+            created_by: None,
+            definition_version: None,
         };
 
         assert_eq!(test_run.name, "Test Run");
@@ -52,10 +50,15 @@ mod tests {
             "image": "test:latest",
             "commands": ["echo"],
             "status": "pending",
-            "created_at": "2025-01-01T00:00:00Z"
+            "createdAt": "2025-01-01T00:00:00Z"
         }"#;
         let run: TestRun = serde_json::from_str(json).unwrap();
         assert_eq!(run.origin, RunOrigin::Api);
+
+        let value = serde_json::to_value(&run).unwrap();
+        assert!(value.get("definitionId").is_some());
+        assert!(value.get("k8sJobName").is_some());
+        assert!(value.get("created_at").is_none());
     }
 
     #[test]
@@ -69,23 +72,17 @@ mod tests {
             created_at: Utc::now(),
             definition_id: None,
             executor_id: None,
-            suite_id: None,
-            variables: None,
-            artifacts: None,
+            schedule_id: None,
             duration: None,
-            retries: None,
             logs: None,
             k8s_job_name: None,
-            pod_scheduled: None,
-            container_created: None,
-            container_started: None,
-            completed: None,
-            failed: None,
             origin: RunOrigin::Crd,
             k8s_ref: Some(K8sRef {
                 namespace: "sparktest".to_string(),
                 name: "test-run-001".to_string(),
             }),
+            created_by: None,
+            definition_version: Some(2),
         };
 
         assert_eq!(test_run.origin, RunOrigin::Crd);
@@ -116,9 +113,11 @@ mod tests {
             image: "test:latest".to_string(),
             commands: vec!["echo".to_string(), "hello".to_string()],
             created_at: Utc::now(),
-            executor_id: Some("executor-1".to_string()),
-            labels: Some(vec!["test".to_string()]),
-            variables: None,
+            executor_id: Some(Uuid::new_v4()),
+            labels: vec!["test".to_string()],
+            created_by: None,
+            version: 1,
+            updated_at: Utc::now(),
         };
 
         assert_eq!(definition.name, "Test Definition");
@@ -126,21 +125,76 @@ mod tests {
         assert!(!definition.commands.is_empty());
     }
 
+    #[test]
+    fn test_definition_version_diff() {
+        let first = DefinitionVersion {
+            definition_id: Uuid::new_v4(),
+            version: 1,
+            name: "API tests".to_string(),
+            description: String::new(),
+            image: "node:18".to_string(),
+            commands: vec!["npm test".to_string()],
+            executor_id: None,
+            labels: vec!["api".to_string()],
+            created_at: Utc::now(),
+            created_by: None,
+            changes: Vec::new(),
+        };
+        let second = DefinitionVersion {
+            version: 2,
+            image: "node:20".to_string(),
+            commands: vec!["npm ci".to_string(), "npm test".to_string()],
+            ..first.clone()
+        };
+
+        let changes = second.diff(&first);
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].field, "image");
+        assert_eq!(changes[0].from, "node:18");
+        assert_eq!(changes[1].field, "commands");
+        assert_eq!(changes[1].to, serde_json::json!(["npm ci", "npm test"]));
+        assert!(first.diff(&first).is_empty());
+    }
+
     #[test]
     fn test_executor_creation() {
         let executor = Executor {
-            id: "executor-1".to_string(),
+            id: Uuid::new_v4(),
             name: "Test Executor".to_string(),
             description: Some("A test executor".to_string()),
             image: "test:latest".to_string(),
-            command: Some(vec!["echo".to_string()]),
-            supported_file_types: Some(vec!["json".to_string()]),
-            env: None,
-            created_at: Utc::now(),
+            default_command: Some("echo".to_string()),
+            supported_file_types: vec!["json".to_string()],
+            environment_variables: Vec::new(),
+            icon: None,
+            version: 1,
+            updated_at: Utc::now(),
         };
 
         assert_eq!(executor.name, "Test Executor");
         assert!(executor.description.is_some());
         assert_eq!(executor.image, "test:latest");
     }
+
+    #[test]
+    fn test_test_schedule_creation() {
+        let schedule = TestSchedule {
+            id: Uuid::new_v4(),
+            name: "Nightly API tests".to_string(),
+            cron_expression: "0 2 * * *".to_string(),
+            timezone: "Europe/Stockholm".to_string(),
+            definition_id: Some(Uuid::new_v4()),
+            suite_id: None,
+            concurrency_policy: ConcurrencyPolicy::Forbid,
+            successful_runs_history_limit: 3,
+            failed_runs_history_limit: 1,
+            enabled: true,
+            last_run_at: None,
+            next_run_at: None,
+            created_at: Utc::now(),
+        };
+
+        assert!(CronSchedule::parse(&schedule.cron_expression, Some(&schedule.timezone)).is_ok());
+        assert_eq!(schedule.concurrency_policy, ConcurrencyPolicy::Forbid);
+    }
 }
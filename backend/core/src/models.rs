@@ -1,8 +1,10 @@
+use crate::schedule::ConcurrencyPolicy;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Default)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum RunOrigin {
     #[default]
@@ -10,40 +12,56 @@ pub enum RunOrigin {
     Crd,
 }
 
+impl RunOrigin {
+    /// Name as stored in the `run_origin` database enum
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            RunOrigin::Api => "api",
+            RunOrigin::Crd => "crd",
+        }
+    }
+}
+
+/// The `TestRun` resource a run was created from
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
 pub struct K8sRef {
     pub namespace: String,
     pub name: String,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct TestRun {
     pub id: Uuid,
     pub name: String,
     pub image: String,
     pub commands: Vec<String>,
+    /// `running`, `succeeded` or `failed`
     pub status: String,
     pub created_at: DateTime<Utc>,
     pub definition_id: Option<Uuid>,
-    pub executor_id: Option<String>,
-    pub suite_id: Option<Uuid>,
-    pub variables: Option<serde_json::Value>,
-    pub artifacts: Option<Vec<String>>,
+    pub executor_id: Option<Uuid>,
+    #[serde(default)]
+    pub schedule_id: Option<Uuid>,
+    /// Seconds the job ran for, once it finished
     pub duration: Option<i32>,
-    pub retries: Option<i32>,
     pub logs: Option<Vec<String>>,
+    /// Kubernetes job of an `api` run; `crd` runs are submitted by the controller
     pub k8s_job_name: Option<String>,
-    pub pod_scheduled: Option<DateTime<Utc>>,
-    pub container_created: Option<DateTime<Utc>>,
-    pub container_started: Option<DateTime<Utc>>,
-    pub completed: Option<DateTime<Utc>>,
-    pub failed: Option<DateTime<Utc>>,
     #[serde(default)]
     pub origin: RunOrigin,
     pub k8s_ref: Option<K8sRef>,
+    pub created_by: Option<String>,
+    /// Version of the definition the run executed
+    #[serde(default)]
+    pub definition_version: Option<i32>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct TestDefinition {
     pub id: Uuid,
     pub name: String,
@@ -51,30 +69,154 @@ pub struct TestDefinition {
     pub image: String,
     pub commands: Vec<String>,
     pub created_at: DateTime<Utc>,
-    pub executor_id: Option<String>,
-    pub variables: Option<serde_json::Value>,
-    pub labels: Option<Vec<String>>,
+    pub executor_id: Option<Uuid>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub created_by: Option<String>,
+    /// Bumped by every update; sent as the `ETag`
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A test definition as it was at one version; versions are never changed
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct DefinitionVersion {
+    pub definition_id: Uuid,
+    pub version: i32,
+    pub name: String,
+    pub description: String,
+    pub image: String,
+    pub commands: Vec<String>,
+    pub executor_id: Option<Uuid>,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    /// When the version was made, by creating, updating or rolling back the definition
+    pub created_at: DateTime<Utc>,
+    pub created_by: Option<String>,
+    /// Fields that differ from the previous version; empty for the first
+    #[serde(default)]
+    pub changes: Vec<FieldChange>,
+}
+
+/// One field that differs between two versions
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub struct FieldChange {
+    pub field: String,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub from: serde_json::Value,
+    #[cfg_attr(feature = "utoipa", schema(value_type = Object))]
+    pub to: serde_json::Value,
+}
+
+impl DefinitionVersion {
+    /// Fields that changed from `previous` to this version, by their JSON names
+    pub fn diff(&self, previous: &DefinitionVersion) -> Vec<FieldChange> {
+        let fields = [
+            ("name", to_value(&previous.name), to_value(&self.name)),
+            (
+                "description",
+                to_value(&previous.description),
+                to_value(&self.description),
+            ),
+            ("image", to_value(&previous.image), to_value(&self.image)),
+            (
+                "commands",
+                to_value(&previous.commands),
+                to_value(&self.commands),
+            ),
+            (
+                "executorId",
+                to_value(&previous.executor_id),
+                to_value(&self.executor_id),
+            ),
+            ("labels", to_value(&previous.labels), to_value(&self.labels)),
+        ];
+
+        fields
+            .into_iter()
+            .filter(|(_, from, to)| from != to)
+            .map(|(field, from, to)| FieldChange {
+                field: field.to_string(),
+                from,
+                to,
+            })
+            .collect()
+    }
+}
+
+fn to_value<T: Serialize>(value: &T) -> serde_json::Value {
+    serde_json::to_value(value).unwrap_or_default()
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct Executor {
-    pub id: String,
+    pub id: Uuid,
     pub name: String,
     pub image: String,
     pub description: Option<String>,
-    pub command: Option<Vec<String>>,
-    pub supported_file_types: Option<Vec<String>>,
-    pub env: Option<serde_json::Value>,
-    pub created_at: DateTime<Utc>,
+    /// Shell command run when a test does not give its own
+    pub default_command: Option<String>,
+    #[serde(default)]
+    pub supported_file_types: Vec<String>,
+    /// Names of the environment variables the executor reads
+    #[serde(default)]
+    pub environment_variables: Vec<String>,
+    pub icon: Option<String>,
+    /// Bumped by every update; sent as the `ETag`
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
 pub struct TestSuite {
     pub id: Uuid,
     pub name: String,
     pub description: String,
-    pub test_definition_ids: Vec<Uuid>,
+    pub definition_ids: Vec<Uuid>,
     pub created_at: DateTime<Utc>,
+    /// `sequential` or `parallel`
+    pub execution_mode: String,
+    #[serde(default)]
+    pub labels: Vec<String>,
+    pub created_by: Option<String>,
+    /// Bumped by every update; sent as the `ETag`
+    pub version: i32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// The runs started for a suite, one per definition
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct SuiteRun {
+    pub suite_id: Uuid,
+    pub suite_name: String,
     pub execution_mode: String,
-    pub labels: Option<Vec<String>>,
+    pub runs: Vec<TestRun>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+#[serde(rename_all = "camelCase")]
+pub struct TestSchedule {
+    pub id: Uuid,
+    pub name: String,
+    pub cron_expression: String,
+    pub timezone: String,
+    pub definition_id: Option<Uuid>,
+    pub suite_id: Option<Uuid>,
+    pub concurrency_policy: ConcurrencyPolicy,
+    pub successful_runs_history_limit: i32,
+    pub failed_runs_history_limit: i32,
+    pub enabled: bool,
+    pub last_run_at: Option<DateTime<Utc>>,
+    pub next_run_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
 }
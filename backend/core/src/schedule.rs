@@ -0,0 +1,163 @@
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use chrono_tz::Tz;
+use serde::{Deserialize, Serialize};
+use std::str::FromStr;
+
+/// How far back missed schedule times are still honoured
+const MAX_CATCH_UP: i64 = 24;
+
+/// What to do when a schedule fires while a previous run is still active
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "schemars", derive(schemars::JsonSchema))]
+#[cfg_attr(feature = "utoipa", derive(utoipa::ToSchema))]
+pub enum ConcurrencyPolicy {
+    /// Start the new run alongside the active ones
+    #[default]
+    Allow,
+    /// Skip the new run while another one is active
+    Forbid,
+    /// Cancel the active runs and start the new one
+    Replace,
+}
+
+impl ConcurrencyPolicy {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ConcurrencyPolicy::Allow => "Allow",
+            ConcurrencyPolicy::Forbid => "Forbid",
+            ConcurrencyPolicy::Replace => "Replace",
+        }
+    }
+}
+
+impl FromStr for ConcurrencyPolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "Allow" => Ok(ConcurrencyPolicy::Allow),
+            "Forbid" => Ok(ConcurrencyPolicy::Forbid),
+            "Replace" => Ok(ConcurrencyPolicy::Replace),
+            other => Err(anyhow!("Unknown concurrency policy: {other}")),
+        }
+    }
+}
+
+/// A parsed cron expression evaluated in a timezone
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    schedule: cron::Schedule,
+    timezone: Tz,
+}
+
+impl CronSchedule {
+    /// Parse a cron expression and an optional IANA timezone (defaults to UTC)
+    ///
+    /// Standard five-field expressions (`*/5 * * * *`) are accepted as well as
+    /// the six/seven-field form with seconds.
+    pub fn parse(expression: &str, timezone: Option<&str>) -> Result<Self> {
+        let expression = expression.trim();
+        let normalized = if expression.split_whitespace().count() == 5 {
+            format!("0 {expression}")
+        } else {
+            expression.to_string()
+        };
+
+        let schedule = cron::Schedule::from_str(&normalized)
+            .map_err(|e| anyhow!("Invalid cron expression '{expression}': {e}"))?;
+
+        let timezone = match timezone {
+            Some(tz) => tz
+                .parse::<Tz>()
+                .map_err(|e| anyhow!("Invalid timezone '{tz}': {e}"))?,
+            None => Tz::UTC,
+        };
+
+        Ok(Self { schedule, timezone })
+    }
+
+    /// First scheduled time strictly after `after`
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.schedule
+            .after(&after.with_timezone(&self.timezone))
+            .next()
+            .map(|t| t.with_timezone(&Utc))
+    }
+
+    /// Most recent scheduled time in `(since, now]`, if any
+    ///
+    /// Only the latest missed time is returned, and times older than a day are
+    /// ignored, so a controller that was down does not replay a backlog of runs.
+    pub fn latest_due(&self, since: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let since = since.max(now - Duration::hours(MAX_CATCH_UP));
+
+        self.schedule
+            .after(&since.with_timezone(&self.timezone))
+            .map(|t| t.with_timezone(&Utc))
+            .take_while(|t| *t <= now)
+            .last()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_five_field_expression() {
+        let schedule = CronSchedule::parse("*/15 * * * *", None).unwrap();
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 10, 7, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(after),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 10, 15, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_input() {
+        assert!(CronSchedule::parse("not a cron", None).is_err());
+        assert!(CronSchedule::parse("0 * * * *", Some("Mars/Olympus")).is_err());
+    }
+
+    #[test]
+    fn test_next_after_respects_timezone() {
+        // 02:00 in Stockholm is 01:00 UTC in winter
+        let schedule = CronSchedule::parse("0 2 * * *", Some("Europe/Stockholm")).unwrap();
+        let after = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(after),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 1, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_latest_due_returns_most_recent_missed_time() {
+        let schedule = CronSchedule::parse("0 * * * *", None).unwrap();
+        let since = Utc.with_ymd_and_hms(2025, 1, 1, 8, 30, 0).unwrap();
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 11, 10, 0).unwrap();
+        assert_eq!(
+            schedule.latest_due(since, now),
+            Some(Utc.with_ymd_and_hms(2025, 1, 1, 11, 0, 0).unwrap())
+        );
+
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 8, 45, 0).unwrap();
+        assert_eq!(schedule.latest_due(since, now), None);
+    }
+
+    #[test]
+    fn test_concurrency_policy_round_trip() {
+        for policy in [
+            ConcurrencyPolicy::Allow,
+            ConcurrencyPolicy::Forbid,
+            ConcurrencyPolicy::Replace,
+        ] {
+            assert_eq!(
+                policy.as_str().parse::<ConcurrencyPolicy>().unwrap(),
+                policy
+            );
+        }
+        assert!("Sometimes".parse::<ConcurrencyPolicy>().is_err());
+    }
+}
@@ -0,0 +1,30 @@
+-- Migration to add scheduled test runs
+-- A schedule targets either a test definition or a test suite and fires on a cron expression
+
+CREATE TABLE test_schedules (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    cron_expression TEXT NOT NULL,
+    timezone TEXT NOT NULL DEFAULT 'UTC',
+    test_definition_id UUID REFERENCES test_definitions(id) ON DELETE CASCADE,
+    test_suite_id UUID REFERENCES test_suites(id) ON DELETE CASCADE,
+    concurrency_policy TEXT NOT NULL DEFAULT 'Allow' CHECK (concurrency_policy IN ('Allow', 'Forbid', 'Replace')),
+    successful_runs_history_limit INTEGER NOT NULL DEFAULT 3 CHECK (successful_runs_history_limit >= 0),
+    failed_runs_history_limit INTEGER NOT NULL DEFAULT 1 CHECK (failed_runs_history_limit >= 0),
+    enabled BOOLEAN NOT NULL DEFAULT TRUE,
+    last_run_at TIMESTAMPTZ,
+    next_run_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    -- Exactly one target must be set
+    CHECK ((test_definition_id IS NULL) <> (test_suite_id IS NULL))
+);
+
+-- Record which schedule produced a run
+ALTER TABLE test_runs
+ADD COLUMN schedule_id UUID REFERENCES test_schedules(id) ON DELETE SET NULL;
+
+-- Add index for the scheduler's due-schedule lookup
+CREATE INDEX idx_test_schedules_next_run_at ON test_schedules(next_run_at) WHERE enabled;
+
+-- Add index for listing and pruning runs of a schedule
+CREATE INDEX idx_test_runs_schedule_id ON test_runs(schedule_id) WHERE schedule_id IS NOT NULL;
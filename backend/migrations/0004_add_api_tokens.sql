@@ -0,0 +1,15 @@
+-- Migration to add API tokens for bearer authentication
+-- Only a SHA-256 hash of each token is stored; the token itself is returned once, on creation
+
+CREATE TABLE api_tokens (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name TEXT NOT NULL,
+    token_hash TEXT NOT NULL UNIQUE,
+    -- First characters of the token, to tell tokens apart when listing them
+    token_prefix TEXT NOT NULL,
+    scopes TEXT[] NOT NULL DEFAULT '{read}' CHECK (scopes <@ ARRAY['read', 'write', 'admin']),
+    expires_at TIMESTAMPTZ,
+    last_used_at TIMESTAMPTZ,
+    revoked_at TIMESTAMPTZ,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
@@ -0,0 +1,9 @@
+-- Migration to add role-based authorization
+-- A subject is an API token ("token:<id>"); each has at most one role
+
+CREATE TABLE role_assignments (
+    subject TEXT PRIMARY KEY,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'runner', 'editor', 'admin')),
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
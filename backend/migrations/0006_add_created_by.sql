@@ -0,0 +1,6 @@
+-- Migration to record who created runs, definitions and suites
+-- Holds the OIDC username, "token:<name>" for API tokens or "controller"; NULL when anonymous
+
+ALTER TABLE test_runs ADD COLUMN created_by TEXT;
+ALTER TABLE test_definitions ADD COLUMN created_by TEXT;
+ALTER TABLE test_suites ADD COLUMN created_by TEXT;
@@ -0,0 +1,21 @@
+-- Migration to add an audit log of mutating API requests
+-- Rows are stored as they were before and after the request, with the fields that changed
+
+CREATE TABLE audit_events (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    -- Same as created_by: the username, "token:<name>" or "controller"; NULL when anonymous
+    actor TEXT,
+    action TEXT NOT NULL CHECK (action IN ('create', 'update', 'delete', 'run')),
+    entity_type TEXT NOT NULL,
+    entity_id TEXT,
+    before JSONB,
+    after JSONB,
+    changes JSONB,
+    request_id TEXT NOT NULL,
+    status_code INTEGER NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX audit_events_entity_idx ON audit_events (entity_type, entity_id, created_at DESC);
+CREATE INDEX audit_events_actor_idx ON audit_events (actor, created_at DESC);
+CREATE INDEX audit_events_created_at_idx ON audit_events (created_at DESC);
@@ -0,0 +1,44 @@
+-- Migration to add projects, which own executors, definitions, suites, schedules and runs
+-- Existing rows, and rows created through the unscoped routes, belong to the "default" project
+
+CREATE TABLE projects (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    slug TEXT NOT NULL UNIQUE CHECK (slug ~ '^[a-z0-9]([-a-z0-9]*[a-z0-9])?$'),
+    name TEXT NOT NULL,
+    description TEXT,
+    -- Namespace the project's Kubernetes jobs run in
+    k8s_namespace TEXT NOT NULL DEFAULT 'default',
+    created_by TEXT,
+    created_at TIMESTAMPTZ DEFAULT NOW()
+);
+
+INSERT INTO projects (id, slug, name, description)
+VALUES ('00000000-0000-0000-0000-000000000001', 'default', 'Default', 'Project of the unscoped /api routes');
+
+ALTER TABLE test_executors
+ADD COLUMN project_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE test_definitions
+ADD COLUMN project_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE test_suites
+ADD COLUMN project_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE test_schedules
+ADD COLUMN project_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE test_runs
+ADD COLUMN project_id UUID NOT NULL DEFAULT '00000000-0000-0000-0000-000000000001' REFERENCES projects(id) ON DELETE CASCADE;
+ALTER TABLE audit_events
+ADD COLUMN project_id UUID REFERENCES projects(id) ON DELETE SET NULL;
+
+CREATE INDEX test_executors_project_idx ON test_executors (project_id);
+CREATE INDEX test_definitions_project_idx ON test_definitions (project_id, created_at DESC);
+CREATE INDEX test_suites_project_idx ON test_suites (project_id, created_at DESC);
+CREATE INDEX test_schedules_project_idx ON test_schedules (project_id, created_at DESC);
+CREATE INDEX test_runs_project_idx ON test_runs (project_id, created_at DESC);
+
+-- Roles within a single project, for the same subjects as role_assignments
+CREATE TABLE project_members (
+    project_id UUID NOT NULL REFERENCES projects(id) ON DELETE CASCADE,
+    subject TEXT NOT NULL,
+    role TEXT NOT NULL CHECK (role IN ('viewer', 'runner', 'editor', 'admin')),
+    created_at TIMESTAMPTZ DEFAULT NOW(),
+    PRIMARY KEY (project_id, subject)
+);
@@ -0,0 +1,17 @@
+-- Migration to index the keyset order and filters of the paginated list endpoints
+-- Pages continue after the (created_at, id) or (name, id) of the previous page's last row
+
+DROP INDEX test_executors_project_idx;
+DROP INDEX test_definitions_project_idx;
+DROP INDEX test_suites_project_idx;
+DROP INDEX test_runs_project_idx;
+
+CREATE INDEX test_executors_project_idx ON test_executors (project_id, name, id);
+CREATE INDEX test_definitions_project_idx ON test_definitions (project_id, created_at DESC, id DESC);
+CREATE INDEX test_suites_project_idx ON test_suites (project_id, created_at DESC, id DESC);
+CREATE INDEX test_runs_project_idx ON test_runs (project_id, created_at DESC, id DESC);
+
+CREATE INDEX test_runs_status_idx ON test_runs (project_id, status, created_at DESC);
+CREATE INDEX test_definitions_labels_idx ON test_definitions USING GIN (labels);
+CREATE INDEX test_suites_labels_idx ON test_suites USING GIN (labels);
+CREATE INDEX test_suites_definitions_idx ON test_suites USING GIN (test_definition_ids);
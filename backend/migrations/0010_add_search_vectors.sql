@@ -0,0 +1,29 @@
+-- Migration to add full-text search over definitions, suites and runs
+-- The 'simple' configuration keeps command tokens such as `test:api` as written instead of stemming them
+
+-- array_to_string is only STABLE, which generated columns do not accept
+CREATE FUNCTION search_text(parts TEXT[]) RETURNS TEXT
+    LANGUAGE sql IMMUTABLE PARALLEL SAFE
+    AS $$ SELECT coalesce(array_to_string(parts, ' '), '') $$;
+
+ALTER TABLE test_definitions ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(description, '')), 'B') ||
+    setweight(to_tsvector('simple', search_text(commands)), 'C')
+) STORED;
+
+ALTER TABLE test_suites ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('simple', coalesce(description, '')), 'B')
+) STORED;
+
+-- Logs are cut off at 256 KiB so long runs stay within the tsvector size limit
+ALTER TABLE test_runs ADD COLUMN search_vector TSVECTOR GENERATED ALWAYS AS (
+    setweight(to_tsvector('simple', coalesce(name, '')), 'A') ||
+    setweight(to_tsvector('simple', search_text(command)), 'B') ||
+    setweight(to_tsvector('simple', left(search_text(logs), 262144)), 'D')
+) STORED;
+
+CREATE INDEX test_definitions_search_idx ON test_definitions USING GIN (search_vector);
+CREATE INDEX test_suites_search_idx ON test_suites USING GIN (search_vector);
+CREATE INDEX test_runs_search_idx ON test_runs USING GIN (search_vector);
@@ -0,0 +1,14 @@
+-- Migration to version definitions, executors and suites for optimistic concurrency
+-- Every update bumps `version`, which the API sends as the ETag and checks against If-Match
+
+ALTER TABLE test_definitions
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+ALTER TABLE test_executors
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
+
+ALTER TABLE test_suites
+    ADD COLUMN version INTEGER NOT NULL DEFAULT 1,
+    ADD COLUMN updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW();
@@ -0,0 +1,24 @@
+-- Migration to keep every version of a test definition
+-- Each create, update or rollback records the definition as a new, immutable version,
+-- and runs record the version they executed
+
+CREATE TABLE test_definition_versions (
+    definition_id UUID NOT NULL REFERENCES test_definitions(id) ON DELETE CASCADE,
+    version INTEGER NOT NULL,
+    name TEXT NOT NULL,
+    description TEXT,
+    image TEXT NOT NULL,
+    commands TEXT[] NOT NULL,
+    executor_id UUID,
+    labels TEXT[] NOT NULL DEFAULT '{}',
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    created_by TEXT,
+    PRIMARY KEY (definition_id, version)
+);
+
+-- Earlier versions were overwritten, so the current one is all there is to keep
+INSERT INTO test_definition_versions (definition_id, version, name, description, image, commands, executor_id, labels, created_at, created_by)
+SELECT id, version, name, description, image, commands, executor_id, COALESCE(labels, '{}'), updated_at, created_by
+FROM test_definitions;
+
+ALTER TABLE test_runs ADD COLUMN definition_version INTEGER;
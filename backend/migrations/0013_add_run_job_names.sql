@@ -0,0 +1,6 @@
+-- Migration to record the Job a run executes in
+-- For CRD-origin runs this is the Job the controller created; older API runs
+-- keep deriving their job name from their id
+
+ALTER TABLE test_runs
+ADD COLUMN k8s_job_name TEXT;